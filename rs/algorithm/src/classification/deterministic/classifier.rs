@@ -1,22 +1,28 @@
 use shared::{
-    preprocessing::compare::compare,
+    preprocessing::{
+        compare::{align, similarity, similarity_bound},
+        variable::mask_tokens,
+    },
     types::{
         class::Class,
         classifier::{error::ClassifierError, state::ClassifierState},
         record::{classified::ClassifiedLogRecord, preprocessed::PreprocessedLogRecord},
         tokenizer::Tokenizer,
     },
-    RedisConnection,
+    RedisConnection, StateCache, StateChange,
 };
-use std::env::var;
 
-const DEFAULT_THRESHOLD: f64 = 0.6;
+use crate::classification::explain::{agreement, Candidate, Explanation};
+use crate::classification::lifecycle::Lifecycle;
+use crate::classification::log_classifier::{state_cache_from_env, LogClassifier};
+use crate::classification::threshold::ThresholdPolicy;
 
 // #[derive(Clone)]
 pub struct Classifier {
-    threshold: f64,
-    redis: RedisConnection,
+    thresholds: ThresholdPolicy,
+    cache: StateCache,
     tokenizer: Tokenizer,
+    lifecycle: Lifecycle,
 }
 
 impl Classifier {
@@ -26,37 +32,82 @@ impl Classifier {
         threshold: Option<f64>,
         redis: RedisConnection,
     ) -> Result<Classifier, ClassifierError> {
-        let threshold = threshold.unwrap_or(
-            var("CLASSIFIER_THRESHOLD")
-                .unwrap_or(DEFAULT_THRESHOLD.to_string())
-                .parse::<f64>()?,
-        );
+        Self::with_cache(threshold, state_cache_from_env(redis)?)
+    }
+
+    // The `with_cache` function creates a classifier on the given state cache.
+    pub fn with_cache(
+        threshold: Option<f64>,
+        cache: StateCache,
+    ) -> Result<Classifier, ClassifierError> {
+        let thresholds = ThresholdPolicy::from_env(threshold)?;
         let tokenizer = Tokenizer::new()?;
+        let lifecycle = Lifecycle::from_env()?;
         Ok(Classifier {
-            threshold,
-            redis,
+            thresholds,
+            cache,
             tokenizer,
+            lifecycle,
         })
     }
 
-    pub fn classify(
+    fn new_class(
+        tokenizer: &Tokenizer,
+        log: &PreprocessedLogRecord,
+        state: &mut ClassifierState,
+    ) -> (Option<Class>, ClassifiedLogRecord) {
+        let mut class = Class::from_log_and_token_count(log, 0);
+        class.token_count = Self::token_count(tokenizer, &class);
+        state.classes.push(class.to_owned());
+        // always return new class
+        (Some(class.clone()), ClassifiedLogRecord::new(log, &class))
+    }
+    fn get_class(tokenizer: &Tokenizer, class: &mut Class, return_none: bool) -> Option<Class> {
+        match return_none {
+            true => None,
+            false => {
+                class.token_count = Self::token_count(tokenizer, class);
+                Some(class.clone())
+            }
+        }
+    }
+
+    fn token_count(tokenizer: &Tokenizer, class: &Class) -> u32 {
+        tokenizer.calculate_token_length(class.to_string().as_str()) as u32
+    }
+}
+
+impl LogClassifier for Classifier {
+    fn classify(
         &mut self,
         log: &PreprocessedLogRecord,
         db: &str,
     ) -> Result<(Option<Class>, ClassifiedLogRecord), ClassifierError> {
         let mut best_match: Option<&mut Class> = None;
         let mut highest_similarity = 0 as f64;
-        let key = self.redis.key(db, None, &log.key);
-        let state = &mut self.redis.get(&key)?;
+        let key = self.cache.key(db, Some(&log.namespace), &log.workload);
+        // states were kept per pod before, their classes are moved to the workload
+        let legacy_key = self.cache.key(db, None, &log.key);
+        let migrated = self.cache.migrate(&key, &legacy_key, &log.workload)?;
+        self.lifecycle.record(migrated);
+        let state = self.cache.get_mut(&key)?;
+        let configured = state
+            .thresholds
+            .get(&log.container)
+            .map(|threshold| threshold.configured);
+        let threshold = self
+            .thresholds
+            .threshold(db, &log.namespace, &log.container, state);
+        let masked_message = mask_tokens(&log.preprocessed_message);
 
         for class in state.classes.iter_mut() {
-            if class.length != log.length as usize {
+            // skip classes that cannot reach the threshold or beat the current best match
+            let bound = similarity_bound(log.length as usize, class.length);
+            if bound < threshold || bound <= highest_similarity {
                 continue;
             }
-            let aggrement = compare(&log.preprocessed_message, &class.mask_items());
-
-            let similarity =
-                aggrement.iter().filter(|&b| *b).count() as f64 / aggrement.len() as f64;
+            let alignment = align(&masked_message, &class.mask_items());
+            let similarity = similarity(&alignment);
             if similarity > highest_similarity {
                 highest_similarity = similarity;
                 best_match = Some(class);
@@ -64,49 +115,88 @@ impl Classifier {
         }
         let result = match best_match {
             Some(class) => {
-                if highest_similarity >= self.threshold {
+                if highest_similarity >= threshold {
                     let previous_class = class.clone();
-                    class.count += 1;
                     class.update_items(log);
+                    class.count += 1;
+                    class.seen(log.timestamp);
                     class.similarity = highest_similarity;
                     // TODO: return class if representation changed
-                    let identical = previous_class.to_string() == class.to_string();
+                    // the severity is stored with the vector, a change is an update as well
+                    let identical = previous_class.to_string() == class.to_string()
+                        && previous_class.severity == class.severity;
                     let classified_log = ClassifiedLogRecord::new(log, class);
-                    (self.get_class(class, identical), classified_log)
+                    (
+                        Self::get_class(&self.tokenizer, class, identical),
+                        classified_log,
+                    )
                 } else {
-                    self.new_class(log, state)
+                    Self::new_class(&self.tokenizer, log, state)
                 }
             }
-            None => self.new_class(log, state),
+            None => Self::new_class(&self.tokenizer, log, state),
         };
-        self.redis.set(&key, state.to_owned())?;
+        let created = matches!(&result.0, Some(class) if class.count == 1);
+        let adapted = self
+            .thresholds
+            .observe(&key, &log.container, state, created);
+        let removed = self
+            .lifecycle
+            .apply(&key, state, threshold, &self.tokenizer);
+        let reset = configured
+            != state
+                .thresholds
+                .get(&log.container)
+                .map(|threshold| threshold.configured);
+        let change = match result.0.is_some() || adapted || removed || reset {
+            true => StateChange::Classes,
+            false => StateChange::Counts,
+        };
+        self.cache.release(&key, change)?;
         Ok(result)
     }
 
-    fn new_class(
-        &self,
+    fn explain(
+        &mut self,
         log: &PreprocessedLogRecord,
-        state: &mut ClassifierState,
-    ) -> (Option<Class>, ClassifiedLogRecord) {
-        let mut class = Class::from_log_and_token_count(log, 0);
-        class.token_count = self.token_count(&class);
-        state.classes.push(class.to_owned());
-        // always return new class
-        (Some(class.clone()), ClassifiedLogRecord::new(log, &class))
+        db: &str,
+    ) -> Result<Explanation, ClassifierError> {
+        let key = self.cache.key(db, Some(&log.namespace), &log.workload);
+        let mut state = self.cache.peek(&key)?;
+        let threshold = self
+            .thresholds
+            .threshold(db, &log.namespace, &log.container, &mut state);
+        let masked_message = mask_tokens(&log.preprocessed_message);
+
+        // unlike `classify`, classes are not skipped by their similarity bound
+        let candidates = state
+            .classes
+            .iter()
+            .map(|class| {
+                let alignment = align(&masked_message, &class.mask_items());
+                let agreement = agreement(&alignment, masked_message.len());
+                Candidate::new(class, agreement, similarity(&alignment))
+            })
+            .collect();
+        Ok(Explanation::new(
+            key,
+            threshold,
+            log,
+            masked_message,
+            candidates,
+        ))
     }
-    fn get_class(&self, class: &mut Class, return_none: bool) -> Option<Class> {
-        match return_none {
-            true => None,
-            false => {
-                class.token_count = self.token_count(class);
-                Some(class.clone())
-            }
-        }
+
+    fn states(&self) -> Vec<(&str, &ClassifierState)> {
+        self.cache.states()
+    }
+
+    fn take_updates(&mut self) -> Vec<Class> {
+        self.lifecycle.take_updates()
     }
 
-    fn token_count(&self, class: &Class) -> u32 {
-        self.tokenizer
-            .calculate_token_length(class.to_string().as_str()) as u32
+    fn flush(&mut self) -> Result<(), ClassifierError> {
+        Ok(self.cache.flush()?)
     }
 }
 
@@ -114,6 +204,7 @@ impl Classifier {
 mod tests {
 
     use super::Classifier;
+    use crate::classification::log_classifier::LogClassifier;
     use rstest::rstest;
     use shared::{
         setup_tracing,
@@ -126,6 +217,7 @@ mod tests {
     #[rstest]
     #[case(get_test_data(TestCase::Simple))]
     #[case(get_test_data(TestCase::Short))]
+    #[case(get_test_data(TestCase::OptionalToken))]
     async fn test_classify_json_nested(#[case] test_data: TestData) -> Result<(), ClassifierError> {
         setup_tracing(false);
 
@@ -0,0 +1,303 @@
+use std::collections::HashMap;
+use std::env::var;
+
+use shared::{
+    preprocessing::{compare::compare, variable::mask_tokens},
+    types::{
+        class::Class,
+        classifier::{error::ClassifierError, state::ClassifierState},
+        record::{classified::ClassifiedLogRecord, preprocessed::PreprocessedLogRecord},
+        tokenizer::Tokenizer,
+    },
+    RedisConnection, StateCache, StateChange,
+};
+
+use crate::classification::explain::{Candidate, Explanation};
+use crate::classification::lifecycle::Lifecycle;
+use crate::classification::log_classifier::{state_cache_from_env, LogClassifier};
+use crate::classification::threshold::ThresholdPolicy;
+
+use super::tree::PrefixTree;
+
+const DEFAULT_DEPTH: usize = 4;
+const DEFAULT_MAX_CHILDREN: usize = 100;
+
+pub struct DrainClassifier {
+    thresholds: ThresholdPolicy,
+    depth: usize,
+    max_children: usize,
+    cache: StateCache,
+    tokenizer: Tokenizer,
+    // parse trees by redis key with the version of the state they were built from, rebuilt when
+    // the state was written or changed elsewhere
+    trees: HashMap<String, (u64, PrefixTree)>,
+    lifecycle: Lifecycle,
+}
+
+impl DrainClassifier {
+    pub fn new(
+        threshold: Option<f64>,
+        redis: RedisConnection,
+    ) -> Result<DrainClassifier, ClassifierError> {
+        Self::with_cache(threshold, state_cache_from_env(redis)?)
+    }
+
+    // The `with_cache` function creates a classifier on the given state cache.
+    pub fn with_cache(
+        threshold: Option<f64>,
+        cache: StateCache,
+    ) -> Result<DrainClassifier, ClassifierError> {
+        let thresholds = ThresholdPolicy::from_env(threshold)?;
+        let depth = var("CLASSIFIER_DRAIN_DEPTH")
+            .unwrap_or(DEFAULT_DEPTH.to_string())
+            .parse::<usize>()?;
+        let max_children = var("CLASSIFIER_DRAIN_MAX_CHILDREN")
+            .unwrap_or(DEFAULT_MAX_CHILDREN.to_string())
+            .parse::<usize>()?;
+        let tokenizer = Tokenizer::new()?;
+        let lifecycle = Lifecycle::from_env()?;
+        Ok(DrainClassifier {
+            thresholds,
+            depth,
+            max_children,
+            cache,
+            tokenizer,
+            trees: HashMap::new(),
+            lifecycle,
+        })
+    }
+
+    fn token_count(tokenizer: &Tokenizer, class: &Class) -> u32 {
+        tokenizer.calculate_token_length(class.to_string().as_str()) as u32
+    }
+}
+
+impl LogClassifier for DrainClassifier {
+    fn classify(
+        &mut self,
+        log: &PreprocessedLogRecord,
+        db: &str,
+    ) -> Result<(Option<Class>, ClassifiedLogRecord), ClassifierError> {
+        let key = self.cache.key(db, Some(&log.namespace), &log.workload);
+        // states were kept per pod before, their classes are moved to the workload
+        let legacy_key = self.cache.key(db, None, &log.key);
+        let migrated = self.cache.migrate(&key, &legacy_key, &log.workload)?;
+        if !migrated.is_empty() {
+            self.trees.remove(&key);
+        }
+        self.lifecycle.record(migrated);
+        let state = self.cache.get_mut(&key)?;
+        let configured = state
+            .thresholds
+            .get(&log.container)
+            .map(|threshold| threshold.configured);
+        let threshold = self
+            .thresholds
+            .threshold(db, &log.namespace, &log.container, state);
+        let masked_message = mask_tokens(&log.preprocessed_message);
+
+        if self.trees.get(&key).map(|(version, _)| *version) != Some(state.version) {
+            let mut tree = PrefixTree::new(self.depth, self.max_children);
+            for class in &state.classes {
+                tree.insert(&class.mask_items(), &class.class_id);
+            }
+            self.trees.insert(key.clone(), (state.version, tree));
+        }
+        let candidates = self.trees[&key].1.search(&masked_message);
+
+        let mut best_match: Option<(usize, f64)> = None;
+        for (index, class) in state.classes.iter().enumerate() {
+            if class.length != masked_message.len() || !candidates.contains(&class.class_id) {
+                continue;
+            }
+            let agreement = compare(&masked_message, &class.mask_items());
+            let similarity =
+                agreement.iter().filter(|&b| *b).count() as f64 / agreement.len().max(1) as f64;
+            match best_match {
+                Some((_, highest)) if highest >= similarity => {}
+                _ => best_match = Some((index, similarity)),
+            }
+        }
+
+        let result = match best_match {
+            Some((index, similarity)) if similarity >= threshold => {
+                let class = &mut state.classes[index];
+                // the severity is stored with the vector, a change is an update as well
+                let previous = (class.to_string(), class.severity);
+                // templates of a leaf share the token count, so updates are positional
+                class.update_items_positional(log);
+                class.count += 1;
+                class.seen(log.timestamp);
+                class.similarity = similarity;
+                let classified_log = ClassifiedLogRecord::new(log, class);
+                if previous == (class.to_string(), class.severity) {
+                    (None, classified_log)
+                } else {
+                    class.token_count = Self::token_count(&self.tokenizer, class);
+                    // a prefix token that became a variable moves the class to another leaf
+                    if let Some((_, tree)) = self.trees.get_mut(&key) {
+                        tree.remove(&class.class_id);
+                        tree.insert(&class.mask_items(), &class.class_id);
+                    }
+                    (Some(class.clone()), classified_log)
+                }
+            }
+            _ => {
+                let mut class = Class::from_log_and_token_count(log, 0);
+                class.token_count = Self::token_count(&self.tokenizer, &class);
+                if let Some((_, tree)) = self.trees.get_mut(&key) {
+                    tree.insert(&class.mask_items(), &class.class_id);
+                }
+                state.classes.push(class.clone());
+                let classified_log = ClassifiedLogRecord::new(log, &class);
+                (Some(class), classified_log)
+            }
+        };
+        let created = matches!(&result.0, Some(class) if class.count == 1);
+        let adapted = self
+            .thresholds
+            .observe(&key, &log.container, state, created);
+        // merged and evicted classes don't change the version, the tree is rebuilt on the next log
+        let removed = self
+            .lifecycle
+            .apply(&key, state, threshold, &self.tokenizer);
+        if removed {
+            self.trees.remove(&key);
+        }
+        let reset = configured
+            != state
+                .thresholds
+                .get(&log.container)
+                .map(|threshold| threshold.configured);
+        let change = match result.0.is_some() || adapted || removed || reset {
+            true => StateChange::Classes,
+            false => StateChange::Counts,
+        };
+        self.cache.release(&key, change)?;
+        Ok(result)
+    }
+
+    fn explain(
+        &mut self,
+        log: &PreprocessedLogRecord,
+        db: &str,
+    ) -> Result<Explanation, ClassifierError> {
+        let key = self.cache.key(db, Some(&log.namespace), &log.workload);
+        let mut state = self.cache.peek(&key)?;
+        let threshold = self
+            .thresholds
+            .threshold(db, &log.namespace, &log.container, &mut state);
+        let masked_message = mask_tokens(&log.preprocessed_message);
+
+        // a separate tree, the cached one belongs to the state of `classify`
+        let mut tree = PrefixTree::new(self.depth, self.max_children);
+        for class in &state.classes {
+            tree.insert(&class.mask_items(), &class.class_id);
+        }
+        let search = tree.search(&masked_message);
+
+        let candidates = state
+            .classes
+            .iter()
+            .filter(|class| {
+                class.length == masked_message.len() && search.contains(&class.class_id)
+            })
+            .map(|class| {
+                let agreement = compare(&masked_message, &class.mask_items());
+                let similarity =
+                    agreement.iter().filter(|&b| *b).count() as f64 / agreement.len().max(1) as f64;
+                Candidate::new(class, agreement, similarity)
+            })
+            .collect();
+        Ok(Explanation::new(
+            key,
+            threshold,
+            log,
+            masked_message,
+            candidates,
+        ))
+    }
+
+    fn states(&self) -> Vec<(&str, &ClassifierState)> {
+        self.cache.states()
+    }
+
+    fn take_updates(&mut self) -> Vec<Class> {
+        self.lifecycle.take_updates()
+    }
+
+    fn flush(&mut self) -> Result<(), ClassifierError> {
+        Ok(self.cache.flush()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::DrainClassifier;
+    use crate::classification::log_classifier::LogClassifier;
+    use rstest::rstest;
+    use shared::{
+        preprocessing::variable::VarKind,
+        setup_tracing,
+        types::{
+            class::item::Item, classifier::error::ClassifierError,
+            record::preprocessed::PreprocessedLogRecord,
+        },
+        utils::mock::{
+            mock_client::get_test_metadata,
+            mock_data::{get_test_data, TestCase, TestData},
+        },
+        RedisConnection, StateCache,
+    };
+
+    #[tokio::test]
+    #[rstest]
+    #[case(get_test_data(TestCase::Simple))]
+    #[case(get_test_data(TestCase::Short))]
+    #[case(get_test_data(TestCase::LeadingVariable))]
+    async fn test_drain_classify(#[case] test_data: TestData) -> Result<(), ClassifierError> {
+        setup_tracing(false);
+
+        let redis = RedisConnection::new()?;
+        let mut classifier = DrainClassifier::new(Some(0.6), redis)?;
+
+        for (index, (key, raw_message, expected_class)) in test_data.into_iter().enumerate() {
+            let preprocessed_log =
+                PreprocessedLogRecord::from((&"customer_id".to_owned(), &raw_message, &key));
+            let class = classifier.classify(&preprocessed_log, "customer_id")?.0;
+
+            match index.cmp(&1) {
+                std::cmp::Ordering::Equal => {
+                    let class = class.expect("class should be updated by the second log");
+                    assert_eq!(class.to_string(), expected_class.to_string());
+                    assert_eq!(class.similarity, expected_class.similarity);
+                }
+                std::cmp::Ordering::Greater => assert!(class.is_none()),
+                std::cmp::Ordering::Less => assert!(class.is_some()),
+            }
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[rstest]
+    async fn test_drain_rebuilds_tree_on_version_change() -> Result<(), ClassifierError> {
+        let mut classifier = DrainClassifier::with_cache(Some(0.6), StateCache::in_memory())?;
+        let metadata = get_test_metadata("test-pod");
+        let log = |message: &str| {
+            PreprocessedLogRecord::from((&"customer_id".to_owned(), &message.to_owned(), &metadata))
+        };
+        classifier.classify(&log("alpha started worker pool"), "db")?;
+        let key = classifier.cache.states()[0].0.to_owned();
+
+        // another writer turned the leading token into a variable, the class count is unchanged
+        let state = classifier.cache.get_mut(&key)?;
+        state.classes[0].items[0] = Item::Var(VarKind::Any);
+        state.version += 1;
+
+        classifier.classify(&log("beta started worker pool"), "db")?;
+        assert_eq!(classifier.cache.peek(&key)?.classes.len(), 1);
+        Ok(())
+    }
+}
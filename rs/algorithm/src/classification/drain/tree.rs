@@ -0,0 +1,208 @@
+use std::collections::HashMap;
+
+const WILDCARD: &str = "<*>";
+
+// Fixed-depth parse tree as described in "Drain: An Online Log Parsing Approach with Fixed Depth
+// Tree". The first level groups by token count, the following `depth - 2` levels by the leading
+// tokens of the log. Leaves hold the ids of the candidate classes.
+#[derive(Debug)]
+pub struct PrefixTree {
+    depth: usize,
+    max_children: usize,
+    root: HashMap<usize, Node>,
+    len: usize,
+}
+
+#[derive(Debug, Default)]
+struct Node {
+    children: HashMap<String, Node>,
+    class_ids: Vec<String>,
+}
+
+impl PrefixTree {
+    pub fn new(depth: usize, max_children: usize) -> Self {
+        Self {
+            depth,
+            max_children,
+            root: HashMap::new(),
+            len: 0,
+        }
+    }
+
+    // number of classes stored in the tree
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn search(&self, tokens: &[String]) -> &[String] {
+        let mut node = match self.root.get(&tokens.len()) {
+            Some(node) => node,
+            None => return &[],
+        };
+        for token in self.prefix(tokens) {
+            node = match node
+                .children
+                .get(token.as_str())
+                .or_else(|| node.children.get(WILDCARD))
+            {
+                Some(child) => child,
+                None => return &[],
+            };
+        }
+        &node.class_ids
+    }
+
+    pub fn insert(&mut self, tokens: &[String], class_id: &str) {
+        let max_children = self.max_children;
+        let prefix_length = self.prefix(tokens).len();
+        let mut node = self.root.entry(tokens.len()).or_default();
+        for token in &tokens[..prefix_length] {
+            let token = if is_variable(token) {
+                WILDCARD
+            } else if node.children.contains_key(token.as_str())
+                // keep one child for the wildcard
+                || node.children.len() + 1 < max_children
+            {
+                token.as_str()
+            } else {
+                WILDCARD
+            };
+            node = node.children.entry(token.to_string()).or_default();
+        }
+        node.class_ids.push(class_id.to_string());
+        self.len += 1;
+    }
+
+    // The `remove` function removes the class from the tree, wherever it was inserted. Nodes left
+    // without classes are pruned so they don't count against `max_children`.
+    pub fn remove(&mut self, class_id: &str) -> bool {
+        let length = self
+            .root
+            .iter_mut()
+            .find_map(|(length, node)| node.remove(class_id).then_some(*length));
+        match length {
+            Some(length) => {
+                if self.root[&length].is_empty() {
+                    self.root.remove(&length);
+                }
+                self.len -= 1;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn prefix<'a>(&self, tokens: &'a [String]) -> &'a [String] {
+        &tokens[..tokens.len().min(self.depth.saturating_sub(2))]
+    }
+}
+
+impl Node {
+    fn remove(&mut self, class_id: &str) -> bool {
+        if let Some(index) = self.class_ids.iter().position(|id| id == class_id) {
+            self.class_ids.remove(index);
+            return true;
+        }
+        let token = self
+            .children
+            .iter_mut()
+            .find_map(|(token, child)| child.remove(class_id).then(|| token.clone()));
+        match token {
+            Some(token) => {
+                if self.children[&token].is_empty() {
+                    self.children.remove(&token);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.class_ids.is_empty() && self.children.is_empty()
+    }
+}
+
+// Tokens that are likely to change between log lines of the same class must not split the tree.
+// This includes masked variables (`<ip>`, ...) and the empty mask of untyped variables.
+fn is_variable(token: &str) -> bool {
+    token.is_empty()
+        || (token.starts_with('<') && token.ends_with('>'))
+        || token.chars().any(|c| c.is_ascii_digit())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PrefixTree;
+    use rstest::rstest;
+
+    fn tokens(input: &str) -> Vec<String> {
+        input.split_whitespace().map(str::to_string).collect()
+    }
+
+    #[rstest]
+    #[case("INFO Connection established", vec!["a"])]
+    #[case("INFO Connection lost", vec!["a"])]
+    #[case("INFO Disconnected now", vec![])]
+    #[case("WARN Connection refused", vec!["b"])]
+    #[case("INFO Connection", vec![])]
+    #[case("<ip> Connection established", vec!["b"])]
+    #[case("10.0.0.1 Connection established", vec!["b"])]
+    fn test_prefix_tree_search(#[case] input: &str, #[case] expected: Vec<&str>) {
+        let mut tree = PrefixTree::new(4, 100);
+        tree.insert(&tokens("INFO Connection established"), "a");
+        tree.insert(&tokens("<ip> Connection refused"), "b");
+
+        assert_eq!(tree.len(), 2);
+        assert_eq!(tree.search(&tokens(input)), expected.as_slice());
+    }
+
+    #[test]
+    fn test_prefix_tree_max_children() {
+        let mut tree = PrefixTree::new(3, 2);
+        tree.insert(&tokens("INFO started"), "a");
+        tree.insert(&tokens("WARN started"), "b");
+        tree.insert(&tokens("ERROR started"), "c");
+
+        assert_eq!(tree.search(&tokens("INFO started")), ["a"]);
+        assert_eq!(tree.search(&tokens("DEBUG started")), ["b", "c"]);
+    }
+
+    #[test]
+    fn test_prefix_tree_reindex_leading_variable() {
+        let mut tree = PrefixTree::new(4, 100);
+        tree.insert(&tokens("alpha started worker"), "a");
+        tree.insert(&tokens("alpha stopped worker"), "b");
+        assert!(tree.search(&tokens("beta started worker")).is_empty());
+
+        // the leading token of "a" became a variable
+        assert!(tree.remove("a"));
+        let mut reindexed = tokens("alpha started worker");
+        reindexed[0] = String::new();
+        tree.insert(&reindexed, "a");
+
+        assert_eq!(tree.len(), 2);
+        assert_eq!(tree.search(&tokens("beta started worker")), ["a"]);
+        assert_eq!(tree.search(&tokens("alpha stopped worker")), ["b"]);
+        assert!(!tree.remove("c"));
+    }
+
+    #[test]
+    fn test_prefix_tree_remove_prunes_nodes() {
+        let mut tree = PrefixTree::new(3, 3);
+        tree.insert(&tokens("INFO started"), "a");
+        tree.insert(&tokens("WARN started"), "b");
+        assert!(tree.remove("a"));
+        tree.insert(&tokens("ERROR started"), "c");
+
+        // the pruned INFO node leaves room for ERROR
+        assert!(tree.search(&tokens("INFO started")).is_empty());
+        assert_eq!(tree.search(&tokens("ERROR started")), ["c"]);
+        assert!(tree.remove("b") && tree.remove("c"));
+        assert!(tree.is_empty());
+    }
+}
@@ -0,0 +1,118 @@
+use serde::Serialize;
+use shared::{
+    preprocessing::compare::Alignment,
+    types::{class::Class, record::preprocessed::PreprocessedLogRecord},
+};
+
+// Candidates beyond this many, ordered by similarity, are not reported
+const MAX_CANDIDATES: usize = 10;
+
+// A class compared with the log during a dry run. `agreement` holds one entry per token of the
+// log, `true` if the token matched an item of the class.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Candidate {
+    pub class_id: String,
+    pub template: String,
+    pub count: u32,
+    pub agreement: Vec<bool>,
+    pub similarity: f64,
+}
+
+impl Candidate {
+    pub fn new(class: &Class, agreement: Vec<bool>, similarity: f64) -> Self {
+        Candidate {
+            class_id: class.class_id.clone(),
+            template: class.to_string(),
+            count: class.count,
+            agreement,
+            similarity,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "decision", rename_all = "kebab-case")]
+pub enum Decision {
+    // the log would be added to the class
+    Matched { class_id: String, similarity: f64 },
+    // no candidate reached the threshold, the log would create a class
+    NewClass { highest_similarity: f64 },
+}
+
+// The result of a dry-run classification: the tokens of the preprocessed log, the classes it was
+// compared with and the decision `classify` would make.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Explanation {
+    pub key: String,
+    pub threshold: f64,
+    pub tokens: Vec<String>,
+    pub masked_tokens: Vec<String>,
+    pub candidates: Vec<Candidate>,
+    pub decision: Decision,
+}
+
+impl Explanation {
+    // The `new` function decides like `classify`: the first candidate with the highest
+    // similarity wins if it reaches the threshold.
+    pub fn new(
+        key: String,
+        threshold: f64,
+        log: &PreprocessedLogRecord,
+        masked_tokens: Vec<String>,
+        mut candidates: Vec<Candidate>,
+    ) -> Self {
+        let best_match = candidates
+            .iter()
+            .fold(None::<&Candidate>, |best, candidate| match best {
+                Some(best) if best.similarity >= candidate.similarity => Some(best),
+                _ => Some(candidate),
+            });
+        let decision = match best_match {
+            Some(best) if best.similarity >= threshold => Decision::Matched {
+                class_id: best.class_id.clone(),
+                similarity: best.similarity,
+            },
+            best => Decision::NewClass {
+                highest_similarity: best.map_or(0.0, |best| best.similarity),
+            },
+        };
+        // stable, so candidates of equal similarity keep the order of the state
+        candidates.sort_by(|a, b| b.similarity.total_cmp(&a.similarity));
+        candidates.truncate(MAX_CANDIDATES);
+        Explanation {
+            key,
+            threshold,
+            tokens: log.preprocessed_message.clone(),
+            masked_tokens,
+            candidates,
+            decision,
+        }
+    }
+}
+
+// The `agreement` function marks the tokens of the first input that are matched in the alignment.
+pub fn agreement(alignment: &[Alignment], length: usize) -> Vec<bool> {
+    let mut agreement = vec![false; length];
+    for step in alignment {
+        if let Alignment::Match(i, _) = step {
+            agreement[*i] = true;
+        }
+    }
+    agreement
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::preprocessing::compare::align;
+
+    fn tokens(text: &str) -> Vec<String> {
+        text.split(' ').map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_agreement() {
+        let alignment = align(&tokens("GET /health 200 OK"), &tokens("GET /ready 200"));
+        assert_eq!(agreement(&alignment, 4), [true, false, true, false]);
+    }
+}
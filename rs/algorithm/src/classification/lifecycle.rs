@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+use std::env::var;
+use std::time::{Duration, Instant};
+
+use shared::types::{
+    class::Class,
+    classifier::{error::ClassifierError, state::ClassifierState},
+    tokenizer::Tokenizer,
+};
+use tracing::info;
+
+const DEFAULT_MAX_CLASSES_PER_KEY: usize = 500;
+const DEFAULT_MERGE_INTERVAL_SECS: u64 = 300;
+const DEFAULT_CLASS_TTL_SECS: u64 = 7 * 24 * 3600;
+
+// Bounds the classes of a key and periodically merges classes that converged while learning.
+// Classes not seen for the TTL are evicted first. Changed and removed classes are collected until
+// they are taken by the caller.
+pub struct Lifecycle {
+    capacity: usize,
+    merge_interval: Duration,
+    // milliseconds, like the timestamps of the logs
+    ttl: i64,
+    last_merge: HashMap<String, Instant>,
+    updates: Vec<Class>,
+}
+
+impl Lifecycle {
+    pub fn new(capacity: usize, merge_interval: Duration, ttl: Duration) -> Self {
+        Self {
+            capacity,
+            merge_interval,
+            ttl: ttl.as_millis().try_into().unwrap_or(i64::MAX),
+            last_merge: HashMap::new(),
+            updates: Vec::new(),
+        }
+    }
+
+    // The `from_env` function reads `CLASSIFIER_MAX_CLASSES_PER_KEY`, `CLASSIFIER_MERGE_INTERVAL_SECS`
+    // and `CLASSIFIER_CLASS_TTL_SECS`.
+    pub fn from_env() -> Result<Self, ClassifierError> {
+        let capacity = var("CLASSIFIER_MAX_CLASSES_PER_KEY")
+            .unwrap_or(DEFAULT_MAX_CLASSES_PER_KEY.to_string())
+            .parse::<usize>()?;
+        let merge_interval = var("CLASSIFIER_MERGE_INTERVAL_SECS")
+            .unwrap_or(DEFAULT_MERGE_INTERVAL_SECS.to_string())
+            .parse::<u64>()?;
+        let ttl = var("CLASSIFIER_CLASS_TTL_SECS")
+            .unwrap_or(DEFAULT_CLASS_TTL_SECS.to_string())
+            .parse::<u64>()?;
+        Ok(Self::new(
+            capacity,
+            Duration::from_secs(merge_interval),
+            Duration::from_secs(ttl),
+        ))
+    }
+
+    // The `apply` function merges and evicts the classes of the state, it returns whether classes
+    // were changed or removed.
+    pub fn apply(
+        &mut self,
+        key: &str,
+        state: &mut ClassifierState,
+        threshold: f64,
+        tokenizer: &Tokenizer,
+    ) -> bool {
+        let mut changed = false;
+        let last_merge = self
+            .last_merge
+            .entry(key.to_owned())
+            .or_insert_with(Instant::now);
+        if last_merge.elapsed() >= self.merge_interval {
+            *last_merge = Instant::now();
+            let (merged, folded) = state.merge_similar(threshold, tokenizer);
+            if !folded.is_empty() {
+                info!("Merged {} classes of key: {}", folded.len(), key);
+            }
+            changed |= !merged.is_empty() || !folded.is_empty();
+            self.updates.extend(merged);
+            self.updates.extend(folded);
+        }
+
+        let evicted = state.evict(self.capacity, self.ttl);
+        if !evicted.is_empty() {
+            info!("Evicted {} classes of key: {}", evicted.len(), key);
+        }
+        changed |= !evicted.is_empty();
+        self.updates.extend(evicted);
+        changed
+    }
+
+    // The `record` function adds classes changed outside of `apply` to the updates.
+    pub fn record(&mut self, classes: Vec<Class>) {
+        self.updates.extend(classes);
+    }
+
+    // The `take_updates` function returns the classes changed or deleted since the last call.
+    pub fn take_updates(&mut self) -> Vec<Class> {
+        std::mem::take(&mut self.updates)
+    }
+}
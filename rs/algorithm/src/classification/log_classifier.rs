@@ -0,0 +1,134 @@
+use std::env::var;
+use std::str::FromStr;
+use std::time::Duration;
+
+use shared::{
+    types::{
+        class::Class,
+        classifier::{error::ClassifierError, state::ClassifierState},
+        record::{classified::ClassifiedLogRecord, preprocessed::PreprocessedLogRecord},
+    },
+    RedisConnection, StateCache,
+};
+use strum::EnumString;
+
+use super::{
+    deterministic::classifier::Classifier, drain::classifier::DrainClassifier, explain::Explanation,
+};
+
+const DEFAULT_BACKEND: ClassifierBackend = ClassifierBackend::Deterministic;
+const DEFAULT_THRESHOLD: f64 = 0.6;
+const DEFAULT_CACHE_CAPACITY: usize = 1024;
+const DEFAULT_CACHE_FLUSH_INTERVAL_MS: u64 = 1000;
+
+pub trait LogClassifier: Send {
+    // The `classify` function assigns the log to a class of its key. It returns the class if it
+    // was created or its representation changed, together with the classified log.
+    fn classify(
+        &mut self,
+        log: &PreprocessedLogRecord,
+        db: &str,
+    ) -> Result<(Option<Class>, ClassifiedLogRecord), ClassifierError>;
+
+    // The `explain` function runs a dry-run classification of the log. It reports the classes
+    // the log was compared with and the decision `classify` would make, without changing state.
+    fn explain(
+        &mut self,
+        log: &PreprocessedLogRecord,
+        db: &str,
+    ) -> Result<Explanation, ClassifierError>;
+
+    // The `states` function returns the states of the keys held in memory by key.
+    fn states(&self) -> Vec<(&str, &ClassifierState)>;
+
+    // The `take_updates` function returns classes that were changed or deleted outside of
+    // `classify`, e.g. by eviction or merging.
+    fn take_updates(&mut self) -> Vec<Class>;
+
+    // The `flush` function writes all cached state changes to Redis, it has to be called before shutdown.
+    fn flush(&mut self) -> Result<(), ClassifierError>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, EnumString, strum::Display)]
+#[strum(serialize_all = "kebab-case")]
+pub enum ClassifierBackend {
+    Deterministic,
+    Drain,
+}
+
+impl ClassifierBackend {
+    pub fn from_env() -> Result<Self, ClassifierError> {
+        match var("CLASSIFIER_BACKEND") {
+            Ok(backend) => ClassifierBackend::from_str(backend.trim())
+                .map_err(|_| ClassifierError::UnknownBackend(backend)),
+            Err(_) => Ok(DEFAULT_BACKEND),
+        }
+    }
+}
+
+// The `threshold_from_env` function returns the given threshold or reads it from `CLASSIFIER_THRESHOLD`.
+pub fn threshold_from_env(threshold: Option<f64>) -> Result<f64, ClassifierError> {
+    match threshold {
+        Some(threshold) => Ok(threshold),
+        None => Ok(var("CLASSIFIER_THRESHOLD")
+            .unwrap_or(DEFAULT_THRESHOLD.to_string())
+            .parse::<f64>()?),
+    }
+}
+
+// The `state_cache_from_env` function wraps the connection in a cache configured by
+// `CLASSIFIER_CACHE_CAPACITY` and `CLASSIFIER_CACHE_FLUSH_INTERVAL_MS`.
+pub fn state_cache_from_env(redis: RedisConnection) -> Result<StateCache, ClassifierError> {
+    let capacity = var("CLASSIFIER_CACHE_CAPACITY")
+        .unwrap_or(DEFAULT_CACHE_CAPACITY.to_string())
+        .parse::<usize>()?;
+    let flush_interval = var("CLASSIFIER_CACHE_FLUSH_INTERVAL_MS")
+        .unwrap_or(DEFAULT_CACHE_FLUSH_INTERVAL_MS.to_string())
+        .parse::<u64>()?;
+    Ok(StateCache::new(
+        redis,
+        capacity,
+        Duration::from_millis(flush_interval),
+    ))
+}
+
+// The `new_classifier` function creates the classifier backend selected by `CLASSIFIER_BACKEND`.
+pub fn new_classifier(
+    threshold: Option<f64>,
+    redis: RedisConnection,
+) -> Result<Box<dyn LogClassifier>, ClassifierError> {
+    let backend = ClassifierBackend::from_env()?;
+    tracing::info!("Using classifier backend: {}", backend);
+    classifier_with_cache(backend, threshold, state_cache_from_env(redis)?)
+}
+
+// The `classifier_with_cache` function creates the given classifier backend on a state cache,
+// e.g. on an in-memory cache to replay logs.
+pub fn classifier_with_cache(
+    backend: ClassifierBackend,
+    threshold: Option<f64>,
+    cache: StateCache,
+) -> Result<Box<dyn LogClassifier>, ClassifierError> {
+    Ok(match backend {
+        ClassifierBackend::Deterministic => Box::new(Classifier::with_cache(threshold, cache)?),
+        ClassifierBackend::Drain => Box::new(DrainClassifier::with_cache(threshold, cache)?),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ClassifierBackend;
+    use rstest::rstest;
+    use std::str::FromStr;
+
+    #[rstest]
+    #[case("deterministic", Some(ClassifierBackend::Deterministic))]
+    #[case("drain", Some(ClassifierBackend::Drain))]
+    #[case("linear", None)]
+    fn test_classifier_backend_from_str(
+        #[case] input: &str,
+        #[case] expected: Option<ClassifierBackend>,
+    ) {
+        assert_eq!(ClassifierBackend::from_str(input).ok(), expected);
+    }
+}
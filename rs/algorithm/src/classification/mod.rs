@@ -1 +1,6 @@
 pub mod deterministic;
+pub mod drain;
+pub mod explain;
+pub mod lifecycle;
+pub mod log_classifier;
+pub mod threshold;
@@ -0,0 +1,282 @@
+use std::env::var;
+
+use serde::Deserialize;
+use shared::{
+    connections::dbname::DbName,
+    types::{
+        class::item::Item,
+        classifier::{
+            error::ClassifierError,
+            state::{ClassifierState, KeyThreshold},
+        },
+    },
+};
+use tracing::info;
+
+use crate::classification::log_classifier::threshold_from_env;
+
+const DEFAULT_ADAPTIVE_WINDOW: u32 = 1000;
+const MIN_THRESHOLD: f64 = 0.3;
+const MAX_THRESHOLD: f64 = 0.95;
+const THRESHOLD_STEP: f64 = 0.05;
+// share of the logs of a window that created a class, above it the classes of a key explode
+const MAX_NEW_CLASS_RATE: f64 = 0.1;
+// share of variable items of the classes of a key, above it the classes collapsed
+const MAX_VARIABLE_SHARE: f64 = 0.5;
+
+// A threshold for the logs of a customer, optionally narrowed to a namespace and container.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ThresholdRule {
+    pub customer: String,
+    #[serde(default)]
+    pub namespace: Option<String>,
+    #[serde(default)]
+    pub container: Option<String>,
+    pub threshold: f64,
+}
+
+impl ThresholdRule {
+    // The `specificity` function returns how many fields of the rule match the key, `None` if
+    // any of them does not match.
+    fn specificity(&self, db: &str, namespace: &str, container: &str) -> Option<usize> {
+        if db != DbName::Log.id(&self.customer) {
+            return None;
+        }
+        let fields = [(&self.namespace, namespace), (&self.container, container)];
+        fields
+            .iter()
+            .try_fold(1, |specificity, (rule, value)| match rule {
+                Some(rule) if rule == value => Some(specificity + 1),
+                Some(_) => None,
+                None => Some(specificity),
+            })
+    }
+}
+
+// Chooses the similarity threshold of a key. Rules are read from `CLASSIFIER_THRESHOLDS`, a JSON
+// array like `[{"customer": "acme", "namespace": "shop", "container": "api", "threshold": 0.7}]`,
+// the most specific matching rule wins. Keys without a rule use `CLASSIFIER_THRESHOLD`.
+// With `CLASSIFIER_ADAPTIVE_THRESHOLD=true` the threshold of a key is lowered when most logs of
+// a window of `CLASSIFIER_ADAPTIVE_WINDOW` logs create new classes, and raised when the classes
+// of the key collapsed into mostly variable templates.
+#[derive(Debug, Clone)]
+pub struct ThresholdPolicy {
+    default: f64,
+    rules: Vec<ThresholdRule>,
+    adaptive: bool,
+    window: u32,
+}
+
+impl ThresholdPolicy {
+    pub fn new(default: f64, rules: Vec<ThresholdRule>, adaptive: bool, window: u32) -> Self {
+        ThresholdPolicy {
+            default,
+            rules,
+            adaptive,
+            window,
+        }
+    }
+
+    pub fn from_env(threshold: Option<f64>) -> Result<Self, ClassifierError> {
+        let default = threshold_from_env(threshold)?;
+        let rules = match var("CLASSIFIER_THRESHOLDS") {
+            Ok(rules) => serde_json::from_str::<Vec<ThresholdRule>>(&rules)?,
+            Err(_) => Vec::new(),
+        };
+        let adaptive = var("CLASSIFIER_ADAPTIVE_THRESHOLD")
+            .unwrap_or(false.to_string())
+            .parse::<bool>()?;
+        let window = var("CLASSIFIER_ADAPTIVE_WINDOW")
+            .unwrap_or(DEFAULT_ADAPTIVE_WINDOW.to_string())
+            .parse::<u32>()?;
+        Ok(Self::new(default, rules, adaptive, window))
+    }
+
+    fn configured(&self, db: &str, namespace: &str, container: &str) -> f64 {
+        self.rules
+            .iter()
+            .filter_map(|rule| {
+                rule.specificity(db, namespace, container)
+                    .map(|specificity| (specificity, rule.threshold))
+            })
+            .max_by_key(|(specificity, _)| *specificity)
+            .map_or(self.default, |(_, threshold)| threshold)
+    }
+
+    // The `threshold` function returns the threshold of the container and records it in the state,
+    // the containers of a workload share the state but not their thresholds. A changed
+    // configuration resets an adapted threshold.
+    pub fn threshold(
+        &self,
+        db: &str,
+        namespace: &str,
+        container: &str,
+        state: &mut ClassifierState,
+    ) -> f64 {
+        let configured = self.configured(db, namespace, container);
+        let threshold = match state.thresholds.get(container) {
+            Some(threshold) if threshold.configured == configured => *threshold,
+            _ => KeyThreshold::new(configured),
+        };
+        state.thresholds.insert(container.to_owned(), threshold);
+        match self.adaptive {
+            true => threshold.current,
+            false => threshold.configured,
+        }
+    }
+
+    // The `observe` function counts a classified log of the container and adapts its threshold at
+    // the end of each window. Returns whether the threshold was adapted.
+    pub fn observe(
+        &self,
+        key: &str,
+        container: &str,
+        state: &mut ClassifierState,
+        created: bool,
+    ) -> bool {
+        if !self.adaptive {
+            return false;
+        }
+        let variable_share = variable_share(state, container);
+        let Some(threshold) = state.thresholds.get_mut(container) else {
+            return false;
+        };
+        threshold.logs += 1;
+        threshold.new_classes += created as u32;
+        if threshold.logs < self.window {
+            return false;
+        }
+
+        let new_class_rate = threshold.new_classes as f64 / threshold.logs as f64;
+        let previous = threshold.current;
+        if new_class_rate > MAX_NEW_CLASS_RATE {
+            threshold.current = (threshold.current - THRESHOLD_STEP).max(MIN_THRESHOLD);
+        } else if variable_share > MAX_VARIABLE_SHARE {
+            threshold.current = (threshold.current + THRESHOLD_STEP).min(MAX_THRESHOLD);
+        }
+        if threshold.current != previous {
+            info!(
+                "Adapted threshold of key {} container {} from {:.2} to {:.2} (new class rate: {:.2}, variable share: {:.2})",
+                key, container, previous, threshold.current, new_class_rate, variable_share
+            );
+        }
+        threshold.logs = 0;
+        threshold.new_classes = 0;
+        threshold.current != previous
+    }
+}
+
+// The `variable_share` function returns the share of variable items of the classes of a container,
+// weighted by the number of logs of each class.
+fn variable_share(state: &ClassifierState, container: &str) -> f64 {
+    let (variable, total) = state
+        .classes
+        .iter()
+        .filter(|class| class.container == container)
+        .fold((0, 0), |(variable, total), class| {
+            let count = class.count as usize;
+            let variables = class
+                .items
+                .iter()
+                .filter(|item| matches!(item, Item::Var(_) | Item::Variadic))
+                .count();
+            (
+                variable + variables * count,
+                total + class.items.len() * count,
+            )
+        });
+    match total {
+        0 => 0.0,
+        _ => variable as f64 / total as f64,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use shared::{
+        preprocessing::variable::VarKind,
+        utils::mock::{mock_client::get_test_metadata, mock_data::class_from_items},
+    };
+
+    fn rule(namespace: Option<&str>, container: Option<&str>, threshold: f64) -> ThresholdRule {
+        ThresholdRule {
+            customer: "acme".to_string(),
+            namespace: namespace.map(str::to_string),
+            container: container.map(str::to_string),
+            threshold,
+        }
+    }
+
+    #[rstest]
+    #[case("log_acme", "shop", "api", 0.8)]
+    #[case("log_acme", "shop", "worker", 0.7)]
+    #[case("log_acme", "billing", "api", 0.5)]
+    #[case("log_other", "shop", "api", 0.6)]
+    fn test_most_specific_rule_wins(
+        #[case] db: &str,
+        #[case] namespace: &str,
+        #[case] container: &str,
+        #[case] expected: f64,
+    ) {
+        let rules = vec![
+            rule(None, None, 0.5),
+            rule(Some("shop"), None, 0.7),
+            rule(Some("shop"), Some("api"), 0.8),
+        ];
+        let policy = ThresholdPolicy::new(0.6, rules, false, 10);
+        let mut state = ClassifierState::default();
+
+        assert_eq!(
+            policy.threshold(db, namespace, container, &mut state),
+            expected
+        );
+        assert_eq!(state.thresholds[container], KeyThreshold::new(expected));
+    }
+
+    #[test]
+    fn test_adaptive_threshold() {
+        let policy = ThresholdPolicy::new(0.6, Vec::new(), true, 10);
+        let mut state = ClassifierState::default();
+        policy.threshold("log_acme", "shop", "api", &mut state);
+
+        // every log creates a class, the threshold is relaxed
+        for _ in 0..10 {
+            policy.observe("key", "api", &mut state, true);
+        }
+        let current = policy.threshold("log_acme", "shop", "api", &mut state);
+        assert!((current - 0.55).abs() < 1e-9);
+
+        // all logs matched a mostly variable class, the threshold is tightened again
+        let items = vec![
+            Item::Fix("request".to_string()),
+            Item::Var(VarKind::Ip),
+            Item::Variadic,
+        ];
+        let mut class = class_from_items(items, 1.0, &get_test_metadata("test-pod"));
+        class.container = "api".to_string();
+        class.count = 10;
+        state.classes.push(class);
+        for _ in 0..10 {
+            policy.observe("key", "api", &mut state, false);
+        }
+        let current = policy.threshold("log_acme", "shop", "api", &mut state);
+        assert!((current - 0.6).abs() < 1e-9);
+
+        // other containers of the key keep their own threshold
+        assert_eq!(
+            policy.threshold("log_acme", "shop", "worker", &mut state),
+            0.6
+        );
+        for _ in 0..10 {
+            policy.observe("key", "worker", &mut state, true);
+        }
+        let current = policy.threshold("log_acme", "shop", "api", &mut state);
+        assert!((current - 0.6).abs() < 1e-9);
+
+        // a changed configuration resets the adapted threshold
+        let policy = ThresholdPolicy::new(0.7, Vec::new(), true, 10);
+        assert_eq!(policy.threshold("log_acme", "shop", "api", &mut state), 0.7);
+    }
+}
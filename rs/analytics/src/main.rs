@@ -2,6 +2,7 @@ pub mod analyze_logs;
 pub mod analyze_resource;
 pub mod analyze_state;
 pub mod histogram;
+pub mod replay;
 pub mod utils;
 
 use std::env;
@@ -9,6 +10,7 @@ use std::env;
 use analyze_logs::analyze_logs;
 use analyze_resource::analyze_resource;
 use analyze_state::analyze_state;
+use replay::{create_replay_table, replay, ReplaySettings, REPLAY_USAGE};
 use shared::{
     get_env_var, setup_tracing, DbName, GreptimeConnection, QdrantConnection, RedisConnection,
 };
@@ -17,6 +19,23 @@ use utils::create_map;
 #[tokio::main]
 async fn main() {
     setup_tracing(false);
+
+    // `analytics replay [...]` replays stored logs through a fresh classifier
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("replay") {
+        let customer_id = get_env_var("ANALYTICS_CLIENT_ID").unwrap_or_default();
+        let settings = match ReplaySettings::from_args(customer_id, &args[1..]) {
+            Ok(settings) => settings,
+            Err(e) => {
+                eprintln!("{e}\n{REPLAY_USAGE}");
+                std::process::exit(2);
+            }
+        };
+        let reports = replay(&settings).await.unwrap();
+        println!("{}", create_replay_table(&reports));
+        return;
+    }
+
     let limit = 1000000;
     let run_analyze_resource = false;
     let run_analyze_log = false;
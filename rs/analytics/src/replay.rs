@@ -0,0 +1,295 @@
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+
+use algorithm::classification::log_classifier::{
+    classifier_with_cache, ClassifierBackend, LogClassifier,
+};
+use shared::{
+    format_key,
+    preprocessing::pipeline::{PipelineConfigError, Pipelines},
+    types::{
+        classifier::{error::ClassifierError, state::ClassifierState},
+        record::{log::dt_from_ts, preprocessed::PreprocessedLogRecord},
+    },
+    DbName, GreptimeConnection, GreptimeConnectionError, RedisConnection, RedisConnectionError,
+    StateCache,
+};
+use tabled::{Table, Tabled};
+use thiserror::Error;
+
+pub const REPLAY_USAGE: &str = "usage: analytics replay [--customer <id>] [--start <time>] \
+    [--end <time>] [--threshold <threshold>] [--backend <deterministic|drain>]";
+
+#[derive(Error, Debug)]
+pub enum ReplayError {
+    #[error("Missing value for argument: {0}")]
+    MissingValue(String),
+    #[error("Unknown argument: {0}")]
+    UnknownArgument(String),
+    #[error("Invalid value for argument {0}: {1}")]
+    InvalidValue(String, String),
+    #[error("Classifier error: {0}")]
+    Classifier(#[from] ClassifierError),
+    #[error("Preprocessing pipeline error: {0}")]
+    PipelineConfig(#[from] PipelineConfigError),
+    #[error("GreptimeDB connection error: {0}")]
+    Greptime(#[from] GreptimeConnectionError),
+    #[error("Redis connection error: {0}")]
+    Redis(#[from] RedisConnectionError),
+}
+
+// Settings of a replay. Times are milliseconds since the epoch, the classifier settings that are
+// not given fall back to the environment like in data processing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplaySettings {
+    pub customer_id: String,
+    pub start: Option<i64>,
+    pub end: Option<i64>,
+    pub threshold: Option<f64>,
+    pub backend: ClassifierBackend,
+}
+
+impl ReplaySettings {
+    pub fn from_args(customer_id: String, args: &[String]) -> Result<Self, ReplayError> {
+        let mut settings = ReplaySettings {
+            customer_id,
+            start: None,
+            end: None,
+            threshold: None,
+            backend: ClassifierBackend::from_env()?,
+        };
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let value = args
+                .next()
+                .ok_or_else(|| ReplayError::MissingValue(arg.clone()))?;
+            let invalid = || ReplayError::InvalidValue(arg.clone(), value.clone());
+            match arg.as_str() {
+                "--customer" => settings.customer_id = value.clone(),
+                "--start" => settings.start = Some(parse_time(value).ok_or_else(invalid)?),
+                "--end" => settings.end = Some(parse_time(value).ok_or_else(invalid)?),
+                "--threshold" => {
+                    settings.threshold = Some(value.parse::<f64>().map_err(|_| invalid())?)
+                }
+                "--backend" => {
+                    settings.backend = ClassifierBackend::from_str(value).map_err(|_| invalid())?
+                }
+                _ => return Err(ReplayError::UnknownArgument(arg.clone())),
+            }
+        }
+        if settings.customer_id.is_empty() {
+            return Err(ReplayError::MissingValue("--customer".to_string()));
+        }
+        Ok(settings)
+    }
+}
+
+// e.g. `1710566898752` or `2024-03-16T05:28:18Z`
+fn parse_time(value: &str) -> Option<i64> {
+    value
+        .parse::<i64>()
+        .ok()
+        .or_else(|| dt_from_ts(value.trim_end_matches('Z')).ok())
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyReport {
+    pub key: String,
+    pub logs: usize,
+    pub classes: usize,
+    pub singletons: usize,
+    pub production_classes: usize,
+    // templates only found by the replay or only in production
+    pub new_templates: usize,
+    pub missing_templates: usize,
+}
+
+impl KeyReport {
+    pub fn new(
+        key: &str,
+        logs: usize,
+        replayed: &ClassifierState,
+        production: &ClassifierState,
+    ) -> Self {
+        let templates = |state: &ClassifierState| -> HashSet<String> {
+            state
+                .classes
+                .iter()
+                .map(|class| class.to_string())
+                .collect()
+        };
+        let (replayed_templates, production_templates) =
+            (templates(replayed), templates(production));
+        KeyReport {
+            key: key.to_string(),
+            logs,
+            classes: replayed.classes.len(),
+            singletons: replayed
+                .classes
+                .iter()
+                .filter(|class| class.count == 1)
+                .count(),
+            production_classes: production.classes.len(),
+            new_templates: replayed_templates.difference(&production_templates).count(),
+            missing_templates: production_templates.difference(&replayed_templates).count(),
+        }
+    }
+
+    // logs per class
+    pub fn compression_ratio(&self) -> f64 {
+        ratio(self.logs, self.classes)
+    }
+
+    // share of classes with a single log
+    pub fn singleton_ratio(&self) -> f64 {
+        ratio(self.singletons, self.classes)
+    }
+}
+
+fn ratio(numerator: usize, denominator: usize) -> f64 {
+    match denominator {
+        0 => 0.0,
+        _ => numerator as f64 / denominator as f64,
+    }
+}
+
+// The `replay` function reads the stored log lines of the customer from GreptimeDB and classifies
+// them with a fresh classifier that is kept in memory, using the preprocessing pipelines of data
+// processing. The classes are compared with the production state in Redis, which is only read.
+// Tables are replayed one pod at a time.
+pub async fn replay(settings: &ReplaySettings) -> Result<Vec<KeyReport>, ReplayError> {
+    let db = DbName::Log.id(&settings.customer_id);
+    let greptime = GreptimeConnection::new().await?;
+    let mut classifier: Box<dyn LogClassifier> = classifier_with_cache(
+        settings.backend,
+        settings.threshold,
+        StateCache::in_memory(),
+    )?;
+    let pipelines = Pipelines::from_env()?;
+
+    let mut logs_by_key: HashMap<String, usize> = HashMap::new();
+    let tables = greptime.list_tables(&db, None, Some("pod"), false).await?;
+    tracing::info!("Replaying {} tables of {}", tables.len(), db);
+    for table in tables {
+        let logs = greptime
+            .query_logs(&db, &table, settings.start, settings.end)
+            .await?;
+        for log in logs {
+            let preprocessed_message = pipelines.preprocess(&settings.customer_id, &log);
+            let preprocessed_log = PreprocessedLogRecord::from((log, preprocessed_message));
+            let key = format_key(
+                &db,
+                Some(&preprocessed_log.namespace),
+                &preprocessed_log.workload,
+            );
+            *logs_by_key.entry(key).or_default() += 1;
+            classifier.classify(&preprocessed_log, &db)?;
+        }
+    }
+
+    let mut redis = RedisConnection::new()?;
+    let mut reports = Vec::new();
+    for (key, state) in classifier.states() {
+        let production = redis.get(key)?;
+        let logs = logs_by_key.get(key).copied().unwrap_or_default();
+        reports.push(KeyReport::new(key, logs, state, &production));
+    }
+    reports.sort_by_key(|report| std::cmp::Reverse(report.logs));
+    Ok(reports)
+}
+
+#[derive(Tabled)]
+struct ReplayRow {
+    #[tabled(rename = "Key")]
+    key: String,
+    #[tabled(rename = "Logs")]
+    logs: usize,
+    #[tabled(rename = "Classes")]
+    classes: usize,
+    #[tabled(rename = "Compression")]
+    compression: String,
+    #[tabled(rename = "Singletons")]
+    singletons: String,
+    #[tabled(rename = "Production classes")]
+    production_classes: usize,
+    #[tabled(rename = "New templates")]
+    new_templates: usize,
+    #[tabled(rename = "Missing templates")]
+    missing_templates: usize,
+}
+
+impl From<&KeyReport> for ReplayRow {
+    fn from(report: &KeyReport) -> Self {
+        ReplayRow {
+            key: report.key.clone(),
+            logs: report.logs,
+            classes: report.classes,
+            compression: format!("{:.1}", report.compression_ratio()),
+            singletons: format!("{:.1}%", report.singleton_ratio() * 100.0),
+            production_classes: report.production_classes,
+            new_templates: report.new_templates,
+            missing_templates: report.missing_templates,
+        }
+    }
+}
+
+pub fn create_replay_table(reports: &[KeyReport]) -> Table {
+    let total = reports.iter().fold(
+        KeyReport {
+            key: "Total".to_string(),
+            logs: 0,
+            classes: 0,
+            singletons: 0,
+            production_classes: 0,
+            new_templates: 0,
+            missing_templates: 0,
+        },
+        |mut total, report| {
+            total.logs += report.logs;
+            total.classes += report.classes;
+            total.singletons += report.singletons;
+            total.production_classes += report.production_classes;
+            total.new_templates += report.new_templates;
+            total.missing_templates += report.missing_templates;
+            total
+        },
+    );
+    let rows: Vec<ReplayRow> = reports
+        .iter()
+        .chain([&total])
+        .map(ReplayRow::from)
+        .collect();
+    Table::new(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &str) -> Vec<String> {
+        args.split(' ').map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_replay_settings_from_args() {
+        let settings = ReplaySettings::from_args(
+            String::new(),
+            &args("--customer acme --start 2024-03-16T05:28:18Z --threshold 0.7 --backend drain"),
+        )
+        .unwrap();
+        assert_eq!(settings.customer_id, "acme");
+        assert_eq!(settings.start, Some(1710566898000));
+        assert_eq!(settings.end, None);
+        assert_eq!(settings.threshold, Some(0.7));
+        assert_eq!(settings.backend, ClassifierBackend::Drain);
+
+        assert!(matches!(
+            ReplaySettings::from_args("acme".to_string(), &args("--threshold high")),
+            Err(ReplayError::InvalidValue(_, _))
+        ));
+        assert!(matches!(
+            ReplaySettings::from_args(String::new(), &args("--end 1710566898000")),
+            Err(ReplayError::MissingValue(_))
+        ));
+    }
+}
@@ -1,10 +1,21 @@
 use rocket::{http::Status, response::Responder, Request, Response};
-use shared::{FluvioConnectionError, GreptimeConnectionError};
+use shared::preprocessing::pipeline::PipelineConfigError;
+use shared::preprocessing::redaction::RedactionConfigError;
+use shared::types::classifier::error::ClassifierError;
+use shared::{FluvioConnectionError, GreptimeConnectionError, RedisConnectionError};
 use std::io::Cursor;
 use thiserror::Error;
 use tracing::error;
 
+use crate::process::dedup::DeduplicationConfigError;
+use crate::process::encoding::EncodingError;
+use crate::process::filter::FilterError;
+use crate::process::format::FormatError;
+use crate::process::loki::LokiError;
+use crate::process::multiline::MultilineConfigError;
 use crate::process::multipart::{MultipartMetadataError, MultipartStreamError};
+use crate::process::otlp::OtlpError;
+use crate::process::quota::QuotaError;
 
 #[derive(Error, Debug)]
 pub enum DataIntakeError {
@@ -12,6 +23,20 @@ pub enum DataIntakeError {
     MultipartIoError(#[source] std::io::Error),
     #[error("Payload too large, limit is: {0}")]
     PayloadTooLarge(String),
+    #[error("Invalid content encoding: {0}")]
+    Encoding(#[from] EncodingError),
+    #[error("Invalid push request format: {0}")]
+    Format(#[from] FormatError),
+    #[error("Invalid OTLP request: {0}")]
+    Otlp(#[from] OtlpError),
+    #[error("Invalid Loki push request: {0}")]
+    Loki(#[from] LokiError),
+    #[error("Quota error: {0}")]
+    Quota(#[from] QuotaError),
+    #[error("Filter rule error: {0}")]
+    Filter(#[from] FilterError),
+    #[error("Invalid request body: {0}")]
+    InvalidBody(#[source] serde_json::Error),
     #[error("Missing boundary in content type")]
     ContentTypeBoundaryMissing,
     #[error("Invalid multipart data: {0}")]
@@ -36,6 +61,24 @@ pub enum DataIntakeError {
     FluvioConnectionError(#[from] FluvioConnectionError),
     #[error("GreptimeDB connection error: {0}")]
     GreptimeError(#[from] GreptimeConnectionError),
+    #[error("Invalid multi-line configuration: {0}")]
+    MultilineConfig(#[from] MultilineConfigError),
+    #[error("Invalid redaction configuration: {0}")]
+    RedactionConfig(#[from] RedactionConfigError),
+    #[error("Invalid deduplication configuration: {0}")]
+    DeduplicationConfig(#[from] DeduplicationConfigError),
+    #[error("Invalid preprocessing pipeline configuration: {0}")]
+    PipelineConfig(#[from] PipelineConfigError),
+    #[error("Preprocessing pipelines are poisoned by a panic of another request")]
+    PipelinesLock,
+    #[error("Classifier error: {0}")]
+    ClassifierError(#[from] ClassifierError),
+    #[error("Classifier is poisoned by a panic of another request")]
+    ClassifierLock,
+    #[error("Blocking task failed: {0}")]
+    BlockingTask(#[from] tokio::task::JoinError),
+    #[error("Redis connection error: {0}")]
+    RedisError(#[from] RedisConnectionError),
     #[error("Rocket error: {0}")]
     RocketError(#[from] rocket::Error),
     #[error("IO error: {0}")]
@@ -54,6 +97,50 @@ impl From<DataIntakeError> for Status {
                 error!("Payload too large, limit: {:?}", e);
                 Status::PayloadTooLarge
             }
+            DataIntakeError::Encoding(e) => {
+                error!("Content encoding error: {:?}", e);
+                match e {
+                    EncodingError::Unsupported(_) => Status::UnsupportedMediaType,
+                    EncodingError::Decompression(_) => Status::BadRequest,
+                    EncodingError::TooLarge(_) => Status::PayloadTooLarge,
+                }
+            }
+            DataIntakeError::Format(e) => {
+                error!("Push request format error: {:?}", e);
+                Status::UnsupportedMediaType
+            }
+            DataIntakeError::Otlp(e) => {
+                error!("OTLP request error: {:?}", e);
+                Status::BadRequest
+            }
+            DataIntakeError::Loki(e) => {
+                error!("Loki push request error: {:?}", e);
+                match e {
+                    LokiError::TooLarge(_) => Status::PayloadTooLarge,
+                    _ => Status::BadRequest,
+                }
+            }
+            DataIntakeError::Quota(e) => {
+                error!("Quota error: {:?}", e);
+                match e {
+                    QuotaError::Exceeded(_) => Status::TooManyRequests,
+                    QuotaError::InvalidDate(_) | QuotaError::InvalidRange(_, _) => {
+                        Status::BadRequest
+                    }
+                    _ => Status::InternalServerError,
+                }
+            }
+            DataIntakeError::Filter(e) => {
+                error!("Filter rule error: {:?}", e);
+                match e {
+                    FilterError::InvalidRule(_, _) => Status::UnprocessableEntity,
+                    _ => Status::InternalServerError,
+                }
+            }
+            DataIntakeError::InvalidBody(e) => {
+                error!("Invalid request body: {:?}", e);
+                Status::UnprocessableEntity
+            }
             DataIntakeError::ContentTypeBoundaryMissing => {
                 error!("Content type boundary missing");
                 Status::BadRequest
@@ -99,6 +186,42 @@ impl From<DataIntakeError> for Status {
                 Status::BadRequest
             }
             DataIntakeError::GreptimeError(_) => Status::InternalServerError,
+            DataIntakeError::MultilineConfig(e) => {
+                error!("Multi-line configuration error: {:?}", e);
+                Status::InternalServerError
+            }
+            DataIntakeError::RedactionConfig(e) => {
+                error!("Redaction configuration error: {:?}", e);
+                Status::InternalServerError
+            }
+            DataIntakeError::DeduplicationConfig(e) => {
+                error!("Deduplication configuration error: {:?}", e);
+                Status::InternalServerError
+            }
+            DataIntakeError::PipelineConfig(e) => {
+                error!("Preprocessing pipeline configuration error: {:?}", e);
+                Status::InternalServerError
+            }
+            DataIntakeError::PipelinesLock => {
+                error!("Preprocessing pipelines lock poisoned");
+                Status::InternalServerError
+            }
+            DataIntakeError::ClassifierError(e) => {
+                error!("Classifier error: {:?}", e);
+                Status::InternalServerError
+            }
+            DataIntakeError::ClassifierLock => {
+                error!("Classifier lock poisoned");
+                Status::InternalServerError
+            }
+            DataIntakeError::BlockingTask(e) => {
+                error!("Blocking task error: {:?}", e);
+                Status::InternalServerError
+            }
+            DataIntakeError::RedisError(e) => {
+                error!("Redis error: {:?}", e);
+                Status::InternalServerError
+            }
             DataIntakeError::RocketError(_) => Status::InternalServerError,
             DataIntakeError::SerializationError(_) => Status::InternalServerError,
             DataIntakeError::DeserializationError(_) => Status::InternalServerError,
@@ -113,10 +236,18 @@ impl From<DataIntakeError> for Status {
 impl<'r> Responder<'r, 'static> for DataIntakeError {
     fn respond_to(self, _: &'r Request<'_>) -> Result<Response<'static>, Status> {
         let body = format!("{}", self);
+        let retry_after = match self {
+            DataIntakeError::Quota(QuotaError::Exceeded(seconds)) => Some(seconds),
+            _ => None,
+        };
         let status = Status::from(self);
-        Response::build()
+        let mut response = Response::build();
+        response
             .status(status)
-            .sized_body(body.len(), Cursor::new(body))
-            .ok()
+            .sized_body(body.len(), Cursor::new(body));
+        if let Some(seconds) = retry_after {
+            response.raw_header("Retry-After", seconds.to_string());
+        }
+        response.ok()
     }
 }
@@ -1,6 +1,15 @@
-use shared::types::{metadata::Metadata, record::log::LogRecord};
+use super::multiline::MultilineAssembler;
+use shared::types::{
+    metadata::Metadata,
+    record::{line::parse_line, log::LogRecord},
+};
 
-pub fn process_chunk(chunk: &str, remainder: &mut String, metadata: &Metadata) -> Vec<LogRecord> {
+pub fn process_chunk(
+    chunk: &str,
+    remainder: &mut String,
+    assembler: &mut MultilineAssembler,
+    metadata: &Metadata,
+) -> Vec<LogRecord> {
     // Split the chunk into lines
     let mut log_lines: Vec<String> = chunk.split('\n').map(|line| line.to_string()).collect();
 
@@ -17,10 +26,12 @@ pub fn process_chunk(chunk: &str, remainder: &mut String, metadata: &Metadata) -
         }
     }
 
-    // Filter out empty lines and parse them
+    // Filter out empty lines, parse them and group partial and continuation lines into records.
+    // The last record stays in the assembler, it may continue in the next chunk.
     log_lines
         .into_iter()
         .filter(|s| !s.trim().is_empty())
-        .map(|line| LogRecord::from((&line, metadata)))
+        .filter_map(|line| assembler.push(parse_line(&line)))
+        .map(|record| LogRecord::from((record, metadata)))
         .collect()
 }
@@ -0,0 +1,280 @@
+use std::collections::HashSet;
+use std::env::var;
+use std::num::ParseIntError;
+
+use serde_json::Value;
+use shared::{format_key, log_error, AsyncRedisConnection, DbName};
+use thiserror::Error;
+use tracing::info;
+
+const DEFAULT_TTL_SECONDS: &str = "3600";
+// longer than a request takes to produce its items
+const DEFAULT_PENDING_SECONDS: &str = "300";
+const DEFAULT_MAX_ENTRIES: &str = "100000";
+
+#[derive(Error, Debug)]
+pub enum DeduplicationConfigError {
+    #[error("Failed to parse deduplication setting: {0}")]
+    ParseIntError(#[from] ParseIntError),
+    #[error("Deduplication setting {0} must be positive")]
+    NotPositive(&'static str),
+}
+
+// Drops the items of the intake routes that were already received, e.g. when an agent re-sends a
+// batch after a timeout. The keys of the items of a customer are kept in a Redis sorted set for
+// each kind of data, e.g. `seen_resource_<customer_id>`, for `DATA_INTAKE_DEDUP_TTL_SECONDS` and
+// at most `DATA_INTAKE_DEDUP_MAX_ENTRIES`. A request first reserves the keys of its items in
+// `pending_resource_<customer_id>`, so a retry that arrives while the first attempt is still
+// producing is dropped as well. The keys are confirmed once the items were produced and released
+// if producing failed, so a batch that failed is accepted again when it is re-sent. A reservation
+// of a request that never settles ends after `DATA_INTAKE_DEDUP_PENDING_SECONDS`. Items without a
+// key are never dropped, neither are the items of a request during which Redis fails.
+pub struct Deduplication {
+    ttl_seconds: i64,
+    pending_seconds: i64,
+    max_entries: isize,
+    redis: AsyncRedisConnection,
+}
+
+impl Deduplication {
+    pub fn new(
+        ttl_seconds: i64,
+        pending_seconds: i64,
+        max_entries: isize,
+        redis: AsyncRedisConnection,
+    ) -> Self {
+        Deduplication {
+            ttl_seconds,
+            pending_seconds,
+            max_entries,
+            redis,
+        }
+    }
+
+    pub fn from_env(redis: AsyncRedisConnection) -> Result<Self, DeduplicationConfigError> {
+        let ttl_seconds = var("DATA_INTAKE_DEDUP_TTL_SECONDS")
+            .unwrap_or(DEFAULT_TTL_SECONDS.to_string())
+            .parse::<i64>()?;
+        let pending_seconds = var("DATA_INTAKE_DEDUP_PENDING_SECONDS")
+            .unwrap_or(DEFAULT_PENDING_SECONDS.to_string())
+            .parse::<i64>()?;
+        let max_entries = var("DATA_INTAKE_DEDUP_MAX_ENTRIES")
+            .unwrap_or(DEFAULT_MAX_ENTRIES.to_string())
+            .parse::<isize>()?;
+        if ttl_seconds <= 0 {
+            return Err(DeduplicationConfigError::NotPositive(
+                "DATA_INTAKE_DEDUP_TTL_SECONDS",
+            ));
+        }
+        if pending_seconds <= 0 {
+            return Err(DeduplicationConfigError::NotPositive(
+                "DATA_INTAKE_DEDUP_PENDING_SECONDS",
+            ));
+        }
+        if max_entries <= 0 {
+            return Err(DeduplicationConfigError::NotPositive(
+                "DATA_INTAKE_DEDUP_MAX_ENTRIES",
+            ));
+        }
+        Ok(Deduplication::new(
+            ttl_seconds,
+            pending_seconds,
+            max_entries,
+            redis,
+        ))
+    }
+
+    // The `reserve` function returns the items whose key was neither seen before nor is reserved
+    // by another request, in their order, and the keys it reserved for them. The first of several
+    // items with the same key in a batch is kept. The reserved keys must be passed to `settle`.
+    pub async fn reserve(
+        &self,
+        customer_id: &str,
+        db: DbName,
+        items: Vec<Value>,
+        key: fn(&Value) -> Option<String>,
+    ) -> (Vec<Value>, Vec<String>) {
+        let keys: Vec<Option<String>> = items.iter().map(key).collect();
+        let members: Vec<&str> = keys.iter().flatten().map(String::as_str).collect();
+        if members.is_empty() {
+            return (items, vec![]);
+        }
+
+        let (seen, pending) = sets(customer_id, &db);
+        let reserved = self
+            .redis
+            .reserve(
+                &seen,
+                &pending,
+                &members,
+                self.ttl_seconds,
+                self.pending_seconds,
+            )
+            .await
+            .map_err(|e| log_error!(e));
+        let Ok(reserved) = reserved else {
+            return (items, vec![]);
+        };
+
+        // `reserved` has an entry for each item with a key, a repeated key is reserved only once
+        let mut reserved = reserved.into_iter();
+        let total = items.len();
+        let mut reserved_keys = Vec::new();
+        let mut unseen = Vec::new();
+        for (item, key) in items.into_iter().zip(keys) {
+            match key {
+                Some(key) => {
+                    if reserved.next().unwrap_or(false) {
+                        reserved_keys.push(key);
+                        unseen.push(item);
+                    }
+                }
+                None => unseen.push(item),
+            }
+        }
+        if unseen.len() < total {
+            info!(
+                "Skipped {} duplicate {} items of customer {}",
+                total - unseen.len(),
+                db,
+                customer_id
+            );
+        }
+        (unseen, reserved_keys)
+    }
+
+    // The `settle` function ends the reservation of the `reserved` keys once the route is done with
+    // the items. If they were produced, the `produced` keys are marked as seen and later items with
+    // one of them are dropped by `reserve`. The other keys, or all of them if producing failed, are
+    // released. Returns the result of producing.
+    pub async fn settle<E>(
+        &self,
+        customer_id: &str,
+        db: DbName,
+        reserved: &[String],
+        produced: Result<Vec<String>, E>,
+    ) -> Result<(), E> {
+        if reserved.is_empty() {
+            return produced.map(|_| ());
+        }
+        let confirmed: HashSet<&str> = match &produced {
+            Ok(keys) => keys.iter().map(String::as_str).collect(),
+            Err(_) => HashSet::new(),
+        };
+        let (confirmed, released): (Vec<&str>, Vec<&str>) = reserved
+            .iter()
+            .map(String::as_str)
+            .partition(|key| confirmed.contains(key));
+
+        let (seen, pending) = sets(customer_id, &db);
+        self.redis
+            .settle(
+                &seen,
+                &pending,
+                &confirmed,
+                &released,
+                self.ttl_seconds,
+                self.max_entries,
+            )
+            .await
+            .map_err(|e| log_error!(e))
+            .ok();
+        produced.map(|_| ())
+    }
+}
+
+// The sorted sets of the seen and of the reserved keys of the items of a customer.
+fn sets(customer_id: &str, db: &DbName) -> (String, String) {
+    let db = db.to_string();
+    (
+        format_key("seen", Some(&db), customer_id),
+        format_key("pending", Some(&db), customer_id),
+    )
+}
+
+// The key of a resource in the data of the agent, its uid and resource version. A resource that
+// changed has a new resource version, even if it is deleted.
+pub fn resource_key(data: &Value) -> Option<String> {
+    let metadata = data.pointer("/json/metadata")?;
+    let uid = metadata.get("uid")?.as_str()?;
+    let resource_version = metadata.get("resourceVersion")?.as_str()?;
+    Some(format!("{}:{}", uid, resource_version))
+}
+
+// The key of an event in the data of the agent, its uid and how often it occurred. A repeated
+// event keeps its uid and its count is incremented, `series.count` for `events.k8s.io/v1`.
+pub fn event_key(data: &Value) -> Option<String> {
+    let event = data.get("json")?;
+    let uid = event.pointer("/metadata/uid")?.as_str()?;
+    let count = event
+        .get("count")
+        .or_else(|| event.pointer("/series/count"))
+        .and_then(Value::as_i64)
+        .unwrap_or(1);
+    Some(format!("{}:{}", uid, count))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use serde_json::json;
+    use shared::RedisConnectionError;
+    use uuid7::uuid4;
+
+    #[rstest]
+    #[case(json!({"json": {"metadata": {"uid": "4b1f", "resourceVersion": "1742"}}}), Some("4b1f:1742"))]
+    #[case(json!({"json": {"metadata": {"uid": "4b1f"}}}), None)]
+    #[case(json!({"json": {"metadata": {"resourceVersion": "1742"}}}), None)]
+    #[case(json!({"metadata": {"uid": "4b1f", "resourceVersion": "1742"}}), None)]
+    fn test_resource_key(#[case] data: Value, #[case] expected: Option<&str>) {
+        assert_eq!(resource_key(&data).as_deref(), expected);
+    }
+
+    #[rstest]
+    #[case(json!({"json": {"metadata": {"uid": "9c2e"}, "count": 3}}), Some("9c2e:3"))]
+    #[case(json!({"json": {"metadata": {"uid": "9c2e"}, "series": {"count": 5}}}), Some("9c2e:5"))]
+    #[case(json!({"json": {"metadata": {"uid": "9c2e"}}}), Some("9c2e:1"))]
+    #[case(json!({"json": {"metadata": {}, "count": 3}}), None)]
+    fn test_event_key(#[case] data: Value, #[case] expected: Option<&str>) {
+        assert_eq!(event_key(&data).as_deref(), expected);
+    }
+
+    #[tokio::test]
+    #[rstest]
+    async fn test_reserve_and_settle() -> Result<(), RedisConnectionError> {
+        let dedup = Deduplication::new(3600, 300, 1000, AsyncRedisConnection::new().await?);
+        let customer_id = uuid4().to_string();
+        let resource = json!({"json": {"metadata": {"uid": "4b1f", "resourceVersion": "1742"}}});
+        let reserve = || {
+            dedup.reserve(
+                &customer_id,
+                DbName::Resource,
+                vec![resource.clone(), resource.clone()],
+                resource_key,
+            )
+        };
+
+        // a repeated item and a concurrent retry are dropped while the key is reserved
+        let (items, reserved) = reserve().await;
+        assert_eq!(items.len(), 1);
+        assert_eq!(reserved, vec!["4b1f:1742".to_string()]);
+        assert!(reserve().await.0.is_empty());
+
+        // a failed request releases the key, a produced one confirms it
+        let failed: Result<Vec<String>, ()> = Err(());
+        assert!(dedup
+            .settle(&customer_id, DbName::Resource, &reserved, failed)
+            .await
+            .is_err());
+        let (items, reserved) = reserve().await;
+        assert_eq!(items.len(), 1);
+        let produced: Result<Vec<String>, ()> = Ok(reserved.clone());
+        assert!(dedup
+            .settle(&customer_id, DbName::Resource, &reserved, produced)
+            .await
+            .is_ok());
+        assert!(reserve().await.0.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,215 @@
+use std::io::Read;
+
+use flate2::read::GzDecoder;
+use rocket::data::{ByteUnit, Limits, ToByteUnit};
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome};
+use rocket::{Data, Request};
+use serde::de::DeserializeOwned;
+use shared::constant::DATA_INTAKE_LIMIT_MEMIBYTES;
+use shared::log_error;
+use thiserror::Error;
+
+use crate::error::DataIntakeError;
+use crate::process::quota::IntakeQuota;
+
+#[derive(Error, Debug)]
+pub enum EncodingError {
+    #[error("Unsupported content encoding: {0}")]
+    Unsupported(String),
+    #[error("Failed to decompress request body: {0}")]
+    Decompression(#[source] std::io::Error),
+    #[error("Decompressed body exceeds the limit of {0} bytes")]
+    TooLarge(u64),
+}
+
+// The `Content-Encoding` of a request body. Bodies are decompressed before they are parsed, the
+// intake limit applies to both the compressed and the decompressed size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentEncoding {
+    Identity,
+    Gzip,
+    Zstd,
+}
+
+impl ContentEncoding {
+    pub fn parse(header: Option<&str>) -> Result<Self, EncodingError> {
+        match header.map(|header| header.trim().to_ascii_lowercase()) {
+            None => Ok(ContentEncoding::Identity),
+            Some(encoding) => match encoding.as_str() {
+                "" | "identity" => Ok(ContentEncoding::Identity),
+                "gzip" | "x-gzip" => Ok(ContentEncoding::Gzip),
+                "zstd" => Ok(ContentEncoding::Zstd),
+                _ => Err(EncodingError::Unsupported(encoding)),
+            },
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ContentEncoding {
+    type Error = DataIntakeError;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        match ContentEncoding::parse(request.headers().get_one("Content-Encoding")) {
+            Ok(encoding) => Outcome::Success(encoding),
+            Err(e) => Outcome::Error((
+                Status::UnsupportedMediaType,
+                DataIntakeError::Encoding(log_error!(e)),
+            )),
+        }
+    }
+}
+
+// The `read_body` function reads and decompresses a request body of at most the intake limit.
+// The decompressed body is counted against the quota of the customer.
+pub async fn read_body(
+    data: Data<'_>,
+    encoding: ContentEncoding,
+    quota: &IntakeQuota<'_>,
+) -> Result<Vec<u8>, DataIntakeError> {
+    read_limited(
+        data,
+        encoding,
+        quota,
+        DATA_INTAKE_LIMIT_MEMIBYTES.mebibytes(),
+    )
+    .await
+}
+
+// The `json_limit` function returns the `json` limit of Rocket, 1 MiB unless configured
+// otherwise, which also limits the bodies of the `Json` data guard.
+pub fn json_limit(limits: &Limits) -> ByteUnit {
+    limits.get("json").unwrap_or(Limits::JSON)
+}
+
+// The `read_json` function reads a JSON request body of at most `limit`, compressed or not.
+pub async fn read_json<T: DeserializeOwned>(
+    data: Data<'_>,
+    encoding: ContentEncoding,
+    quota: &IntakeQuota<'_>,
+    limit: ByteUnit,
+) -> Result<T, DataIntakeError> {
+    let body = read_limited(data, encoding, quota, limit).await?;
+    serde_json::from_slice(&body).map_err(|e| DataIntakeError::InvalidBody(log_error!(e)))
+}
+
+async fn read_limited(
+    data: Data<'_>,
+    encoding: ContentEncoding,
+    quota: &IntakeQuota<'_>,
+    limit: ByteUnit,
+) -> Result<Vec<u8>, DataIntakeError> {
+    let mut buffer = Vec::new();
+    let result = data.open(limit).stream_to(&mut buffer).await;
+    match result {
+        Ok(n) => {
+            if !n.complete {
+                return Err(log_error!(DataIntakeError::PayloadTooLarge(
+                    limit.to_string()
+                )));
+            }
+        }
+        Err(e) => {
+            return Err(DataIntakeError::MultipartIoError(log_error!(e)));
+        }
+    }
+    // decompressing up to the limit takes a while, it must not block the async workers
+    let body = match encoding {
+        ContentEncoding::Identity => buffer,
+        _ => tokio::task::spawn_blocking(move || decode(buffer, encoding, limit.as_u64()))
+            .await?
+            .map_err(|e| DataIntakeError::Encoding(log_error!(e)))?,
+    };
+    quota.add_bytes(body.len()).await?;
+    Ok(body)
+}
+
+// Decompression stops one byte after the limit, a body that expands beyond it is rejected without
+// being decompressed completely.
+fn decode(body: Vec<u8>, encoding: ContentEncoding, limit: u64) -> Result<Vec<u8>, EncodingError> {
+    let decoder: Box<dyn Read + '_> = match encoding {
+        ContentEncoding::Identity => return Ok(body),
+        ContentEncoding::Gzip => Box::new(GzDecoder::new(body.as_slice())),
+        ContentEncoding::Zstd => Box::new(
+            zstd::stream::read::Decoder::new(body.as_slice())
+                .map_err(EncodingError::Decompression)?,
+        ),
+    };
+    let mut decoded = Vec::new();
+    decoder
+        .take(limit + 1)
+        .read_to_end(&mut decoded)
+        .map_err(EncodingError::Decompression)?;
+    if decoded.len() as u64 > limit {
+        return Err(EncodingError::TooLarge(limit));
+    }
+    Ok(decoded)
+}
+
+// compresses test bodies for the routes
+#[cfg(test)]
+pub fn encode(body: &[u8], encoding: ContentEncoding) -> Vec<u8> {
+    use flate2::{write::GzEncoder, Compression};
+    use std::io::Write;
+
+    match encoding {
+        ContentEncoding::Identity => body.to_vec(),
+        ContentEncoding::Gzip => {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(body).unwrap();
+            encoder.finish().unwrap()
+        }
+        ContentEncoding::Zstd => zstd::encode_all(body, 0).unwrap(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(None, ContentEncoding::Identity)]
+    #[case(Some("gzip"), ContentEncoding::Gzip)]
+    #[case(Some(" ZSTD "), ContentEncoding::Zstd)]
+    fn test_parse_content_encoding(
+        #[case] header: Option<&str>,
+        #[case] expected: ContentEncoding,
+    ) {
+        assert_eq!(ContentEncoding::parse(header).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_json_limit() {
+        assert_eq!(json_limit(&Limits::default()), 1.mebibytes());
+        let limits = Limits::default().limit("json", 4.mebibytes());
+        assert_eq!(json_limit(&limits), 4.mebibytes());
+    }
+
+    #[rstest]
+    #[case(ContentEncoding::Identity)]
+    #[case(ContentEncoding::Gzip)]
+    #[case(ContentEncoding::Zstd)]
+    fn test_decode_limit(#[case] encoding: ContentEncoding) {
+        let body = "a".repeat(1024);
+        let encoded = encode(body.as_bytes(), encoding);
+        assert_eq!(
+            decode(encoded.clone(), encoding, 1024).unwrap(),
+            body.as_bytes()
+        );
+
+        if encoding != ContentEncoding::Identity {
+            // the compressed body is within the limit, the decompressed body is not
+            assert!(encoded.len() < 1023);
+            assert!(matches!(
+                decode(encoded, encoding, 1023),
+                Err(EncodingError::TooLarge(1023))
+            ));
+        }
+        assert!(matches!(
+            ContentEncoding::parse(Some("br")),
+            Err(EncodingError::Unsupported(_))
+        ));
+    }
+}
@@ -0,0 +1,412 @@
+use std::collections::{BTreeMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use shared::{format_key, log_error, AsyncRedisConnection, RedisConnectionError};
+use thiserror::Error;
+use tracing::info;
+
+// Custom resources that change too often to be useful, e.g. the endpoints of Cilium, the sources
+// of Flux or the reports of a policy engine.
+const HIGH_CHURN_KINDS: [&str; 8] = [
+    "partition",
+    "kustomization",
+    "ciliumendpoint",
+    "ciliumidentity",
+    "gitrepository",
+    "helmchart",
+    "policyreport",
+    "ephemeralreport",
+];
+
+#[derive(Error, Debug)]
+pub enum FilterError {
+    #[error("Failed to access filter rules: {0}")]
+    Redis(#[from] RedisConnectionError),
+    #[error("Failed to parse filter rules: {0}")]
+    JsonParseError(#[from] serde_json::Error),
+    #[error("Invalid filter rule {0:?}: {1}")]
+    InvalidRule(String, &'static str),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FilterAction {
+    Allow,
+    Deny,
+}
+
+// A rule matches an object if all of its matchers that are set match: the kind (ignoring case),
+// the apiVersion and the namespace are one of the listed ones, the object has all of the labels
+// and one of its owner references is of one of the owner kinds. With `single_owner`, that owner
+// reference must be the only one of the object.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FilterRule {
+    pub name: String,
+    pub action: FilterAction,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub kinds: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub api_versions: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub namespaces: Vec<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub owner_kinds: Vec<String>,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub single_owner: bool,
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+// The types of Kubernetes objects of the intake, each has its own built-in rules that apply to
+// its single and batch routes alike.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObjectType {
+    Resource,
+    CustomResource,
+}
+
+impl ObjectType {
+    pub const ALL: [ObjectType; 2] = [ObjectType::Resource, ObjectType::CustomResource];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ObjectType::Resource => "resource",
+            ObjectType::CustomResource => "customresource",
+        }
+    }
+}
+
+impl FilterRule {
+    fn deny(name: &str, reason: &str) -> Self {
+        FilterRule {
+            name: name.to_string(),
+            action: FilterAction::Deny,
+            reason: Some(reason.to_string()),
+            kinds: vec![],
+            api_versions: vec![],
+            namespaces: vec![],
+            labels: BTreeMap::new(),
+            owner_kinds: vec![],
+            single_owner: false,
+        }
+    }
+
+    pub fn matches(&self, object: &Value) -> bool {
+        let field = |pointer: &str| {
+            object
+                .pointer(pointer)
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+        };
+        let kind = field("/kind");
+        let owner_kinds: Vec<&str> = object
+            .pointer("/metadata/ownerReferences")
+            .and_then(Value::as_array)
+            .map(|owners| {
+                owners
+                    .iter()
+                    .filter_map(|owner| owner.get("kind").and_then(Value::as_str))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        (self.kinds.is_empty() || self.kinds.iter().any(|k| k.eq_ignore_ascii_case(kind)))
+            && (self.api_versions.is_empty()
+                || self.api_versions.iter().any(|v| v == field("/apiVersion")))
+            && (self.namespaces.is_empty()
+                || self
+                    .namespaces
+                    .iter()
+                    .any(|ns| ns == field("/metadata/namespace")))
+            && self.labels.iter().all(|(key, value)| {
+                object
+                    .pointer("/metadata/labels")
+                    .and_then(|labels| labels.get(key))
+                    .and_then(Value::as_str)
+                    == Some(value.as_str())
+            })
+            && (self.owner_kinds.is_empty()
+                || self
+                    .owner_kinds
+                    .iter()
+                    .any(|owner| owner_kinds.contains(&owner.as_str())))
+            && (!self.single_owner || owner_kinds.len() == 1)
+    }
+
+    pub fn reason(&self) -> String {
+        self.reason
+            .clone()
+            .unwrap_or_else(|| format!("Denied by rule {}", self.name))
+    }
+}
+
+// The rules of an object type that apply to all customers after their own rules. Resources owned
+// by a Job are skipped, e.g. its pods, and so are custom resources of high-churn kinds.
+pub fn builtin_rules(object_type: ObjectType) -> Vec<FilterRule> {
+    match object_type {
+        ObjectType::Resource => vec![FilterRule {
+            owner_kinds: vec!["Job".to_string()],
+            single_owner: true,
+            ..FilterRule::deny("job-owned", "Owned by a Job")
+        }],
+        ObjectType::CustomResource => vec![FilterRule {
+            kinds: HIGH_CHURN_KINDS.into_iter().map(str::to_string).collect(),
+            ..FilterRule::deny("high-churn-kinds", "Kind changes too often to be useful")
+        }],
+    }
+}
+
+// The `validate` function checks that the rules of a customer have unique names.
+pub fn validate(rules: &[FilterRule]) -> Result<(), FilterError> {
+    let mut names = HashSet::new();
+    for rule in rules {
+        if rule.name.trim().is_empty() {
+            return Err(FilterError::InvalidRule(
+                rule.name.clone(),
+                "name must not be empty",
+            ));
+        }
+        if !names.insert(rule.name.as_str()) {
+            return Err(FilterError::InvalidRule(
+                rule.name.clone(),
+                "name must be unique",
+            ));
+        }
+    }
+    Ok(())
+}
+
+// The rules of a customer followed by the built-in rules. The first rule that matches an object
+// decides whether it is kept, e.g. a customer can allow the pods of its Jobs. Objects that no rule
+// matches are kept.
+pub struct Filter {
+    rules: Vec<FilterRule>,
+}
+
+impl Filter {
+    pub fn new(customer_rules: Vec<FilterRule>, object_type: ObjectType) -> Self {
+        let mut rules = customer_rules;
+        rules.extend(builtin_rules(object_type));
+        Filter { rules }
+    }
+
+    // The `check` function returns the rule that denies the object, if any.
+    pub fn check(&self, object: &Value) -> Option<&FilterRule> {
+        self.rules
+            .iter()
+            .find(|rule| rule.matches(object))
+            .filter(|rule| rule.action == FilterAction::Deny)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SkippedItem {
+    pub kind: String,
+    pub namespace: String,
+    pub name: String,
+    pub rule: String,
+    pub reason: String,
+}
+
+// The response of the resource routes, which items of a request were stored and why the others
+// were not.
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct IntakeReport {
+    pub accepted: usize,
+    pub duplicates: usize,
+    pub skipped: Vec<SkippedItem>,
+}
+
+impl IntakeReport {
+    // The `admit` function counts the object as accepted, or as skipped if a rule denies it.
+    pub fn admit(&mut self, filter: &Filter, object: &Value) -> bool {
+        let Some(rule) = filter.check(object) else {
+            self.accepted += 1;
+            return true;
+        };
+        let field = |pointer: &str| {
+            object
+                .pointer(pointer)
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string()
+        };
+        let skipped = SkippedItem {
+            kind: field("/kind"),
+            namespace: field("/metadata/namespace"),
+            name: field("/metadata/name"),
+            rule: rule.name.clone(),
+            reason: rule.reason(),
+        };
+        info!(
+            "Skipping {} {}/{}: {}",
+            skipped.kind, skipped.namespace, skipped.name, skipped.reason
+        );
+        self.skipped.push(skipped);
+        false
+    }
+}
+
+// The filter rules of the customers, stored as JSON in Redis, e.g. `intake_filters_<customer_id>`.
+pub struct IntakeFilters {
+    redis: AsyncRedisConnection,
+}
+
+impl IntakeFilters {
+    pub fn new(redis: AsyncRedisConnection) -> Self {
+        IntakeFilters { redis }
+    }
+
+    pub async fn rules(&self, customer_id: &str) -> Result<Vec<FilterRule>, FilterError> {
+        match self.redis.get::<String>(&key(customer_id)).await? {
+            Some(rules) => Ok(serde_json::from_str(&rules)?),
+            None => Ok(vec![]),
+        }
+    }
+
+    pub async fn set_rules(
+        &self,
+        customer_id: &str,
+        rules: &[FilterRule],
+    ) -> Result<(), FilterError> {
+        validate(rules)?;
+        let rules = serde_json::to_string(rules)?;
+        self.redis.set(&key(customer_id), &rules).await?;
+        Ok(())
+    }
+
+    pub async fn delete_rules(&self, customer_id: &str) -> Result<(), FilterError> {
+        self.redis.delete(&key(customer_id)).await?;
+        Ok(())
+    }
+
+    // Failing to read the rules of the customer does not fail the intake, the built-in rules apply.
+    pub async fn filter(&self, customer_id: &str, object_type: ObjectType) -> Filter {
+        let rules = self
+            .rules(customer_id)
+            .await
+            .map_err(|e| log_error!(e))
+            .unwrap_or_default();
+        Filter::new(rules, object_type)
+    }
+}
+
+fn key(customer_id: &str) -> String {
+    format_key("intake_filters", None, customer_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use serde_json::json;
+
+    fn job_pod() -> Value {
+        json!({
+            "apiVersion": "v1",
+            "kind": "Pod",
+            "metadata": {
+                "name": "backup-28519200-x7k2p",
+                "namespace": "ops",
+                "labels": {"app": "backup", "tier": "batch"},
+                "ownerReferences": [{"kind": "Job", "name": "backup-28519200"}]
+            }
+        })
+    }
+
+    fn rule(value: Value) -> FilterRule {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[rstest]
+    #[case(json!({"name": "all", "action": "deny"}), true)]
+    #[case(json!({"name": "r", "action": "deny", "kinds": ["pod"]}), true)]
+    #[case(json!({"name": "r", "action": "deny", "kinds": ["Deployment"]}), false)]
+    #[case(json!({"name": "r", "action": "deny", "api_versions": ["v1"]}), true)]
+    #[case(json!({"name": "r", "action": "deny", "namespaces": ["ops", "ci"]}), true)]
+    #[case(json!({"name": "r", "action": "deny", "namespaces": ["shop"]}), false)]
+    #[case(json!({"name": "r", "action": "deny", "labels": {"app": "backup"}}), true)]
+    #[case(json!({"name": "r", "action": "deny", "labels": {"app": "backup", "tier": "web"}}), false)]
+    #[case(json!({"name": "r", "action": "deny", "owner_kinds": ["Job", "CronJob"]}), true)]
+    #[case(json!({"name": "r", "action": "deny", "owner_kinds": ["ReplicaSet"]}), false)]
+    #[case(json!({"name": "r", "action": "deny", "owner_kinds": ["Job"], "single_owner": true}), true)]
+    #[case(json!({"name": "r", "action": "deny", "kinds": ["Pod"], "namespaces": ["shop"]}), false)]
+    fn test_matches(#[case] rule_value: Value, #[case] expected: bool) {
+        assert_eq!(rule(rule_value).matches(&job_pod()), expected);
+    }
+
+    #[rstest]
+    #[case(json!({"kind": "PolicyReport", "metadata": {"name": "r"}}), ObjectType::CustomResource, Some("high-churn-kinds"))]
+    #[case(json!({"kind": "Kustomization", "metadata": {"name": "k"}}), ObjectType::CustomResource, Some("high-churn-kinds"))]
+    #[case(json!({"kind": "Certificate", "metadata": {"name": "c"}}), ObjectType::CustomResource, None)]
+    #[case(json!({"kind": "Kustomization", "metadata": {"name": "k"}}), ObjectType::Resource, None)]
+    #[case(job_pod(), ObjectType::Resource, Some("job-owned"))]
+    #[case(job_pod(), ObjectType::CustomResource, None)]
+    #[case(json!({"kind": "Pod", "metadata": {"name": "api", "ownerReferences": [{"kind": "Job"}, {"kind": "Workflow"}]}}), ObjectType::Resource, None)]
+    #[case(json!({"kind": "Pod", "metadata": {"name": "api", "ownerReferences": [{"kind": "ReplicaSet"}]}}), ObjectType::Resource, None)]
+    fn test_builtin_rules(
+        #[case] object: Value,
+        #[case] object_type: ObjectType,
+        #[case] expected: Option<&str>,
+    ) {
+        let filter = Filter::new(vec![], object_type);
+        assert_eq!(
+            filter.check(&object).map(|rule| rule.name.as_str()),
+            expected
+        );
+    }
+
+    #[rstest]
+    fn test_customer_rules() {
+        let filter = Filter::new(
+            vec![
+                rule(json!({"name": "backups", "action": "allow", "labels": {"app": "backup"}})),
+                rule(json!({"name": "ci", "action": "deny", "namespaces": ["ci"]})),
+            ],
+            ObjectType::Resource,
+        );
+
+        // a customer rule allows what a built-in rule denies
+        assert_eq!(filter.check(&job_pod()), None);
+
+        let object = json!({"kind": "Pod", "metadata": {"name": "runner", "namespace": "ci"}});
+        let mut report = IntakeReport::default();
+        assert!(report.admit(&filter, &job_pod()));
+        assert!(!report.admit(&filter, &object));
+        assert_eq!(
+            report,
+            IntakeReport {
+                accepted: 1,
+                duplicates: 0,
+                skipped: vec![SkippedItem {
+                    kind: "Pod".to_string(),
+                    namespace: "ci".to_string(),
+                    name: "runner".to_string(),
+                    rule: "ci".to_string(),
+                    reason: "Denied by rule ci".to_string(),
+                }],
+            }
+        );
+    }
+
+    #[rstest]
+    fn test_validate() {
+        let ci = rule(json!({"name": "ci", "action": "deny", "namespaces": ["ci"]}));
+        assert!(validate(std::slice::from_ref(&ci)).is_ok());
+        assert!(matches!(
+            validate(&[ci.clone(), ci]),
+            Err(FilterError::InvalidRule(name, _)) if name == "ci"
+        ));
+        assert!(validate(&[rule(json!({"name": " ", "action": "allow"}))]).is_err());
+        assert!(
+            serde_json::from_value::<FilterRule>(json!({"name": "r", "action": "drop"})).is_err()
+        );
+    }
+}
@@ -0,0 +1,43 @@
+use rocket::http::ContentType;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum FormatError {
+    #[error("Unsupported content type: {0}, expected application/x-protobuf or application/json")]
+    UnsupportedContentType(String),
+}
+
+// The encoding of the body of a collector's push request, like the OTLP/HTTP exporter and the
+// Loki push API send it. Responses are sent in the same encoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PushFormat {
+    Protobuf,
+    Json,
+}
+
+impl PushFormat {
+    pub fn from_content_type(content_type: &ContentType) -> Result<Self, FormatError> {
+        match (content_type.top().as_str(), content_type.sub().as_str()) {
+            ("application", "x-protobuf") => Ok(PushFormat::Protobuf),
+            ("application", "json") => Ok(PushFormat::Json),
+            _ => Err(FormatError::UnsupportedContentType(
+                content_type.to_string(),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("application/x-protobuf", Some(PushFormat::Protobuf))]
+    #[case("application/json; charset=utf-8", Some(PushFormat::Json))]
+    #[case("text/plain", None)]
+    fn test_push_format(#[case] content_type: &str, #[case] expected: Option<PushFormat>) {
+        let content_type = ContentType::parse_flexible(content_type).unwrap();
+        assert_eq!(PushFormat::from_content_type(&content_type).ok(), expected);
+    }
+}
@@ -0,0 +1,60 @@
+use shared::connections::greptime::greptime_connection::GreptimeTable;
+use shared::connections::greptime::middleware::insert::logs_to_insert_request;
+use shared::fluvio::TopicName;
+use shared::log_error;
+use shared::types::metadata::Metadata;
+use shared::types::record::log::LogRecord;
+use shared::{FluvioConnection, GreptimeConnection};
+use tracing::warn;
+
+use crate::error::DataIntakeError;
+
+// The `store_logs` function inserts the logs of a pod into its table in the log database `db` and
+// sends them to the log topic. The database must exist.
+pub async fn store_logs(
+    greptime: &GreptimeConnection,
+    fluvio: &FluvioConnection,
+    customer_id: &str,
+    db: &str,
+    metadata: &Metadata,
+    logs: &mut Vec<LogRecord>,
+) -> Result<(), DataIntakeError> {
+    let topic = TopicName::Log;
+
+    // insert to greptime
+    let table = GreptimeTable::from(metadata);
+    let stream_inserter = greptime.streaming_inserter(db)?;
+    let insert_request = logs_to_insert_request(logs, table);
+    stream_inserter.insert(vec![insert_request]).await?;
+    stream_inserter.finish().await?;
+
+    // send to fluvio
+    for log in logs.iter_mut() {
+        let max_bytes = fluvio.get_topic(topic).max_bytes;
+        log.truncate_record(db, max_bytes);
+        let serialized_record = serde_json::to_string(&log).unwrap();
+        if serialized_record.len() > max_bytes {
+            warn!(
+                "Data too large for record, will be skipped. customer_id: {}, key: {}, record_id: {}, len: {}",
+                customer_id,
+                log.key,
+                log.record_id,
+                serialized_record.len()
+            );
+            continue;
+        }
+        fluvio
+            .get_producer(topic)
+            .send(customer_id.to_owned(), serialized_record)
+            .await
+            .map_err(|e| log_error!(e))
+            .ok();
+        fluvio
+            .get_producer(topic)
+            .flush()
+            .await
+            .map_err(|e| log_error!(e))
+            .ok();
+    }
+    Ok(())
+}
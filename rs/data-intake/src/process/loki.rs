@@ -0,0 +1,365 @@
+use std::collections::BTreeMap;
+
+use prost::{DecodeError, Message};
+use serde::Deserialize;
+use serde_json::Value;
+use shared::constant::DATA_INTAKE_LIMIT_MEMIBYTES;
+use shared::preprocessing::severity::Severity;
+use shared::types::metadata::Metadata;
+use shared::types::record::line::ContainerLine;
+use shared::types::record::log::LogRecord;
+use thiserror::Error;
+
+use crate::process::format::PushFormat;
+
+const NAMESPACE: &str = "namespace";
+const POD_NAME: &str = "pod";
+const POD_UID: &str = "pod_uid";
+const CONTAINER: &str = "container";
+// set by the `cri` and `docker` stages of promtail
+const STREAM: &str = "stream";
+const LEVEL: &str = "level";
+
+#[derive(Error, Debug)]
+pub enum LokiError {
+    #[error("Failed to decompress snappy body: {0}")]
+    Snappy(#[from] snap::Error),
+    #[error("Decompressed body exceeds the limit of {0} bytes")]
+    TooLarge(u64),
+    #[error("Failed to decode protobuf: {0}")]
+    Protobuf(#[from] DecodeError),
+    #[error("Failed to parse JSON: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Invalid stream labels: {0}")]
+    InvalidLabels(String),
+    #[error("Invalid entry: {0}")]
+    InvalidEntry(String),
+    #[error("Invalid entry timestamp: {0}")]
+    InvalidTimestamp(String),
+    #[error("Rejected {0} entries of streams without the labels {NAMESPACE}, {POD_NAME}, {POD_UID} and {CONTAINER}")]
+    MissingLabels(usize),
+}
+
+// The messages of Loki's `push.proto` the intake reads. The body is compressed with snappy in the
+// block format.
+#[derive(Clone, PartialEq, Message)]
+pub struct PushRequest {
+    #[prost(message, repeated, tag = "1")]
+    pub streams: Vec<StreamAdapter>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct StreamAdapter {
+    // labels in the Prometheus format, e.g. `{namespace="shop", pod="cart-0"}`
+    #[prost(string, tag = "1")]
+    pub labels: String,
+    #[prost(message, repeated, tag = "2")]
+    pub entries: Vec<EntryAdapter>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+pub struct EntryAdapter {
+    #[prost(message, optional, tag = "1")]
+    pub timestamp: Option<Timestamp>,
+    #[prost(string, tag = "2")]
+    pub line: String,
+}
+
+// `google.protobuf.Timestamp`
+#[derive(Clone, PartialEq, Message)]
+pub struct Timestamp {
+    #[prost(int64, tag = "1")]
+    pub seconds: i64,
+    #[prost(int32, tag = "2")]
+    pub nanos: i32,
+}
+
+// `{"streams": [{"stream": {"namespace": "shop"}, "values": [["<unix ns>", "line"]]}]}`
+#[derive(Deserialize)]
+struct JsonPushRequest {
+    streams: Vec<JsonStream>,
+}
+
+// the third element of an entry is its structured metadata, which is not read
+#[derive(Deserialize)]
+struct JsonStream {
+    stream: BTreeMap<String, String>,
+    values: Vec<Vec<Value>>,
+}
+
+// A stream of log lines with the same labels, timestamps are in milliseconds.
+#[derive(Debug, PartialEq)]
+pub struct LokiStream {
+    pub labels: BTreeMap<String, String>,
+    pub entries: Vec<(i64, String)>,
+}
+
+// The `decode_streams` function decodes the body of a push request, protobuf bodies are snappy
+// compressed.
+pub fn decode_streams(format: PushFormat, body: &[u8]) -> Result<Vec<LokiStream>, LokiError> {
+    match format {
+        PushFormat::Protobuf => {
+            // the intake limit applies to the decompressed size, like for `Content-Encoding`
+            let limit = DATA_INTAKE_LIMIT_MEMIBYTES * 1024 * 1024;
+            if snap::raw::decompress_len(body)? as u64 > limit {
+                return Err(LokiError::TooLarge(limit));
+            }
+            let body = snap::raw::Decoder::new().decompress_vec(body)?;
+            PushRequest::decode(body.as_slice())?
+                .streams
+                .into_iter()
+                .map(LokiStream::try_from)
+                .collect()
+        }
+        PushFormat::Json => serde_json::from_slice::<JsonPushRequest>(body)?
+            .streams
+            .into_iter()
+            .map(LokiStream::try_from)
+            .collect(),
+    }
+}
+
+impl TryFrom<StreamAdapter> for LokiStream {
+    type Error = LokiError;
+
+    fn try_from(stream: StreamAdapter) -> Result<Self, Self::Error> {
+        let entries = stream
+            .entries
+            .into_iter()
+            .map(|entry| {
+                let timestamp = entry.timestamp.unwrap_or_default();
+                let millis = timestamp.seconds * 1000 + i64::from(timestamp.nanos) / 1_000_000;
+                (millis, entry.line)
+            })
+            .collect();
+        Ok(LokiStream {
+            labels: parse_labels(&stream.labels)?,
+            entries,
+        })
+    }
+}
+
+impl TryFrom<JsonStream> for LokiStream {
+    type Error = LokiError;
+
+    fn try_from(stream: JsonStream) -> Result<Self, Self::Error> {
+        let entries = stream
+            .values
+            .into_iter()
+            .map(|entry| match entry.as_slice() {
+                [Value::String(ts), Value::String(line), ..] => {
+                    let nanos: i64 = ts
+                        .parse()
+                        .map_err(|_| LokiError::InvalidTimestamp(ts.to_owned()))?;
+                    Ok((nanos / 1_000_000, line.to_owned()))
+                }
+                _ => Err(LokiError::InvalidEntry(Value::Array(entry).to_string())),
+            })
+            .collect::<Result<_, LokiError>>()?;
+        Ok(LokiStream {
+            labels: stream.stream,
+            entries,
+        })
+    }
+}
+
+// The `parse_labels` function reads labels in the Prometheus format, values are quoted with the
+// escapes `\"`, `\\` and `\n`.
+pub fn parse_labels(labels: &str) -> Result<BTreeMap<String, String>, LokiError> {
+    let invalid = || LokiError::InvalidLabels(labels.to_owned());
+    let inner = labels
+        .trim()
+        .strip_prefix('{')
+        .and_then(|inner| inner.strip_suffix('}'))
+        .ok_or_else(invalid)?;
+
+    let mut parsed = BTreeMap::new();
+    let mut rest = inner.trim_start();
+    while !rest.is_empty() {
+        let (key, value) = rest.split_once('=').ok_or_else(invalid)?;
+        let mut chars = value
+            .trim_start()
+            .strip_prefix('"')
+            .ok_or_else(invalid)?
+            .chars();
+        let mut value = String::new();
+        loop {
+            match chars.next().ok_or_else(invalid)? {
+                '"' => break,
+                '\\' => match chars.next().ok_or_else(invalid)? {
+                    'n' => value.push('\n'),
+                    escaped => value.push(escaped),
+                },
+                c => value.push(c),
+            }
+        }
+        parsed.insert(key.trim().to_owned(), value);
+        rest = chars.as_str().trim_start();
+        rest = rest.strip_prefix(',').unwrap_or(rest).trim_start();
+    }
+    Ok(parsed)
+}
+
+// The `into_pod_logs` function groups the entries of the streams by their pod, entries of streams
+// without the pod labels are counted as rejected.
+pub fn into_pod_logs(streams: Vec<LokiStream>) -> (Vec<(Metadata, Vec<LogRecord>)>, usize) {
+    let mut pods = Vec::new();
+    let mut rejected = 0;
+    for stream in streams {
+        let Some(metadata) = pod_metadata(&stream.labels) else {
+            rejected += stream.entries.len();
+            continue;
+        };
+        let stream_name = stream
+            .labels
+            .get(STREAM)
+            .and_then(|stream| stream.parse().ok())
+            .unwrap_or_default();
+        let level = stream
+            .labels
+            .get(LEVEL)
+            .and_then(|level| Severity::from_level(level));
+        let logs: Vec<LogRecord> = stream
+            .entries
+            .into_iter()
+            .map(|(timestamp, message)| {
+                let line = ContainerLine {
+                    timestamp: Some(timestamp),
+                    stream: stream_name,
+                    partial: false,
+                    message,
+                };
+                let mut log = LogRecord::from((line, &metadata));
+                if let Some(level) = level {
+                    log.severity = level;
+                }
+                log
+            })
+            .collect();
+        if !logs.is_empty() {
+            pods.push((metadata, logs));
+        }
+    }
+    (pods, rejected)
+}
+
+fn pod_metadata(labels: &BTreeMap<String, String>) -> Option<Metadata> {
+    Some(Metadata::from_pod(
+        labels.get(NAMESPACE)?,
+        labels.get(POD_NAME)?,
+        labels.get(POD_UID)?,
+        labels.get(CONTAINER)?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use shared::types::record::line::Stream;
+
+    const LABELS: &str = r#"{container="cart", namespace="shop", pod="cart-7db6d8ff4d-x7k2p", pod_uid="9c5e1c4a-1d2b-4f5e-8a7b-0c1d2e3f4a5b", stream="stderr"}"#;
+
+    #[rstest]
+    #[case(r#"{}"#, vec![])]
+    #[case(r#"{app="cart"}"#, vec![("app", "cart")])]
+    #[case(
+        r#"{ job = "kube/cart" ,msg="say \"hi\"\n", path="C:\\logs",}"#,
+        vec![("job", "kube/cart"), ("msg", "say \"hi\"\n"), ("path", "C:\\logs")]
+    )]
+    fn test_parse_labels(#[case] labels: &str, #[case] expected: Vec<(&str, &str)>) {
+        let expected: BTreeMap<String, String> = expected
+            .into_iter()
+            .map(|(key, value)| (key.to_owned(), value.to_owned()))
+            .collect();
+        assert_eq!(parse_labels(labels).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case(r#"app="cart""#)]
+    #[case(r#"{app=cart}"#)]
+    #[case(r#"{app="cart}"#)]
+    fn test_parse_invalid_labels(#[case] labels: &str) {
+        assert!(matches!(
+            parse_labels(labels),
+            Err(LokiError::InvalidLabels(_))
+        ));
+    }
+
+    #[rstest]
+    fn test_protobuf_into_pod_logs() {
+        let request = PushRequest {
+            streams: vec![
+                StreamAdapter {
+                    labels: LABELS.to_owned(),
+                    entries: vec![EntryAdapter {
+                        timestamp: Some(Timestamp {
+                            seconds: 1710566898,
+                            nanos: 100412000,
+                        }),
+                        line: "ERROR request failed".to_owned(),
+                    }],
+                },
+                StreamAdapter {
+                    labels: r#"{job="varlogs"}"#.to_owned(),
+                    entries: vec![EntryAdapter::default(), EntryAdapter::default()],
+                },
+            ],
+        };
+        let body = snap::raw::Encoder::new()
+            .compress_vec(&request.encode_to_vec())
+            .unwrap();
+        let (pods, rejected) = into_pod_logs(decode_streams(PushFormat::Protobuf, &body).unwrap());
+
+        assert_eq!(rejected, 2);
+        assert_eq!(pods.len(), 1);
+        let (metadata, logs) = &pods[0];
+        assert_eq!(metadata.namespace, "shop");
+        assert_eq!(metadata.pod_name, "cart-7db6d8ff4d-x7k2p");
+        assert_eq!(metadata.pod_uid, "9c5e1c4a-1d2b-4f5e-8a7b-0c1d2e3f4a5b");
+        assert_eq!(metadata.container, "cart");
+        assert_eq!(logs[0].timestamp, 1710566898100);
+        assert_eq!(logs[0].message, "ERROR request failed");
+        assert_eq!(logs[0].stream, Stream::Stderr);
+        assert_eq!(logs[0].severity, Severity::Error);
+
+        // the body is not snappy compressed
+        assert!(matches!(
+            decode_streams(PushFormat::Protobuf, &request.encode_to_vec()),
+            Err(LokiError::Snappy(_)) | Err(LokiError::TooLarge(_))
+        ));
+    }
+
+    #[rstest]
+    fn test_json_into_pod_logs() {
+        let body = r#"{"streams": [{
+            "stream": {"namespace": "shop", "pod": "cart-0", "pod_uid": "9c5e1c4a", "container": "cart", "level": "warn"},
+            "values": [
+                ["1710566898100412000", "retrying request"],
+                ["1710566898200000000", "request failed", {"trace_id": "0af7651916cd43dd"}]
+            ]
+        }]}"#;
+        let (pods, rejected) =
+            into_pod_logs(decode_streams(PushFormat::Json, body.as_bytes()).unwrap());
+
+        assert_eq!(rejected, 0);
+        let (metadata, logs) = &pods[0];
+        assert_eq!(metadata.path, "/var/log/pods/shop_cart-0_9c5e1c4a/cart");
+        assert_eq!(logs.len(), 2);
+        assert_eq!(logs[0].timestamp, 1710566898100);
+        assert_eq!(logs[1].timestamp, 1710566898200);
+        assert_eq!(logs[1].message, "request failed");
+        assert_eq!(logs[1].severity, Severity::Warn);
+
+        let body = r#"{"streams": [{"stream": {}, "values": [["yesterday", "line"]]}]}"#;
+        assert!(matches!(
+            decode_streams(PushFormat::Json, body.as_bytes()),
+            Err(LokiError::InvalidTimestamp(_))
+        ));
+        let body = r#"{"streams": [{"stream": {}, "values": [[1710566898100412000, "line"]]}]}"#;
+        assert!(matches!(
+            decode_streams(PushFormat::Json, body.as_bytes()),
+            Err(LokiError::InvalidEntry(_))
+        ));
+    }
+}
@@ -1,2 +1,12 @@
 pub mod chunk;
+pub mod dedup;
+pub mod encoding;
+pub mod filter;
+pub mod format;
+pub mod logs;
+pub mod loki;
+pub mod multiline;
 pub mod multipart;
+pub mod otlp;
+pub mod quota;
+pub mod redaction;
@@ -0,0 +1,355 @@
+use regex::Regex;
+use serde_json::Value;
+use shared::types::record::line::{ContainerLine, Stream};
+use std::collections::HashMap;
+use std::env::var;
+use std::num::ParseIntError;
+use thiserror::Error;
+
+const DEFAULT_MAX_GAP_MS: &str = "1000";
+const MAX_LINES_PER_RECORD: usize = 1000;
+// partial lines of a runtime are joined up to this length, e.g. if the final line was lost
+const MAX_PARTIAL_BYTES: usize = 1 << 20;
+const EXCEPTION_SUFFIXES: [&str; 6] = [
+    "Error",
+    "Exception",
+    "Throwable",
+    "Exit",
+    "Interrupt",
+    "Warning",
+];
+
+#[derive(Error, Debug)]
+pub enum MultilineConfigError {
+    #[error("Failed to parse start patterns: {0}")]
+    JsonParseError(#[from] serde_json::Error),
+    #[error("Start patterns must be a JSON object of container names to patterns")]
+    InvalidStartPatterns,
+    #[error("Invalid start pattern for container {0}: {1}")]
+    InvalidPattern(String, #[source] regex::Error),
+    #[error("Failed to parse max gap: {0}")]
+    ParseIntError(#[from] ParseIntError),
+}
+
+// Settings for grouping physical log lines into records.
+// `DATA_INTAKE_MULTILINE_START_PATTERNS` is a JSON object mapping container names to a regular
+// expression matching the first line of a record, e.g. `{"api": "^\\d{4}-\\d{2}-\\d{2} "}`.
+// Containers without a pattern fall back to continuation-line heuristics.
+// `DATA_INTAKE_MULTILINE_MAX_GAP_MS` is the largest gap between two lines of the same record.
+#[derive(Debug)]
+pub struct MultilineConfig {
+    start_patterns: HashMap<String, Regex>,
+    max_gap_ms: i64,
+}
+
+impl MultilineConfig {
+    pub fn new(start_patterns: HashMap<String, Regex>, max_gap_ms: i64) -> Self {
+        MultilineConfig {
+            start_patterns,
+            max_gap_ms,
+        }
+    }
+
+    pub fn from_env() -> Result<Self, MultilineConfigError> {
+        let max_gap_ms = var("DATA_INTAKE_MULTILINE_MAX_GAP_MS")
+            .unwrap_or(DEFAULT_MAX_GAP_MS.to_string())
+            .parse::<i64>()?;
+        let start_patterns = match var("DATA_INTAKE_MULTILINE_START_PATTERNS") {
+            Ok(patterns) => parse_start_patterns(&patterns)?,
+            Err(_) => HashMap::new(),
+        };
+        Ok(MultilineConfig::new(start_patterns, max_gap_ms))
+    }
+
+    pub fn assembler(&self, container: &str) -> MultilineAssembler<'_> {
+        MultilineAssembler {
+            start_pattern: self.start_patterns.get(container),
+            max_gap_ms: self.max_gap_ms,
+            partials: HashMap::new(),
+            record: None,
+        }
+    }
+}
+
+fn parse_start_patterns(patterns: &str) -> Result<HashMap<String, Regex>, MultilineConfigError> {
+    let patterns: Value = serde_json::from_str(patterns)?;
+    let patterns = patterns
+        .as_object()
+        .ok_or(MultilineConfigError::InvalidStartPatterns)?;
+    patterns
+        .iter()
+        .map(|(container, pattern)| {
+            let pattern = pattern
+                .as_str()
+                .ok_or(MultilineConfigError::InvalidStartPatterns)?;
+            let regex = Regex::new(pattern)
+                .map_err(|e| MultilineConfigError::InvalidPattern(container.clone(), e))?;
+            Ok((container.clone(), regex))
+        })
+        .collect()
+}
+
+struct PendingRecord {
+    line: ContainerLine,
+    last_timestamp: Option<i64>,
+    lines: usize,
+    go_trace: bool,
+}
+
+// Groups the parsed lines of one container into records, e.g. a Java exception with its stack
+// trace. Partial lines of the runtime are joined first, per stream as the partial lines of stdout
+// and stderr may interleave. Continuation lines are appended to the first line of the record,
+// which keeps its timestamp and stream.
+pub struct MultilineAssembler<'a> {
+    start_pattern: Option<&'a Regex>,
+    max_gap_ms: i64,
+    partials: HashMap<Stream, ContainerLine>,
+    record: Option<PendingRecord>,
+}
+
+impl MultilineAssembler<'_> {
+    // The `push` function returns the previous record once a line starts a new one.
+    // Blank lines are dropped, they neither start nor end a record.
+    pub fn push(&mut self, line: ContainerLine) -> Option<ContainerLine> {
+        let line = self.join_partial(line)?;
+        if line.message.trim().is_empty() {
+            return None;
+        }
+
+        if self.is_continuation(&line) {
+            if let Some(record) = self.record.as_mut() {
+                record.line.message.push('\n');
+                record.line.message.push_str(&line.message);
+                record.lines += 1;
+                record.last_timestamp = line.timestamp.or(record.last_timestamp);
+                record.go_trace |= is_goroutine_header(&line.message);
+                return None;
+            }
+        }
+
+        self.record
+            .replace(PendingRecord {
+                last_timestamp: line.timestamp,
+                line,
+                lines: 1,
+                go_trace: false,
+            })
+            .map(|record| record.line)
+    }
+
+    // The `flush` function returns the pending record and the lines still waiting for their
+    // final part.
+    pub fn flush(&mut self) -> Vec<ContainerLine> {
+        let mut partials: Vec<ContainerLine> =
+            self.partials.drain().map(|(_, line)| line).collect();
+        partials.sort_by_key(|line| line.timestamp);
+        let mut records: Vec<ContainerLine> = partials
+            .into_iter()
+            .filter_map(|mut line| {
+                line.partial = false;
+                self.push(line)
+            })
+            .collect();
+        records.extend(self.record.take().map(|record| record.line));
+        records
+    }
+
+    // The `join_partial` function returns the line once its final part arrived, the joined
+    // line keeps the timestamp of its first part.
+    fn join_partial(&mut self, line: ContainerLine) -> Option<ContainerLine> {
+        let line = match self.partials.remove(&line.stream) {
+            Some(mut pending) => {
+                pending.message.push_str(&line.message);
+                pending.partial = line.partial;
+                pending
+            }
+            None => line,
+        };
+        if line.partial && line.message.len() < MAX_PARTIAL_BYTES {
+            self.partials.insert(line.stream, line);
+            return None;
+        }
+        Some(line)
+    }
+
+    fn is_continuation(&self, line: &ContainerLine) -> bool {
+        let Some(record) = &self.record else {
+            return false;
+        };
+        if record.lines >= MAX_LINES_PER_RECORD || record.line.stream != line.stream {
+            return false;
+        }
+        if let (Some(previous), Some(current)) = (record.last_timestamp, line.timestamp) {
+            if current - previous > self.max_gap_ms {
+                return false;
+            }
+        }
+        match self.start_pattern {
+            Some(pattern) => !pattern.is_match(&line.message),
+            None => is_continuation_line(&line.message, record.go_trace),
+        }
+    }
+}
+
+fn is_continuation_line(message: &str, go_trace: bool) -> bool {
+    // indented stack frames: `\tat com.example.Main.run(Main.java:12)`, `  File "app.py", line 3`
+    message.starts_with([' ', '\t'])
+        // java
+        || message.starts_with("Caused by: ")
+        || message.starts_with("Suppressed: ")
+        // python, `logger.exception` writes the traceback after the log message
+        || message.starts_with("Traceback (most recent call last):")
+        || message.starts_with("During handling of the above exception")
+        || message.starts_with("The above exception was the direct cause")
+        // go
+        || is_goroutine_header(message)
+        || (go_trace && (message.starts_with("created by ") || is_go_frame(message)))
+        || is_exception_header(message)
+        // closing brackets of a multi-line object, e.g. a node.js error with its properties
+        || message.chars().all(|c| matches!(c, '}' | ']' | ')' | ','))
+}
+
+// e.g. `goroutine 1 [running]:`
+fn is_goroutine_header(message: &str) -> bool {
+    message.starts_with("goroutine ") && message.ends_with("]:")
+}
+
+// e.g. `main.(*Server).handle(0xc000012345, {0x4b2f60, 0x5})`
+fn is_go_frame(message: &str) -> bool {
+    match message.split_once('(') {
+        Some((function, _)) => {
+            !function.is_empty()
+                && !function.contains(char::is_whitespace)
+                && message.ends_with(')')
+        }
+        None => false,
+    }
+}
+
+// e.g. `java.lang.IllegalStateException: boom` or `ValueError: invalid literal`
+fn is_exception_header(message: &str) -> bool {
+    let name = match message.split_once(' ') {
+        Some((name, _)) => match name.strip_suffix(':') {
+            Some(name) => name,
+            None => return false,
+        },
+        None => message.strip_suffix(':').unwrap_or(message),
+    };
+    EXCEPTION_SUFFIXES
+        .iter()
+        .any(|suffix| name.len() > suffix.len() && name.ends_with(suffix))
+        && name.starts_with(|c: char| c.is_ascii_alphabetic())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '_' || c == '$')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use shared::types::record::line::parse_line;
+
+    fn assemble(config: &MultilineConfig, container: &str, lines: &str) -> Vec<ContainerLine> {
+        let mut assembler = config.assembler(container);
+        let mut records: Vec<ContainerLine> = lines
+            .lines()
+            .filter_map(|line| assembler.push(parse_line(line)))
+            .collect();
+        records.extend(assembler.flush());
+        records
+    }
+
+    fn messages(records: &[ContainerLine]) -> Vec<&str> {
+        records.iter().map(|r| r.message.as_str()).collect()
+    }
+
+    #[rstest]
+    #[case(include_str!("../../testdata/multiline/java.log"), 3)]
+    #[case(include_str!("../../testdata/multiline/go.log"), 2)]
+    #[case(include_str!("../../testdata/multiline/python.log"), 3)]
+    #[case(include_str!("../../testdata/multiline/node.log"), 2)]
+    fn test_assemble_runtime_fixtures(#[case] fixture: &str, #[case] expected: usize) {
+        let config = MultilineConfig::new(HashMap::new(), 1000);
+        let records = assemble(&config, "app", fixture);
+
+        assert_eq!(records.len(), expected, "{records:#?}");
+        // every non-blank line ends up in exactly one record
+        let lines = fixture
+            .lines()
+            .filter(|l| !parse_line(l).message.trim().is_empty());
+        assert_eq!(
+            records
+                .iter()
+                .map(|r| r.message.lines().count())
+                .sum::<usize>(),
+            lines.count()
+        );
+        // every record keeps the timestamp of its first line
+        assert!(records.iter().all(|r| r.timestamp.is_some()));
+    }
+
+    #[test]
+    fn test_assemble_start_pattern() {
+        let patterns = parse_start_patterns(r#"{"app": "^\\[\\d+\\]"}"#).unwrap();
+        let config = MultilineConfig::new(patterns, 1000);
+        let lines = "2024-03-16T05:28:18.100Z [1] request failed\n\
+                     2024-03-16T05:28:18.100Z details: upstream closed\n\
+                     2024-03-16T05:28:18.200Z [2] request done";
+
+        let records = assemble(&config, "app", lines);
+        assert_eq!(
+            messages(&records),
+            [
+                "[1] request failed\ndetails: upstream closed",
+                "[2] request done"
+            ]
+        );
+
+        // other containers still use the heuristics
+        assert_eq!(assemble(&config, "sidecar", lines).len(), 3);
+    }
+
+    #[test]
+    fn test_assemble_max_gap() {
+        let config = MultilineConfig::new(HashMap::new(), 1000);
+        let lines = "2024-03-16T05:28:18.100Z ERROR request failed\n\
+                     2024-03-16T05:28:20.100Z   retrying";
+        assert_eq!(assemble(&config, "app", lines).len(), 2);
+    }
+
+    #[test]
+    fn test_assemble_partial_lines() {
+        let config = MultilineConfig::new(HashMap::new(), 1000);
+        let lines = "2024-03-16T07:28:18.100+02:00 stdout P {\"level\":\"info\",\n\
+                     2024-03-16T07:28:18.100+02:00 stderr F ERROR request failed\n\
+                     2024-03-16T07:28:18.101+02:00 stderr F \tat com.example.Main.run(Main.java:12)\n\
+                     2024-03-16T07:28:18.102+02:00 stdout P \"msg\":\n\
+                     2024-03-16T07:28:18.102+02:00 stdout F \"done\"}";
+
+        let records = assemble(&config, "app", lines);
+        assert_eq!(
+            messages(&records),
+            [
+                "ERROR request failed\n\tat com.example.Main.run(Main.java:12)",
+                "{\"level\":\"info\",\"msg\":\"done\"}"
+            ]
+        );
+        assert_eq!(
+            records.iter().map(|r| r.stream).collect::<Vec<_>>(),
+            [Stream::Stderr, Stream::Stdout]
+        );
+        assert!(records.iter().all(|r| r.timestamp == Some(1710566898100)));
+    }
+
+    #[rstest]
+    #[case("java.lang.IllegalStateException: boom", true)]
+    #[case("ValueError: invalid literal for int()", true)]
+    #[case("KeyboardInterrupt", true)]
+    #[case("Error: connect ECONNREFUSED 127.0.0.1:5432", false)]
+    #[case("ERROR: failed to connect", false)]
+    #[case("ConnectionError occurred while reading", false)]
+    fn test_is_exception_header(#[case] message: &str, #[case] expected: bool) {
+        assert_eq!(is_exception_header(message), expected);
+    }
+}
@@ -1,13 +1,13 @@
 use crate::error::DataIntakeError;
 
 use super::chunk::process_chunk;
+use super::encoding::{read_body, ContentEncoding};
+use super::multiline::MultilineConfig;
+use super::quota::IntakeQuota;
 use multipart::server::{Multipart, MultipartData};
-use rocket::data::ToByteUnit;
 use rocket::http::ContentType;
 use rocket::Data;
 use serde_json::Value;
-use shared::constant::DATA_INTAKE_LIMIT_MEMIBYTES;
-use shared::log_error;
 use shared::types::metadata::Metadata;
 use shared::types::record::log::LogRecord;
 use std::io::Cursor;
@@ -30,11 +30,13 @@ pub enum MultipartStreamError {
 pub fn process_stream(
     mut data: MultipartData<&mut Multipart<Cursor<Vec<u8>>>>,
     metadata: &Metadata,
+    multiline: &MultilineConfig,
 ) -> Result<Vec<LogRecord>, MultipartStreamError> {
     let key: String = metadata.pod_name.to_owned();
 
     let mut buffer = Vec::new();
     let mut remainder = String::new();
+    let mut assembler = multiline.assembler(&metadata.container);
     let mut logs = Vec::new();
 
     while let Ok(n) = data.read_to_end(&mut buffer) {
@@ -42,8 +44,19 @@ pub fn process_stream(
             break;
         }
         let chunk = from_utf8(&buffer[..n]).inspect_err(|e| error!("{e:?}, {key}"))?;
-        logs.extend(process_chunk(chunk, &mut remainder, metadata));
+        logs.extend(process_chunk(
+            chunk,
+            &mut remainder,
+            &mut assembler,
+            metadata,
+        ));
     }
+    logs.extend(
+        assembler
+            .flush()
+            .into_iter()
+            .map(|record| LogRecord::from((record, metadata))),
+    );
 
     Ok(logs)
 }
@@ -90,23 +103,11 @@ pub fn process_metadata(
 
 pub async fn into_multipart<'a>(
     content_type: &ContentType,
+    encoding: ContentEncoding,
+    quota: &IntakeQuota<'_>,
     data: Data<'a>,
 ) -> Result<Multipart<Cursor<Vec<u8>>>, DataIntakeError> {
-    let mut buffer = Vec::new();
-    let limit = DATA_INTAKE_LIMIT_MEMIBYTES.mebibytes();
-    let result = data.open(limit).stream_to(&mut buffer).await;
-    match result {
-        Ok(n) => {
-            if !n.complete {
-                return Err(log_error!(DataIntakeError::PayloadTooLarge(
-                    limit.to_string()
-                )));
-            }
-        }
-        Err(e) => {
-            return Err(DataIntakeError::MultipartIoError(log_error!(e)));
-        }
-    }
+    let buffer = read_body(data, encoding, quota).await?;
 
     let boundary = content_type
         .params()
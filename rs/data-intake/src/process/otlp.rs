@@ -0,0 +1,503 @@
+use std::fmt::Display;
+use std::str::FromStr;
+
+use prost::{DecodeError, Message, Oneof};
+use rocket::http::ContentType;
+use serde::{de, Deserialize, Deserializer, Serialize};
+use serde_json::{Map, Number, Value};
+use shared::preprocessing::severity::Severity;
+use shared::types::metadata::Metadata;
+use shared::types::record::line::ContainerLine;
+use shared::types::record::log::LogRecord;
+use thiserror::Error;
+
+use crate::process::format::PushFormat;
+
+const NAMESPACE: &str = "k8s.namespace.name";
+const POD_NAME: &str = "k8s.pod.name";
+const POD_UID: &str = "k8s.pod.uid";
+const CONTAINER: &str = "k8s.container.name";
+// set by the container parser of the filelog receiver
+const STREAM: &str = "log.iostream";
+
+#[derive(Error, Debug)]
+pub enum OtlpError {
+    #[error("Failed to decode protobuf: {0}")]
+    Protobuf(#[from] DecodeError),
+    #[error("Failed to parse JSON: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+// The messages of `opentelemetry/proto/collector/logs/v1/logs_service.proto` the intake reads,
+// fields that are not read are skipped when decoding. The JSON encoding uses lowerCamelCase names,
+// 64 bit integers as strings and enums as numbers.
+#[derive(Clone, PartialEq, Message, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ExportLogsServiceRequest {
+    #[prost(message, repeated, tag = "1")]
+    pub resource_logs: Vec<ResourceLogs>,
+}
+
+#[derive(Clone, PartialEq, Message, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ResourceLogs {
+    #[prost(message, optional, tag = "1")]
+    pub resource: Option<Resource>,
+    #[prost(message, repeated, tag = "2")]
+    pub scope_logs: Vec<ScopeLogs>,
+}
+
+#[derive(Clone, PartialEq, Message, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Resource {
+    #[prost(message, repeated, tag = "1")]
+    pub attributes: Vec<KeyValue>,
+}
+
+#[derive(Clone, PartialEq, Message, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ScopeLogs {
+    #[prost(message, repeated, tag = "2")]
+    pub log_records: Vec<OtlpLogRecord>,
+}
+
+// `LogRecord` of `opentelemetry/proto/logs/v1/logs.proto`
+#[derive(Clone, PartialEq, Message, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct OtlpLogRecord {
+    #[prost(fixed64, tag = "1")]
+    #[serde(deserialize_with = "json_int")]
+    pub time_unix_nano: u64,
+    #[prost(fixed64, tag = "11")]
+    #[serde(deserialize_with = "json_int")]
+    pub observed_time_unix_nano: u64,
+    #[prost(int32, tag = "2")]
+    pub severity_number: i32,
+    #[prost(string, tag = "3")]
+    pub severity_text: String,
+    #[prost(message, optional, tag = "5")]
+    pub body: Option<AnyValue>,
+    #[prost(message, repeated, tag = "6")]
+    pub attributes: Vec<KeyValue>,
+}
+
+#[derive(Clone, PartialEq, Message, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct KeyValue {
+    #[prost(string, tag = "1")]
+    pub key: String,
+    #[prost(message, optional, tag = "2")]
+    pub value: Option<AnyValue>,
+}
+
+#[derive(Clone, PartialEq, Message, Deserialize)]
+#[serde(from = "AnyValueJson")]
+pub struct AnyValue {
+    #[prost(oneof = "AnyValueKind", tags = "1, 2, 3, 4, 5, 6, 7")]
+    pub value: Option<AnyValueKind>,
+}
+
+#[derive(Clone, PartialEq, Oneof)]
+pub enum AnyValueKind {
+    #[prost(string, tag = "1")]
+    StringValue(String),
+    #[prost(bool, tag = "2")]
+    BoolValue(bool),
+    #[prost(int64, tag = "3")]
+    IntValue(i64),
+    #[prost(double, tag = "4")]
+    DoubleValue(f64),
+    #[prost(message, tag = "5")]
+    ArrayValue(ArrayValue),
+    #[prost(message, tag = "6")]
+    KvlistValue(KeyValueList),
+    #[prost(bytes = "vec", tag = "7")]
+    BytesValue(Vec<u8>),
+}
+
+#[derive(Clone, PartialEq, Message, Deserialize)]
+#[serde(default)]
+pub struct ArrayValue {
+    #[prost(message, repeated, tag = "1")]
+    pub values: Vec<AnyValue>,
+}
+
+#[derive(Clone, PartialEq, Message, Deserialize)]
+#[serde(default)]
+pub struct KeyValueList {
+    #[prost(message, repeated, tag = "1")]
+    pub values: Vec<KeyValue>,
+}
+
+#[derive(Clone, PartialEq, Message, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportLogsServiceResponse {
+    #[prost(message, optional, tag = "1")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub partial_success: Option<ExportLogsPartialSuccess>,
+}
+
+#[derive(Clone, PartialEq, Message, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportLogsPartialSuccess {
+    #[prost(int64, tag = "1")]
+    pub rejected_log_records: i64,
+    #[prost(string, tag = "2")]
+    pub error_message: String,
+}
+
+// In JSON the value is an object with one of the keys, bytes are kept as their base64 text.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AnyValueJson {
+    string_value: Option<String>,
+    bool_value: Option<bool>,
+    #[serde(default, deserialize_with = "json_int_option")]
+    int_value: Option<i64>,
+    double_value: Option<f64>,
+    array_value: Option<ArrayValue>,
+    kvlist_value: Option<KeyValueList>,
+    bytes_value: Option<String>,
+}
+
+impl From<AnyValueJson> for AnyValue {
+    fn from(json: AnyValueJson) -> Self {
+        let value = if let Some(value) = json.string_value {
+            Some(AnyValueKind::StringValue(value))
+        } else if let Some(value) = json.bool_value {
+            Some(AnyValueKind::BoolValue(value))
+        } else if let Some(value) = json.int_value {
+            Some(AnyValueKind::IntValue(value))
+        } else if let Some(value) = json.double_value {
+            Some(AnyValueKind::DoubleValue(value))
+        } else if let Some(value) = json.array_value {
+            Some(AnyValueKind::ArrayValue(value))
+        } else if let Some(value) = json.kvlist_value {
+            Some(AnyValueKind::KvlistValue(value))
+        } else {
+            json.bytes_value
+                .map(|value| AnyValueKind::BytesValue(value.into_bytes()))
+        };
+        AnyValue { value }
+    }
+}
+
+impl AnyValue {
+    fn as_str(&self) -> Option<&str> {
+        match &self.value {
+            Some(AnyValueKind::StringValue(value)) => Some(value),
+            _ => None,
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        match &self.value {
+            None => Value::Null,
+            Some(AnyValueKind::StringValue(value)) => Value::String(value.to_owned()),
+            Some(AnyValueKind::BoolValue(value)) => Value::Bool(*value),
+            Some(AnyValueKind::IntValue(value)) => Value::Number((*value).into()),
+            Some(AnyValueKind::DoubleValue(value)) => {
+                Number::from_f64(*value).map_or(Value::Null, Value::Number)
+            }
+            Some(AnyValueKind::ArrayValue(array)) => {
+                Value::Array(array.values.iter().map(AnyValue::to_json).collect())
+            }
+            Some(AnyValueKind::KvlistValue(list)) => Value::Object(
+                list.values
+                    .iter()
+                    .map(|kv| {
+                        let value = kv.value.as_ref().map_or(Value::Null, AnyValue::to_json);
+                        (kv.key.to_owned(), value)
+                    })
+                    .collect::<Map<String, Value>>(),
+            ),
+            Some(AnyValueKind::BytesValue(value)) => {
+                Value::String(String::from_utf8_lossy(value).into_owned())
+            }
+        }
+    }
+
+    // string bodies are the message, structured bodies are kept as JSON
+    fn to_message(&self) -> String {
+        match (self.as_str(), self.to_json()) {
+            (Some(message), _) => message.to_owned(),
+            (None, Value::Null) => String::new(),
+            (None, json) => json.to_string(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonInt<T> {
+    Number(T),
+    String(String),
+}
+
+fn json_int<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + FromStr,
+    T::Err: Display,
+{
+    match JsonInt::<T>::deserialize(deserializer)? {
+        JsonInt::Number(value) => Ok(value),
+        JsonInt::String(value) => value.parse().map_err(de::Error::custom),
+    }
+}
+
+fn json_int_option<'de, D>(deserializer: D) -> Result<Option<i64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    json_int(deserializer).map(Some)
+}
+
+// The `decode_request` function decodes the body of an OTLP/HTTP request.
+pub fn decode_request(
+    format: PushFormat,
+    body: &[u8],
+) -> Result<ExportLogsServiceRequest, OtlpError> {
+    match format {
+        PushFormat::Protobuf => Ok(ExportLogsServiceRequest::decode(body)?),
+        PushFormat::Json => Ok(serde_json::from_slice(body)?),
+    }
+}
+
+// The `response` function reports the rejected log records as a partial success, in the
+// encoding of the request.
+pub fn response(format: PushFormat, rejected: i64) -> (ContentType, Vec<u8>) {
+    let response = ExportLogsServiceResponse {
+        partial_success: (rejected > 0).then(|| ExportLogsPartialSuccess {
+            rejected_log_records: rejected,
+            error_message: format!(
+                "log records require the resource attributes {NAMESPACE}, {POD_NAME}, {POD_UID} and {CONTAINER}"
+            ),
+        }),
+    };
+    match format {
+        PushFormat::Protobuf => (
+            ContentType::new("application", "x-protobuf"),
+            response.encode_to_vec(),
+        ),
+        PushFormat::Json => (
+            ContentType::JSON,
+            serde_json::to_vec(&response).unwrap_or_default(),
+        ),
+    }
+}
+
+// The `into_pod_logs` function groups the log records of a request by their pod. The pod is
+// identified by the resource attributes of the k8s attributes processor, records of resources
+// without them are counted as rejected.
+pub fn into_pod_logs(request: ExportLogsServiceRequest) -> (Vec<(Metadata, Vec<LogRecord>)>, i64) {
+    let mut pods = Vec::new();
+    let mut rejected = 0;
+    for resource_logs in request.resource_logs {
+        let records = resource_logs
+            .scope_logs
+            .into_iter()
+            .flat_map(|scope_logs| scope_logs.log_records);
+        match pod_metadata(resource_logs.resource.as_ref()) {
+            Some(metadata) => {
+                let logs: Vec<LogRecord> = records
+                    .map(|record| log_record(record, &metadata))
+                    .collect();
+                if !logs.is_empty() {
+                    pods.push((metadata, logs));
+                }
+            }
+            None => rejected += records.count() as i64,
+        }
+    }
+    (pods, rejected)
+}
+
+fn attribute<'a>(attributes: &'a [KeyValue], key: &str) -> Option<&'a str> {
+    attributes
+        .iter()
+        .find(|kv| kv.key == key)
+        .and_then(|kv| kv.value.as_ref())
+        .and_then(AnyValue::as_str)
+}
+
+fn pod_metadata(resource: Option<&Resource>) -> Option<Metadata> {
+    let attributes = &resource?.attributes;
+    Some(Metadata::from_pod(
+        attribute(attributes, NAMESPACE)?,
+        attribute(attributes, POD_NAME)?,
+        attribute(attributes, POD_UID)?,
+        attribute(attributes, CONTAINER)?,
+    ))
+}
+
+// The time the record was observed is used if the time of the event is unknown. The severity of
+// the record wins over the one extracted from the message.
+fn log_record(record: OtlpLogRecord, metadata: &Metadata) -> LogRecord {
+    let timestamp = [record.time_unix_nano, record.observed_time_unix_nano]
+        .into_iter()
+        .find(|ts| *ts > 0)
+        .map(|ts| (ts / 1_000_000) as i64);
+    let line = ContainerLine {
+        timestamp,
+        stream: attribute(&record.attributes, STREAM)
+            .and_then(|stream| stream.parse().ok())
+            .unwrap_or_default(),
+        partial: false,
+        message: record
+            .body
+            .as_ref()
+            .map(AnyValue::to_message)
+            .unwrap_or_default(),
+    };
+    let mut log = LogRecord::from((line, metadata));
+    if let Some(severity) = severity(record.severity_number, &record.severity_text) {
+        log.severity = severity;
+    }
+    log
+}
+
+// `SeverityNumber` ranges of the log data model
+fn severity(number: i32, text: &str) -> Option<Severity> {
+    match number {
+        1..=4 => Some(Severity::Trace),
+        5..=8 => Some(Severity::Debug),
+        9..=12 => Some(Severity::Info),
+        13..=16 => Some(Severity::Warn),
+        17..=20 => Some(Severity::Error),
+        21..=24 => Some(Severity::Fatal),
+        _ => Severity::from_level(text),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use shared::types::record::line::Stream;
+
+    fn string_value(value: &str) -> Option<AnyValue> {
+        Some(AnyValue {
+            value: Some(AnyValueKind::StringValue(value.to_owned())),
+        })
+    }
+
+    fn key_value(key: &str, value: &str) -> KeyValue {
+        KeyValue {
+            key: key.to_owned(),
+            value: string_value(value),
+        }
+    }
+
+    #[rstest]
+    fn test_json_into_pod_logs() {
+        let body = r#"{
+            "resourceLogs": [
+                {
+                    "resource": {
+                        "attributes": [
+                            {"key": "k8s.namespace.name", "value": {"stringValue": "shop"}},
+                            {"key": "k8s.pod.name", "value": {"stringValue": "cart-7db6d8ff4d-x7k2p"}},
+                            {"key": "k8s.pod.uid", "value": {"stringValue": "9c5e1c4a-1d2b-4f5e-8a7b-0c1d2e3f4a5b"}},
+                            {"key": "k8s.container.name", "value": {"stringValue": "cart"}}
+                        ]
+                    },
+                    "scopeLogs": [
+                        {
+                            "scope": {"name": "filelog"},
+                            "logRecords": [
+                                {
+                                    "timeUnixNano": "1710566898100412000",
+                                    "severityNumber": 17,
+                                    "body": {"stringValue": "request failed"},
+                                    "attributes": [{"key": "log.iostream", "value": {"stringValue": "stderr"}}]
+                                },
+                                {
+                                    "observedTimeUnixNano": 1710566898200000000,
+                                    "body": {"kvlistValue": {"values": [
+                                        {"key": "level", "value": {"stringValue": "warn"}},
+                                        {"key": "retries", "value": {"intValue": "3"}}
+                                    ]}}
+                                }
+                            ]
+                        }
+                    ]
+                },
+                {
+                    "resource": {"attributes": [{"key": "host.name", "value": {"stringValue": "node-1"}}]},
+                    "scopeLogs": [{"logRecords": [{"body": {"stringValue": "kernel: oom"}}]}]
+                }
+            ]
+        }"#;
+        let request = decode_request(PushFormat::Json, body.as_bytes()).unwrap();
+        let (pods, rejected) = into_pod_logs(request);
+
+        assert_eq!(rejected, 1);
+        assert_eq!(pods.len(), 1);
+        let (metadata, logs) = &pods[0];
+        assert_eq!(metadata.namespace, "shop");
+        assert_eq!(metadata.pod_name, "cart-7db6d8ff4d-x7k2p");
+        assert_eq!(metadata.container, "cart");
+        assert_eq!(
+            metadata.path,
+            "/var/log/pods/shop_cart-7db6d8ff4d-x7k2p_9c5e1c4a-1d2b-4f5e-8a7b-0c1d2e3f4a5b/cart"
+        );
+
+        assert_eq!(logs[0].timestamp, 1710566898100);
+        assert_eq!(logs[0].message, "request failed");
+        assert_eq!(logs[0].stream, Stream::Stderr);
+        assert_eq!(logs[0].severity, Severity::Error);
+        assert_eq!(logs[0].key, "cart-7db6d8ff4d-x7k2p");
+
+        assert_eq!(logs[1].timestamp, 1710566898200);
+        assert_eq!(logs[1].message, r#"{"level":"warn","retries":3}"#);
+        assert_eq!(logs[1].stream, Stream::Unknown);
+        assert_eq!(logs[1].severity, Severity::Warn);
+    }
+
+    #[rstest]
+    fn test_protobuf_into_pod_logs() {
+        let request = ExportLogsServiceRequest {
+            resource_logs: vec![ResourceLogs {
+                resource: Some(Resource {
+                    attributes: vec![
+                        key_value(NAMESPACE, "shop"),
+                        key_value(POD_NAME, "cart-7db6d8ff4d-x7k2p"),
+                        key_value(POD_UID, "9c5e1c4a-1d2b-4f5e-8a7b-0c1d2e3f4a5b"),
+                        key_value(CONTAINER, "cart"),
+                    ],
+                }),
+                scope_logs: vec![ScopeLogs {
+                    log_records: vec![OtlpLogRecord {
+                        time_unix_nano: 1710566898100412000,
+                        severity_text: "INFO".to_owned(),
+                        body: string_value("cart ready"),
+                        ..Default::default()
+                    }],
+                }],
+            }],
+        };
+        let body = request.encode_to_vec();
+        let (pods, rejected) = into_pod_logs(decode_request(PushFormat::Protobuf, &body).unwrap());
+
+        assert_eq!(rejected, 0);
+        let (metadata, logs) = &pods[0];
+        assert_eq!(metadata.pod_uid, "9c5e1c4a-1d2b-4f5e-8a7b-0c1d2e3f4a5b");
+        assert_eq!(logs[0].timestamp, 1710566898100);
+        assert_eq!(logs[0].message, "cart ready");
+        assert_eq!(logs[0].severity, Severity::Info);
+
+        assert!(decode_request(PushFormat::Protobuf, b"\x0a\xff").is_err());
+    }
+
+    #[rstest]
+    fn test_response() {
+        let (_, body) = response(PushFormat::Json, 0);
+        assert_eq!(body, b"{}");
+
+        let (content_type, body) = response(PushFormat::Protobuf, 2);
+        assert_eq!(content_type.sub(), "x-protobuf");
+        let response = ExportLogsServiceResponse::decode(body.as_slice()).unwrap();
+        assert_eq!(response.partial_success.unwrap().rejected_log_records, 2);
+    }
+}
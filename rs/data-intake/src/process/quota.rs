@@ -0,0 +1,451 @@
+use std::collections::HashMap;
+use std::env::var;
+
+use chrono::{NaiveDate, Utc};
+use rocket::http::{Header, Status};
+use rocket::request::{FromRequest, Outcome};
+use rocket::{catch, Request, Responder, State};
+use serde::{Deserialize, Serialize};
+use shared::router::auth::guard::AuthenticatedUser;
+use shared::{format_key, log_error, AsyncRedisConnection, RedisConnectionError};
+use thiserror::Error;
+use tracing::warn;
+
+use crate::error::DataIntakeError;
+
+const DEFAULT_INTERVAL_SECONDS: u64 = 60;
+// the longest range of days of a usage report
+const MAX_USAGE_DAYS: i64 = 366;
+const REQUESTS: &str = "requests";
+const RECORDS: &str = "records";
+const BYTES: &str = "bytes";
+const REJECTED: &str = "rejected";
+
+#[derive(Error, Debug)]
+pub enum QuotaError {
+    #[error("Failed to parse quotas: {0}")]
+    JsonParseError(#[from] serde_json::Error),
+    #[error("Interval of the quota of customer {0:?} must be positive")]
+    InvalidInterval(Option<String>),
+    #[error("Intake quota exceeded, retry after {0} seconds")]
+    Exceeded(u64),
+    #[error("Invalid date, expected YYYY-MM-DD: {0}")]
+    InvalidDate(#[from] chrono::ParseError),
+    #[error("Invalid date range from {0} to {1}, at most {MAX_USAGE_DAYS} days are reported")]
+    InvalidRange(NaiveDate, NaiveDate),
+    #[error("Failed to read usage: {0}")]
+    Redis(#[from] RedisConnectionError),
+}
+
+// A limit of the intake of a customer per fixed interval, limits that are not set are unlimited.
+// A quota without a customer applies to all customers without their own quota. The byte limit
+// should be at least the intake limit, a larger body is never accepted.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Quota {
+    #[serde(default)]
+    pub customer: Option<String>,
+    #[serde(default = "default_interval_seconds")]
+    pub interval_seconds: u64,
+    #[serde(default)]
+    pub max_records: Option<u64>,
+    #[serde(default)]
+    pub max_bytes: Option<u64>,
+}
+
+fn default_interval_seconds() -> u64 {
+    DEFAULT_INTERVAL_SECONDS
+}
+
+impl Quota {
+    // The `window` function returns the start of the interval containing `now` and the seconds
+    // until it ends, both in seconds since the epoch.
+    pub fn window(&self, now: u64) -> (u64, u64) {
+        let start = now - now % self.interval_seconds;
+        (start, start + self.interval_seconds - now)
+    }
+
+    // The limits of the usage of an interval. The records of a request are only known once its
+    // body is read, a request that goes over the record limit is accepted and the following ones
+    // are rejected.
+    pub fn limits(&self) -> Vec<(&'static str, u64)> {
+        [(RECORDS, self.max_records), (BYTES, self.max_bytes)]
+            .into_iter()
+            .filter_map(|(field, max)| max.map(|max| (field, max)))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Usage {
+    pub requests: u64,
+    pub records: u64,
+    pub bytes: u64,
+    pub rejected: u64,
+}
+
+impl From<HashMap<String, u64>> for Usage {
+    fn from(counts: HashMap<String, u64>) -> Self {
+        let count = |field: &str| counts.get(field).copied().unwrap_or_default();
+        Usage {
+            requests: count(REQUESTS),
+            records: count(RECORDS),
+            bytes: count(BYTES),
+            rejected: count(REJECTED),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct WindowUsage {
+    pub start: u64,
+    pub end: u64,
+    pub usage: Usage,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DailyUsage {
+    pub date: String,
+    pub usage: Usage,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UsageReport {
+    pub customer_id: String,
+    pub quota: Option<Quota>,
+    pub window: Option<WindowUsage>,
+    pub days: Vec<DailyUsage>,
+}
+
+// The intake quotas of the customers. `DATA_INTAKE_QUOTAS` is a JSON array like
+// `[{"customer": "acme", "interval_seconds": 60, "max_records": 100000, "max_bytes": 104857600}]`.
+// The usage of the current interval is counted in a Redis hash that expires with the interval,
+// e.g. `quota_<interval start>_<customer_id>`. For billing, the usage is also counted per day in
+// `intake_usage_<YYYY-MM-DD>_<customer_id>`, which does not expire, together with the rejected
+// requests. Failing to count does not fail the intake, the request is accepted.
+pub struct Quotas {
+    quotas: Vec<Quota>,
+    redis: AsyncRedisConnection,
+}
+
+impl Quotas {
+    pub fn new(quotas: Vec<Quota>, redis: AsyncRedisConnection) -> Self {
+        Quotas { quotas, redis }
+    }
+
+    pub fn from_env(redis: AsyncRedisConnection) -> Result<Self, QuotaError> {
+        let quotas = match var("DATA_INTAKE_QUOTAS") {
+            Ok(quotas) => parse_quotas(&quotas)?,
+            Err(_) => Vec::new(),
+        };
+        Ok(Quotas::new(quotas, redis))
+    }
+
+    // The quota of the customer, or the one of all customers.
+    pub fn quota(&self, customer_id: &str) -> Option<&Quota> {
+        self.quotas
+            .iter()
+            .find(|quota| quota.customer.as_deref() == Some(customer_id))
+            .or_else(|| self.quotas.iter().find(|quota| quota.customer.is_none()))
+    }
+
+    // The `admit` function counts a request unless the customer is at its quota.
+    pub async fn admit(&self, customer_id: &str) -> Result<(), QuotaError> {
+        self.count(customer_id, &[(REQUESTS, 1)]).await
+    }
+
+    // The `add_bytes` function counts the bytes of a decoded request body, unless they take the
+    // customer over its quota.
+    pub async fn add_bytes(&self, customer_id: &str, bytes: u64) -> Result<(), QuotaError> {
+        self.count(customer_id, &[(BYTES, bytes)]).await
+    }
+
+    // The limits are checked and the counts added in one step in Redis, concurrent requests can't
+    // go over the quota together.
+    async fn count(&self, customer_id: &str, counts: &[(&str, u64)]) -> Result<(), QuotaError> {
+        let now = Utc::now();
+        let day = usage_key(customer_id, now.date_naive());
+        if let Some(quota) = self.quota(customer_id) {
+            let (start, retry_after) = quota.window(now.timestamp() as u64);
+            let added = self
+                .redis
+                .increment_bounded(
+                    &window_key(customer_id, start),
+                    counts,
+                    &quota.limits(),
+                    quota.interval_seconds as i64,
+                )
+                .await
+                .map_err(|e| log_error!(e))
+                .unwrap_or(true);
+            if !added {
+                self.redis
+                    .increment(&day, &[(REJECTED, 1)])
+                    .await
+                    .map_err(|e| log_error!(e))
+                    .ok();
+                return Err(QuotaError::Exceeded(retry_after));
+            }
+        }
+        self.redis
+            .increment(&day, counts)
+            .await
+            .map_err(|e| log_error!(e))
+            .ok();
+        Ok(())
+    }
+
+    pub async fn add_records(&self, customer_id: &str, records: usize) {
+        let now = Utc::now();
+        let counts = [(RECORDS, records as u64)];
+        if let Some(quota) = self.quota(customer_id) {
+            let (start, _) = quota.window(now.timestamp() as u64);
+            self.redis
+                .increment_expiring(
+                    &window_key(customer_id, start),
+                    &counts,
+                    quota.interval_seconds as i64,
+                )
+                .await
+                .map_err(|e| log_error!(e))
+                .ok();
+        }
+        self.redis
+            .increment(&usage_key(customer_id, now.date_naive()), &counts)
+            .await
+            .map_err(|e| log_error!(e))
+            .ok();
+    }
+
+    // The `usage` function reports the usage of the current interval and of the days from `from`
+    // to `to`, both in the format `YYYY-MM-DD` and today if not given.
+    pub async fn usage(
+        &self,
+        customer_id: &str,
+        from: Option<&str>,
+        to: Option<&str>,
+    ) -> Result<UsageReport, QuotaError> {
+        let now = Utc::now();
+        let (from, to) = parse_range(from, to, now.date_naive())?;
+
+        let quota = self.quota(customer_id).cloned();
+        let window = match &quota {
+            Some(quota) => {
+                let (start, retry_after) = quota.window(now.timestamp() as u64);
+                let usage = self.redis.counts(&window_key(customer_id, start)).await?;
+                Some(WindowUsage {
+                    start,
+                    end: now.timestamp() as u64 + retry_after,
+                    usage: Usage::from(usage),
+                })
+            }
+            None => None,
+        };
+        let mut days = Vec::new();
+        for date in from.iter_days().take_while(|date| *date <= to) {
+            let usage = self.redis.counts(&usage_key(customer_id, date)).await?;
+            days.push(DailyUsage {
+                date: date.to_string(),
+                usage: Usage::from(usage),
+            });
+        }
+
+        Ok(UsageReport {
+            customer_id: customer_id.to_string(),
+            quota,
+            window,
+            days,
+        })
+    }
+}
+
+fn parse_quotas(quotas: &str) -> Result<Vec<Quota>, QuotaError> {
+    let quotas: Vec<Quota> = serde_json::from_str(quotas)?;
+    match quotas.iter().find(|quota| quota.interval_seconds == 0) {
+        Some(quota) => Err(QuotaError::InvalidInterval(quota.customer.clone())),
+        None => Ok(quotas),
+    }
+}
+
+fn parse_range(
+    from: Option<&str>,
+    to: Option<&str>,
+    today: NaiveDate,
+) -> Result<(NaiveDate, NaiveDate), QuotaError> {
+    let to = match to {
+        Some(to) => to.parse::<NaiveDate>()?,
+        None => today,
+    };
+    let from = match from {
+        Some(from) => from.parse::<NaiveDate>()?,
+        None => to,
+    };
+    let days = (to - from).num_days();
+    if !(0..MAX_USAGE_DAYS).contains(&days) {
+        return Err(QuotaError::InvalidRange(from, to));
+    }
+    Ok((from, to))
+}
+
+fn window_key(customer_id: &str, start: u64) -> String {
+    format_key("quota", Some(&start.to_string()), customer_id)
+}
+
+fn usage_key(customer_id: &str, date: NaiveDate) -> String {
+    format_key("intake_usage", Some(&date.to_string()), customer_id)
+}
+
+// The quota of the authenticated customer, checked before the body of an intake request is read.
+// The bytes of the body are counted once it is read and decoded, the route adds the records.
+pub struct IntakeQuota<'r> {
+    quotas: &'r Quotas,
+    customer_id: String,
+}
+
+impl IntakeQuota<'_> {
+    pub async fn add_bytes(&self, bytes: usize) -> Result<(), QuotaError> {
+        self.quotas
+            .add_bytes(&self.customer_id, bytes as u64)
+            .await
+            .inspect_err(|e| warn!("Rejected request of customer {}: {}", self.customer_id, e))
+    }
+
+    pub async fn add_records(&self, records: usize) {
+        self.quotas.add_records(&self.customer_id, records).await;
+    }
+}
+
+// The seconds until the quota window of a rejected request ends, for the `Retry-After` header.
+struct RetryAfter(u64);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for IntakeQuota<'r> {
+    type Error = DataIntakeError;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let quotas = match request.guard::<&State<Quotas>>().await {
+            Outcome::Success(quotas) => quotas.inner(),
+            Outcome::Error((status, _)) | Outcome::Forward(status) => {
+                return Outcome::Forward(status)
+            }
+        };
+        // the routes declare the quota after the user, which was authenticated already
+        let Some(user) = AuthenticatedUser::cached(request) else {
+            return Outcome::Forward(Status::Unauthorized);
+        };
+
+        match quotas.admit(&user.customer_id).await {
+            Ok(()) => Outcome::Success(IntakeQuota {
+                quotas,
+                customer_id: user.customer_id.clone(),
+            }),
+            Err(e) => {
+                warn!("Rejected request of customer {}: {}", user.customer_id, e);
+                if let QuotaError::Exceeded(retry_after) = e {
+                    request.local_cache(|| RetryAfter(retry_after));
+                }
+                Outcome::Error((Status::TooManyRequests, DataIntakeError::Quota(e)))
+            }
+        }
+    }
+}
+
+#[derive(Responder)]
+#[response(status = 429)]
+pub struct TooManyRequests {
+    message: &'static str,
+    retry_after: Header<'static>,
+}
+
+#[catch(429)]
+pub fn too_many_requests(request: &Request) -> TooManyRequests {
+    let RetryAfter(seconds) = request.local_cache(|| RetryAfter(DEFAULT_INTERVAL_SECONDS));
+    TooManyRequests {
+        message: "Intake quota exceeded. Please retry later.",
+        retry_after: Header::new("Retry-After", seconds.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn quota(max_records: Option<u64>, max_bytes: Option<u64>) -> Quota {
+        Quota {
+            customer: None,
+            interval_seconds: 60,
+            max_records,
+            max_bytes,
+        }
+    }
+
+    #[rstest]
+    #[case(quota(None, None), vec![])]
+    #[case(quota(Some(100), None), vec![("records", 100)])]
+    #[case(quota(Some(100), Some(1000)), vec![("records", 100), ("bytes", 1000)])]
+    fn test_limits(#[case] quota: Quota, #[case] expected: Vec<(&str, u64)>) {
+        assert_eq!(quota.limits(), expected);
+    }
+
+    #[rstest]
+    #[case(1710566880, (1710566880, 60))]
+    #[case(1710566898, (1710566880, 42))]
+    #[case(1710566939, (1710566880, 1))]
+    fn test_window(#[case] now: u64, #[case] expected: (u64, u64)) {
+        assert_eq!(quota(None, None).window(now), expected);
+    }
+
+    #[rstest]
+    fn test_parse_quotas() {
+        let quotas = parse_quotas(
+            r#"[
+                {"max_bytes": 104857600},
+                {"customer": "acme", "interval_seconds": 3600, "max_records": 100000}
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            quotas,
+            vec![
+                Quota {
+                    customer: None,
+                    interval_seconds: 60,
+                    max_records: None,
+                    max_bytes: Some(104857600),
+                },
+                Quota {
+                    customer: Some("acme".to_string()),
+                    interval_seconds: 3600,
+                    max_records: Some(100000),
+                    max_bytes: None,
+                },
+            ]
+        );
+
+        assert!(matches!(
+            parse_quotas(r#"[{"customer": "acme", "interval_seconds": 0}]"#),
+            Err(QuotaError::InvalidInterval(Some(customer))) if customer == "acme"
+        ));
+        assert!(parse_quotas(r#"{"customer": "acme"}"#).is_err());
+    }
+
+    #[rstest]
+    #[case(None, None, Ok(("2024-03-16", "2024-03-16")))]
+    #[case(Some("2024-03-01"), None, Ok(("2024-03-01", "2024-03-16")))]
+    #[case(None, Some("2024-02-29"), Ok(("2024-02-29", "2024-02-29")))]
+    #[case(Some("2024-03-17"), None, Err(()))]
+    #[case(Some("2023-01-01"), None, Err(()))]
+    #[case(Some("16.03.2024"), None, Err(()))]
+    fn test_parse_range(
+        #[case] from: Option<&str>,
+        #[case] to: Option<&str>,
+        #[case] expected: Result<(&str, &str), ()>,
+    ) {
+        let today = NaiveDate::from_ymd_opt(2024, 3, 16).unwrap();
+        let range = parse_range(from, to, today)
+            .map(|(from, to)| (from.to_string(), to.to_string()))
+            .map_err(|_| ());
+        let expected = expected.map(|(from, to)| (from.to_string(), to.to_string()));
+        assert_eq!(range, expected);
+    }
+}
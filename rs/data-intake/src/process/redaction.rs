@@ -0,0 +1,72 @@
+use serde_json::Value;
+use shared::{
+    format_key, log_error,
+    preprocessing::redaction::{RedactionCounts, Redactor},
+    types::{audit::AuditEvent, kubeapidata::KubeApiData, record::log::LogRecord},
+    AsyncRedisConnection, DbName,
+};
+use tracing::info;
+
+// Redacts the data of the intake routes. How many values were redacted is counted per customer
+// in a Redis hash for each kind of data, e.g. `redactions_log_<customer_id>` with one field per
+// rule, so that the redaction can be audited.
+pub struct Redaction {
+    redactor: Redactor,
+    redis: AsyncRedisConnection,
+}
+
+impl Redaction {
+    pub fn new(redactor: Redactor, redis: AsyncRedisConnection) -> Self {
+        Redaction { redactor, redis }
+    }
+
+    pub async fn logs(&self, customer_id: &str, logs: &mut [LogRecord]) {
+        let mut counts = RedactionCounts::default();
+        for log in logs.iter_mut() {
+            self.redactor
+                .redact(customer_id, &mut log.message, &mut counts);
+        }
+        self.record(customer_id, DbName::Log, &counts).await;
+    }
+
+    pub async fn json(&self, customer_id: &str, db: DbName, value: &mut Value) {
+        let mut counts = RedactionCounts::default();
+        self.redactor.redact_json(customer_id, value, &mut counts);
+        self.record(customer_id, db, &counts).await;
+    }
+
+    pub async fn kube_api_data(&self, customer_id: &str, db: DbName, data: &mut KubeApiData) {
+        let mut counts = RedactionCounts::default();
+        self.redactor
+            .redact_kube_api_data(customer_id, data, &mut counts);
+        self.record(customer_id, db, &counts).await;
+    }
+
+    pub async fn audit_event(&self, customer_id: &str, event: &mut AuditEvent) {
+        let mut counts = RedactionCounts::default();
+        self.redactor
+            .redact_audit_event(customer_id, event, &mut counts);
+        self.record(customer_id, DbName::Audit, &counts).await;
+    }
+
+    // Failing to count does not fail the intake, the data is redacted either way.
+    async fn record(&self, customer_id: &str, db: DbName, counts: &RedactionCounts) {
+        if counts.is_empty() {
+            return;
+        }
+        info!(
+            "Redacted {} values in {} data of customer {}: {:?}",
+            counts.total(),
+            db,
+            customer_id,
+            counts
+        );
+        let key = format_key("redactions", Some(&db.to_string()), customer_id);
+        let counts: Vec<(&str, u64)> = counts.iter().collect();
+        self.redis
+            .increment(&key, &counts)
+            .await
+            .map_err(|e| log_error!(e))
+            .ok();
+    }
+}
@@ -0,0 +1,135 @@
+use std::sync::{Arc, Mutex};
+
+use crate::error::DataIntakeError;
+
+use algorithm::classification::explain::Explanation;
+use algorithm::classification::log_classifier::LogClassifier;
+use rocket::post;
+use rocket::serde::json::Json;
+use rocket::State;
+use serde::Deserialize;
+use shared::preprocessing::pipeline::Pipelines;
+use shared::router::auth::guard::AuthenticatedUser;
+use shared::types::metadata::Metadata;
+use shared::types::record::{log::LogRecord, preprocessed::PreprocessedLogRecord};
+use shared::DbName;
+
+// A raw log line, as read from the log file of the container, with the metadata of its pod.
+#[derive(Debug, Deserialize)]
+pub struct ExplainRequest {
+    pub line: String,
+    pub metadata: Metadata,
+}
+
+// Classifies the line against the current classes of its key without storing the result.
+#[post("/classify/explain", format = "json", data = "<request>")]
+pub async fn classify_explain(
+    user: AuthenticatedUser,
+    classifier: &State<Arc<Mutex<Box<dyn LogClassifier>>>>,
+    pipelines: &State<Arc<Mutex<Pipelines>>>,
+    request: Json<ExplainRequest>,
+) -> Result<Json<Explanation>, DataIntakeError> {
+    let ExplainRequest { line, metadata } = request.into_inner();
+    let (classifier, pipelines) = (Arc::clone(classifier), Arc::clone(pipelines));
+    // the pipelines are reloaded from a file and the classes read from redis, which blocks
+    let explanation = tokio::task::spawn_blocking(move || {
+        explain(&user.customer_id, line, metadata, &classifier, &pipelines)
+    })
+    .await?
+    .map_err(|e| *e)?;
+    Ok(Json(explanation))
+}
+
+fn explain(
+    customer_id: &str,
+    line: String,
+    metadata: Metadata,
+    classifier: &Mutex<Box<dyn LogClassifier>>,
+    pipelines: &Mutex<Pipelines>,
+) -> Result<Explanation, Box<DataIntakeError>> {
+    let db = DbName::Log.id(customer_id);
+    let log = LogRecord::from((&line, &metadata));
+
+    // preprocess with the pipeline of the container, as data processing does
+    let preprocessed_message = {
+        let mut pipelines = pipelines
+            .lock()
+            .map_err(|_| DataIntakeError::PipelinesLock)?;
+        pipelines.reload_if_changed();
+        pipelines.preprocess(customer_id, &log)
+    };
+    let log = PreprocessedLogRecord::from((log, preprocessed_message));
+
+    let mut classifier = classifier
+        .lock()
+        .map_err(|_| DataIntakeError::ClassifierLock)?;
+    Ok(classifier
+        .explain(&log, &db)
+        .map_err(DataIntakeError::from)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::DataIntakeError;
+    use crate::server::initialize_data_intake;
+
+    use rstest::rstest;
+    use serde_json::{json, Value};
+    use shared::mock::rocket::get_test_client;
+    use shared::types::class::item::Item;
+    use shared::types::classifier::state::ClassifierState;
+    use shared::utils::mock::mock_client::{get_test_metadata, post_test_response};
+    use shared::utils::mock::mock_data::class_from_items;
+    use shared::{setup_tracing, RedisConnection};
+    use uuid7::uuid7;
+
+    #[tokio::test]
+    #[rstest]
+    async fn test_classify_explain_route() -> Result<(), DataIntakeError> {
+        setup_tracing(false);
+
+        // rocket client
+        let server = initialize_data_intake().await?;
+        let client = get_test_client(server).await?;
+
+        // a workload without classes, the line would create a class
+        let metadata =
+            get_test_metadata(&format!("explain{}", uuid7().to_string().replace('-', "")));
+        let request = json!({
+            "line": "2024-03-16T05:28:18.752849Z stderr F GET /health 200",
+            "metadata": metadata,
+        });
+        let (status, body) =
+            post_test_response(&client, "/classify/explain", request.clone()).await;
+        assert_eq!(status.code, 200);
+        let explanation: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(explanation["decision"]["decision"], "new-class");
+        assert_eq!(explanation["candidates"], json!([]));
+
+        // a class of the masked tokens of the line is the matching candidate
+        let key = explanation["key"].as_str().unwrap();
+        let items = explanation["masked_tokens"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|token| Item::Fix(token.as_str().unwrap().to_string()))
+            .collect();
+        let class = class_from_items(items, 1.0, &metadata);
+        let state = ClassifierState {
+            classes: vec![class.clone()],
+            ..Default::default()
+        };
+        RedisConnection::new()?.set(key, state)?;
+
+        let (status, body) = post_test_response(&client, "/classify/explain", request).await;
+        assert_eq!(status.code, 200);
+        let explanation: Value = serde_json::from_str(&body).unwrap();
+        let candidates = explanation["candidates"].as_array().unwrap();
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0]["class_id"], class.class_id);
+        assert_eq!(candidates[0]["similarity"], 1.0);
+        assert_eq!(explanation["decision"]["decision"], "matched");
+        assert_eq!(explanation["decision"]["class_id"], class.class_id);
+        Ok(())
+    }
+}
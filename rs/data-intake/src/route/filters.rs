@@ -0,0 +1,103 @@
+use crate::error::DataIntakeError;
+use crate::process::filter::{builtin_rules, FilterRule, IntakeFilters, ObjectType};
+
+use std::collections::BTreeMap;
+
+use rocket::serde::json::Json;
+use rocket::{delete, get, put, State};
+use serde::Serialize;
+use shared::router::auth::guard::AuthenticatedUser;
+
+// The filter rules of the customer, evaluated in order before the built-in rules of each object
+// type.
+#[derive(Debug, Serialize)]
+pub struct FilterRules {
+    pub rules: Vec<FilterRule>,
+    pub builtin: BTreeMap<&'static str, Vec<FilterRule>>,
+}
+
+impl From<Vec<FilterRule>> for FilterRules {
+    fn from(rules: Vec<FilterRule>) -> Self {
+        FilterRules {
+            rules,
+            builtin: ObjectType::ALL
+                .into_iter()
+                .map(|object_type| (object_type.name(), builtin_rules(object_type)))
+                .collect(),
+        }
+    }
+}
+
+#[get("/filters")]
+pub async fn get_intake_filters(
+    user: AuthenticatedUser,
+    filters: &State<IntakeFilters>,
+) -> Result<Json<FilterRules>, DataIntakeError> {
+    let rules = filters.rules(&user.customer_id).await?;
+    Ok(Json(rules.into()))
+}
+
+// Replaces the filter rules of the customer.
+#[put("/filters", format = "json", data = "<rules>")]
+pub async fn put_intake_filters(
+    user: AuthenticatedUser,
+    filters: &State<IntakeFilters>,
+    rules: Json<Vec<FilterRule>>,
+) -> Result<Json<FilterRules>, DataIntakeError> {
+    let rules = rules.into_inner();
+    filters.set_rules(&user.customer_id, &rules).await?;
+    Ok(Json(rules.into()))
+}
+
+// Removes the filter rules of the customer, only the built-in rules apply.
+#[delete("/filters")]
+pub async fn delete_intake_filters(
+    user: AuthenticatedUser,
+    filters: &State<IntakeFilters>,
+) -> Result<Json<FilterRules>, DataIntakeError> {
+    filters.delete_rules(&user.customer_id).await?;
+    Ok(Json(Vec::new().into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::DataIntakeError;
+    use crate::server::initialize_data_intake;
+
+    use rstest::rstest;
+    use serde_json::json;
+    use shared::mock::rocket::get_test_client;
+    use shared::setup_tracing;
+    use shared::utils::mock::mock_client::{delete_test, get_test, put_test};
+
+    #[tokio::test]
+    #[rstest]
+    async fn test_intake_filters_routes() -> Result<(), DataIntakeError> {
+        setup_tracing(false);
+
+        // rocket client
+        let server = initialize_data_intake().await?;
+        let client = get_test_client(server).await?;
+
+        // test data
+        let rules = json!([
+            {"name": "backups", "action": "allow", "owner_kinds": ["Job"], "labels": {"app": "backup"}},
+            {"name": "ci", "action": "deny", "namespaces": ["ci"], "reason": "CI runners"}
+        ]);
+        let duplicate_rules = json!([
+            {"name": "ci", "action": "deny", "namespaces": ["ci"]},
+            {"name": "ci", "action": "allow"}
+        ]);
+
+        // test routes
+        let status = put_test(&client, "/filters", rules).await;
+        assert_eq!(status.code, 200);
+        let status = get_test(&client, "/filters").await;
+        assert_eq!(status.code, 200);
+        let status = put_test(&client, "/filters", duplicate_rules).await;
+        assert_eq!(status.code, 422);
+        let status = delete_test(&client, "/filters").await;
+        assert_eq!(status.code, 200);
+        Ok(())
+    }
+}
@@ -0,0 +1,88 @@
+use crate::error::DataIntakeError;
+use crate::process::encoding::{read_json, ContentEncoding};
+use crate::process::quota::IntakeQuota;
+use crate::process::redaction::Redaction;
+
+use rocket::data::ToByteUnit;
+use rocket::post;
+use rocket::{Data, State};
+use shared::constant::DATA_INTAKE_LIMIT_MEMIBYTES;
+use shared::fluvio::TopicName;
+use shared::router::auth::guard::AuthenticatedUser;
+use shared::types::audit::{AuditEvent, AuditEventList};
+use shared::{log_error, log_warn_continue, FluvioConnection};
+
+// Receives the batches of the audit webhook backend of the API server, the webhook is configured
+// with a kubeconfig that authenticates with the customer's token.
+#[post("/audit", format = "json", data = "<events>")]
+pub async fn audit_intake(
+    user: AuthenticatedUser,
+    quota: IntakeQuota<'_>,
+    fluvio: FluvioConnection,
+    redaction: &State<Redaction>,
+    encoding: ContentEncoding,
+    events: Data<'_>,
+) -> Result<String, DataIntakeError> {
+    // a batch of the webhook holds up to hundreds of events, it gets the intake limit
+    let limit = DATA_INTAKE_LIMIT_MEMIBYTES.mebibytes();
+    let events: AuditEventList = read_json(events, encoding, &quota, limit).await?;
+    quota.add_records(events.items.len()).await;
+    let producer = fluvio.get_producer(TopicName::Audit);
+    for event in events.items {
+        // the request and response objects are dropped before the remaining fields are redacted
+        let mut event: AuditEvent = log_warn_continue!(serde_json::from_value(event));
+        redaction.audit_event(&user.customer_id, &mut event).await;
+        let serialized_event = log_warn_continue!(serde_json::to_string(&event));
+        producer
+            .send(user.customer_id.clone(), serialized_event)
+            .await
+            .map_err(|e| log_error!(e))
+            .ok();
+    }
+    producer.flush().await.map_err(|e| log_error!(e)).ok();
+
+    Ok("Success".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::DataIntakeError;
+    use crate::server::initialize_data_intake;
+
+    use rstest::rstest;
+    use serde_json::json;
+    use shared::mock::rocket::get_test_client;
+    use shared::setup_tracing;
+    use shared::utils::mock::mock_client::post_test;
+
+    #[tokio::test]
+    #[rstest]
+    async fn test_audit_intake_route() -> Result<(), DataIntakeError> {
+        setup_tracing(false);
+
+        // rocket client
+        let server = initialize_data_intake().await?;
+        let client = get_test_client(server).await?;
+
+        // test data
+        let events = json!({
+            "kind": "EventList",
+            "apiVersion": "audit.k8s.io/v1",
+            "items": [{
+                "auditID": "5b1fa1c4-0a2f-4c7e-9f51-2b8c8f3d6a10",
+                "stage": "ResponseComplete",
+                "requestURI": "/apis/apps/v1/namespaces/shop/deployments/cart",
+                "verb": "delete",
+                "user": {"username": "jane@example.com"},
+                "objectRef": {"resource": "deployments", "namespace": "shop", "name": "cart"},
+                "responseStatus": {"code": 200},
+                "stageTimestamp": "2024-03-16T05:28:18.351044Z"
+            }]
+        });
+
+        // test route
+        let status = post_test(&client, "/audit", events).await;
+        assert_eq!(status.code, 200);
+        Ok(())
+    }
+}
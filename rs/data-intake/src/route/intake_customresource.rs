@@ -1,89 +1,164 @@
 use crate::error::DataIntakeError;
+use crate::process::dedup::{resource_key, Deduplication};
+use crate::process::encoding::{json_limit, read_json, ContentEncoding};
+use crate::process::filter::{IntakeFilters, IntakeReport, ObjectType};
+use crate::process::quota::IntakeQuota;
+use crate::process::redaction::Redaction;
 
+use rocket::data::Limits;
 use rocket::post;
 use rocket::serde::json::Json;
+use rocket::{Data, State};
 use shared::fluvio::TopicName;
 use shared::router::auth::guard::AuthenticatedUser;
 use shared::types::kubeapidata::KubeApiData;
-use shared::utils::get_as_string;
-use shared::{log_error, FluvioConnection};
+use shared::{log_error, DbName, FluvioConnection, FluvioConnectionError};
 
 #[post("/customresource", format = "json", data = "<customresource>")]
+#[allow(clippy::too_many_arguments)]
 pub async fn customresource_intake(
     user: AuthenticatedUser,
+    quota: IntakeQuota<'_>,
     fluvio: FluvioConnection,
-    customresource: Json<serde_json::Value>,
-) -> Result<String, DataIntakeError> {
-    let data: KubeApiData = customresource
-        .into_inner()
-        .try_into()
-        .map_err(|e| DataIntakeError::DeserializationError(log_error!(e)))?;
+    redaction: &State<Redaction>,
+    dedup: &State<Deduplication>,
+    filters: &State<IntakeFilters>,
+    encoding: ContentEncoding,
+    limits: &Limits,
+    customresource: Data<'_>,
+) -> Result<Json<IntakeReport>, DataIntakeError> {
+    let customresource: serde_json::Value =
+        read_json(customresource, encoding, &quota, json_limit(limits)).await?;
+    quota.add_records(1).await;
+    let mut report = IntakeReport::default();
+    let (mut customresources, reserved) = dedup
+        .reserve(
+            &user.customer_id,
+            DbName::CustomResource,
+            vec![customresource],
+            resource_key,
+        )
+        .await;
+    let Some(customresource) = customresources.pop() else {
+        report.duplicates = 1;
+        return Ok(Json(report));
+    };
+    let produced = async {
+        let mut data: KubeApiData = customresource
+            .try_into()
+            .map_err(|e| DataIntakeError::DeserializationError(log_error!(e)))?;
 
-    let kind = get_as_string(&data.json, "kind")
-        .map_err(|e| log_error!(e))?
-        .to_lowercase();
-    if kind == "partition"
-        || kind == "kustomization"
-        || kind == "gitrepository"
-        || kind == "helmchart"
-        || kind == "ciliumendpoint"
-        || kind == "ciliumidentity"
-        || kind == "policyreport"
-        || kind == "ephemeralreport"
-    {
-        return Ok(format!("Skip {}", kind));
-    }
+        let filter = filters
+            .filter(&user.customer_id, ObjectType::CustomResource)
+            .await;
+        if !report.admit(&filter, &data.json) {
+            return Ok(vec![]);
+        }
 
-    let producer = fluvio.get_producer(TopicName::CustomResource);
-    let data_ser: Vec<u8> = data
-        .try_into()
-        .map_err(|e| DataIntakeError::SerializationError(log_error!(e)))?;
+        redaction
+            .kube_api_data(&user.customer_id, DbName::CustomResource, &mut data)
+            .await;
+        let producer = fluvio.get_producer(TopicName::CustomResource);
+        let data_ser: Vec<u8> = data
+            .try_into()
+            .map_err(|e| DataIntakeError::SerializationError(log_error!(e)))?;
 
-    producer
-        .send(user.customer_id.clone(), data_ser)
-        .await
-        .map_err(|e| log_error!(e))
-        .ok();
-    producer.flush().await.map_err(|e| log_error!(e)).ok();
+        producer
+            .send(user.customer_id.clone(), data_ser)
+            .await
+            .map_err(FluvioConnectionError::ProducerSend)?;
+        producer
+            .flush()
+            .await
+            .map_err(FluvioConnectionError::ProducerFlush)?;
+        Ok::<_, DataIntakeError>(reserved.clone())
+    }
+    .await;
+    dedup
+        .settle(
+            &user.customer_id,
+            DbName::CustomResource,
+            &reserved,
+            produced,
+        )
+        .await?;
 
-    Ok("Success".to_string())
+    Ok(Json(report))
 }
 
 #[post("/customresources", format = "json", data = "<customresources>")]
+#[allow(clippy::too_many_arguments)]
 pub async fn customresources_intake(
     user: AuthenticatedUser,
+    quota: IntakeQuota<'_>,
     fluvio: FluvioConnection,
-    customresources: Json<Vec<serde_json::Value>>,
-) -> Result<String, DataIntakeError> {
+    redaction: &State<Redaction>,
+    dedup: &State<Deduplication>,
+    filters: &State<IntakeFilters>,
+    encoding: ContentEncoding,
+    limits: &Limits,
+    customresources: Data<'_>,
+) -> Result<Json<IntakeReport>, DataIntakeError> {
+    let customresources: Vec<serde_json::Value> =
+        read_json(customresources, encoding, &quota, json_limit(limits)).await?;
+    quota.add_records(customresources.len()).await;
+    let total = customresources.len();
+    let (customresources, reserved) = dedup
+        .reserve(
+            &user.customer_id,
+            DbName::CustomResource,
+            customresources,
+            resource_key,
+        )
+        .await;
+    let mut report = IntakeReport {
+        duplicates: total - customresources.len(),
+        ..Default::default()
+    };
+    let filter = filters
+        .filter(&user.customer_id, ObjectType::CustomResource)
+        .await;
     let producer = fluvio.get_producer(TopicName::CustomResource);
 
-    for cr in customresources.into_inner() {
-        let data: KubeApiData = cr
-            .try_into()
-            .map_err(|e| DataIntakeError::DeserializationError(log_error!(e)))?;
+    let produced = async {
+        let mut seen_keys = Vec::new();
+        for cr in customresources {
+            let seen_key = resource_key(&cr);
+            let mut data: KubeApiData = cr
+                .try_into()
+                .map_err(|e| DataIntakeError::DeserializationError(log_error!(e)))?;
+            if !report.admit(&filter, &data.json) {
+                continue;
+            }
 
-        let kind = get_as_string(&data.json, "kind")
-            .map_err(|e| log_error!(e))?
-            .to_lowercase();
-        if kind == "partition"
-            || kind == "kustomization"
-            || kind == "ciliumendpoint"
-            || kind == "ciliumidentity"
-        {
-            continue;
-        }
+            redaction
+                .kube_api_data(&user.customer_id, DbName::CustomResource, &mut data)
+                .await;
+            let data_ser: Vec<u8> = data
+                .try_into()
+                .map_err(|e| DataIntakeError::SerializationError(log_error!(e)))?;
 
-        let data_ser: Vec<u8> = data
-            .try_into()
-            .map_err(|e| DataIntakeError::SerializationError(log_error!(e)))?;
+            producer
+                .send(user.customer_id.clone(), data_ser)
+                .await
+                .map_err(FluvioConnectionError::ProducerSend)?;
+            seen_keys.extend(seen_key);
+        }
 
         producer
-            .send(user.customer_id.clone(), data_ser)
+            .flush()
             .await
-            .map_err(|e| log_error!(e))
-            .ok();
+            .map_err(FluvioConnectionError::ProducerFlush)?;
+        Ok::<_, DataIntakeError>(seen_keys)
     }
-
-    producer.flush().await.map_err(|e| log_error!(e)).ok();
-    Ok("Success".to_string())
+    .await;
+    dedup
+        .settle(
+            &user.customer_id,
+            DbName::CustomResource,
+            &reserved,
+            produced,
+        )
+        .await?;
+    Ok(Json(report))
 }
@@ -1,43 +1,181 @@
 use crate::error::DataIntakeError;
+use crate::process::dedup::{event_key, Deduplication};
+use crate::process::encoding::{json_limit, read_json, ContentEncoding};
+use crate::process::quota::IntakeQuota;
+use crate::process::redaction::Redaction;
 
+use rocket::data::Limits;
 use rocket::post;
-use rocket::serde::json::Json;
+use rocket::{Data, State};
 use shared::fluvio::TopicName;
 use shared::router::auth::guard::AuthenticatedUser;
-use shared::{log_error, FluvioConnection};
+use shared::{DbName, FluvioConnection, FluvioConnectionError};
 
 #[post("/event", format = "json", data = "<event>")]
+#[allow(clippy::too_many_arguments)]
 pub async fn event_intake(
     user: AuthenticatedUser,
+    quota: IntakeQuota<'_>,
     fluvio: FluvioConnection,
-    event: Json<serde_json::Value>,
+    redaction: &State<Redaction>,
+    dedup: &State<Deduplication>,
+    encoding: ContentEncoding,
+    limits: &Limits,
+    event: Data<'_>,
 ) -> Result<String, DataIntakeError> {
-    let producer = fluvio.get_producer(TopicName::Event);
-    producer
-        .send(user.customer_id.clone(), event.into_inner().to_string())
-        .await
-        .map_err(|e| log_error!(e))
-        .ok();
-    producer.flush().await.map_err(|e| log_error!(e)).ok();
+    let event: serde_json::Value = read_json(event, encoding, &quota, json_limit(limits)).await?;
+    quota.add_records(1).await;
+    let (mut events, reserved) = dedup
+        .reserve(&user.customer_id, DbName::Event, vec![event], event_key)
+        .await;
+    let Some(mut event) = events.pop() else {
+        return Ok("Skipping duplicate event".to_string());
+    };
+    let produced = async {
+        redaction
+            .json(&user.customer_id, DbName::Event, &mut event)
+            .await;
+        let producer = fluvio.get_producer(TopicName::Event);
+        producer
+            .send(user.customer_id.clone(), event.to_string())
+            .await
+            .map_err(FluvioConnectionError::ProducerSend)?;
+        producer
+            .flush()
+            .await
+            .map_err(FluvioConnectionError::ProducerFlush)?;
+        Ok::<_, DataIntakeError>(reserved.clone())
+    }
+    .await;
+    dedup
+        .settle(&user.customer_id, DbName::Event, &reserved, produced)
+        .await?;
 
     Ok("Success".to_string())
 }
 
 #[post("/events", format = "json", data = "<events>")]
+#[allow(clippy::too_many_arguments)]
 pub async fn events_intake(
     user: AuthenticatedUser,
+    quota: IntakeQuota<'_>,
     fluvio: FluvioConnection,
-    events: Json<Vec<serde_json::Value>>,
+    redaction: &State<Redaction>,
+    dedup: &State<Deduplication>,
+    encoding: ContentEncoding,
+    limits: &Limits,
+    events: Data<'_>,
 ) -> Result<String, DataIntakeError> {
+    let events: Vec<serde_json::Value> =
+        read_json(events, encoding, &quota, json_limit(limits)).await?;
+    quota.add_records(events.len()).await;
+    let (events, reserved) = dedup
+        .reserve(&user.customer_id, DbName::Event, events, event_key)
+        .await;
     let producer = fluvio.get_producer(TopicName::Event);
-    for event in events.into_inner() {
+    let produced = async {
+        let mut seen_keys = Vec::new();
+        for mut event in events {
+            seen_keys.extend(event_key(&event));
+            redaction
+                .json(&user.customer_id, DbName::Event, &mut event)
+                .await;
+            producer
+                .send(user.customer_id.clone(), event.to_string())
+                .await
+                .map_err(FluvioConnectionError::ProducerSend)?;
+        }
         producer
-            .send(user.customer_id.clone(), event.to_string())
+            .flush()
             .await
-            .map_err(|e| log_error!(e))
-            .ok();
+            .map_err(FluvioConnectionError::ProducerFlush)?;
+        Ok::<_, DataIntakeError>(seen_keys)
     }
-    producer.flush().await.map_err(|e| log_error!(e)).ok();
+    .await;
+    dedup
+        .settle(&user.customer_id, DbName::Event, &reserved, produced)
+        .await?;
 
     Ok("Success".to_string())
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::error::DataIntakeError;
+    use crate::process::encoding::{encode, ContentEncoding};
+    use crate::server::initialize_data_intake;
+
+    use rocket::http::ContentType;
+    use rstest::rstest;
+    use serde_json::json;
+    use shared::mock::rocket::get_test_client;
+    use shared::setup_tracing;
+    use shared::utils::mock::mock_client::{
+        post_test_batch, post_test_encoded, post_test_response,
+    };
+    use uuid7::uuid4;
+
+    #[tokio::test]
+    #[rstest]
+    #[case(ContentEncoding::Identity, "identity", 200)]
+    #[case(ContentEncoding::Gzip, "gzip", 200)]
+    #[case(ContentEncoding::Zstd, "zstd", 200)]
+    #[case(ContentEncoding::Gzip, "br", 415)]
+    async fn test_events_intake_route_encoded(
+        #[case] encoding: ContentEncoding,
+        #[case] header: &str,
+        #[case] expected: u16,
+    ) -> Result<(), DataIntakeError> {
+        setup_tracing(false);
+
+        // rocket client
+        let server = initialize_data_intake().await?;
+        let client = get_test_client(server).await?;
+
+        // test data
+        let events = json!([{
+            "kind": "Event",
+            "metadata": {"name": "api.17c0e3c1", "namespace": "shop"},
+            "reason": "BackOff",
+            "message": "Back-off restarting failed container api"
+        }]);
+        let body = encode(events.to_string().as_bytes(), encoding);
+
+        // test route
+        let status = post_test_encoded(&client, "/events", ContentType::JSON, header, body).await;
+        assert_eq!(status.code, expected);
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[rstest]
+    async fn test_events_intake_route_replay() -> Result<(), DataIntakeError> {
+        setup_tracing(false);
+
+        // rocket client
+        let server = initialize_data_intake().await?;
+        let client = get_test_client(server).await?;
+
+        // test data, a new event uid for each run and a batch that repeats the event
+        let event = json!({
+            "timestamp": 1710566898351i64,
+            "event_type": "apply",
+            "json": {
+                "kind": "Event",
+                "metadata": {"name": "api.17c0e3c1", "namespace": "shop", "uid": uuid4().to_string()},
+                "count": 4,
+                "reason": "BackOff",
+                "message": "Back-off restarting failed container api"
+            }
+        });
+        let events = vec![event.clone(), event.clone()];
+
+        // test route, the event is produced once and skipped when it is sent again
+        let status = post_test_batch(&client, "/events", events).await;
+        assert_eq!(status.code, 200);
+        let (status, body) = post_test_response(&client, "/event", event).await;
+        assert_eq!(status.code, 200);
+        assert_eq!(body, "Skipping duplicate event");
+        Ok(())
+    }
+}
@@ -1,32 +1,37 @@
+use crate::process::encoding::ContentEncoding;
+use crate::process::logs::store_logs;
+use crate::process::multiline::MultilineConfig;
 use crate::process::multipart::{into_multipart, process_metadata, process_stream};
+use crate::process::quota::IntakeQuota;
+use crate::process::redaction::Redaction;
 
 use crate::error::DataIntakeError;
 use rocket::http::ContentType;
 use rocket::post;
 use rocket::Data;
-use shared::connections::greptime::greptime_connection::GreptimeTable;
-use shared::connections::greptime::middleware::insert::logs_to_insert_request;
-use shared::fluvio::TopicName;
-use shared::log_error;
+use rocket::State;
 use shared::router::auth::guard::AuthenticatedUser;
 use shared::types::metadata::Metadata;
 use shared::DbName;
 use shared::FluvioConnection;
 use shared::GreptimeConnection;
 use std::ops::Deref;
-use tracing::warn;
 
 #[post("/logs", data = "<data>")]
+#[allow(clippy::too_many_arguments)]
 pub async fn log_intake<'a>(
     user: AuthenticatedUser,
+    quota: IntakeQuota<'_>,
     greptime: GreptimeConnection,
     fluvio: FluvioConnection,
+    multiline: &State<MultilineConfig>,
+    redaction: &State<Redaction>,
     content_type: &ContentType,
+    encoding: ContentEncoding,
     data: Data<'a>,
 ) -> Result<String, DataIntakeError> {
-    let mut multipart = into_multipart(content_type, data).await?;
+    let mut multipart = into_multipart(content_type, encoding, &quota, data).await?;
     let mut metadata: Option<Metadata> = None;
-    let topic = TopicName::Log;
     let db = DbName::Log.id(&user.customer_id);
 
     greptime.create_database(&db).await?;
@@ -47,43 +52,19 @@ pub async fn log_intake<'a>(
             "stream" => {
                 // process stream
                 let metadata = metadata.ok_or(DataIntakeError::MetadataNone)?;
-                let mut logs = process_stream(field.data, &metadata)?;
-
-                // insert to greptime
-                let table = GreptimeTable::from(&metadata);
-                let stream_inserter = greptime.streaming_inserter(&db)?;
-                let insert_request = logs_to_insert_request(&logs, table);
-                stream_inserter.insert(vec![insert_request]).await?;
-                stream_inserter.finish().await?;
-
-                // send to fluvio
-                for log in logs.iter_mut() {
-                    let max_bytes = fluvio.get_topic(topic).max_bytes;
-                    log.truncate_record(&db, max_bytes);
-                    let serialized_record = serde_json::to_string(&log).unwrap();
-                    if serialized_record.len() > max_bytes {
-                        warn!(
-                            "Data too large for record, will be skipped. customer_id: {}, key: {}, record_id: {}, len: {}",
-                            &user.customer_id,
-                            log.key,
-                            log.record_id,
-                            serialized_record.len()
-                        );
-                        continue;
-                    }
-                    fluvio
-                        .get_producer(topic)
-                        .send(user.customer_id.clone(), serialized_record)
-                        .await
-                        .map_err(|e| log_error!(e))
-                        .ok();
-                    fluvio
-                        .get_producer(topic)
-                        .flush()
-                        .await
-                        .map_err(|e| log_error!(e))
-                        .ok();
-                }
+                let mut logs = process_stream(field.data, &metadata, multiline)?;
+                quota.add_records(logs.len()).await;
+                redaction.logs(&user.customer_id, &mut logs).await;
+
+                store_logs(
+                    &greptime,
+                    &fluvio,
+                    &user.customer_id,
+                    &db,
+                    &metadata,
+                    &mut logs,
+                )
+                .await?;
                 return Ok("Success".to_string());
             }
             field_name => {
@@ -98,14 +79,20 @@ pub async fn log_intake<'a>(
 #[cfg(test)]
 mod tests {
     use super::DataIntakeError;
+    use crate::process::encoding::{encode, ContentEncoding};
     use crate::server::initialize_data_intake;
 
+    use rocket::http::ContentType;
     use rstest::rstest;
+    use shared::constant::DATA_INTAKE_LIMIT_MEMIBYTES;
     use shared::mock::rocket::get_test_client;
 
     use shared::setup_tracing;
     use shared::utils::mock::mock_data::{get_test_data, TestCase};
-    use shared::utils::mock::{mock_client::post_test_stream, mock_stream::get_multipart_stream};
+    use shared::utils::mock::{
+        mock_client::{post_test_encoded, post_test_stream},
+        mock_stream::get_multipart_stream,
+    };
 
     #[tokio::test]
     #[rstest]
@@ -127,4 +114,37 @@ mod tests {
         assert_eq!(status.code, 200);
         Ok(())
     }
+
+    #[tokio::test]
+    #[rstest]
+    #[case(ContentEncoding::Gzip, "gzip")]
+    #[case(ContentEncoding::Zstd, "zstd")]
+    async fn test_log_intake_route_encoded(
+        #[case] encoding: ContentEncoding,
+        #[case] header: &str,
+    ) -> Result<(), DataIntakeError> {
+        setup_tracing(false);
+
+        // rocket client
+        let server = initialize_data_intake().await?;
+        let client = get_test_client(server).await?;
+        let content_type =
+            ContentType::new("multipart", "form-data").with_params(vec![("boundary", "boundary")]);
+
+        // test data
+        let test_data = get_test_data(TestCase::Simple);
+        let test_stream = encode(get_multipart_stream(&test_data).as_bytes(), encoding);
+
+        // test route
+        let status =
+            post_test_encoded(&client, "/logs", content_type.clone(), header, test_stream).await;
+        assert_eq!(status.code, 200);
+
+        // the limit applies to the decompressed body
+        let oversized = "a".repeat((DATA_INTAKE_LIMIT_MEMIBYTES as usize + 1) * 1024 * 1024);
+        let test_stream = encode(oversized.as_bytes(), encoding);
+        let status = post_test_encoded(&client, "/logs", content_type, header, test_stream).await;
+        assert_eq!(status.code, 413);
+        Ok(())
+    }
 }
@@ -0,0 +1,102 @@
+use crate::error::DataIntakeError;
+use crate::process::encoding::{read_body, ContentEncoding};
+use crate::process::format::PushFormat;
+use crate::process::logs::store_logs;
+use crate::process::loki::{decode_streams, into_pod_logs, LokiError};
+use crate::process::quota::IntakeQuota;
+use crate::process::redaction::Redaction;
+
+use rocket::http::{ContentType, Status};
+use rocket::post;
+use rocket::Data;
+use rocket::State;
+use shared::router::auth::guard::AuthenticatedUser;
+use shared::DbName;
+use shared::FluvioConnection;
+use shared::GreptimeConnection;
+
+// Loki push API for promtail and the loki output of Fluent Bit. Like Loki, the streams with the
+// pod labels are stored even if others are rejected, the rejected entries fail the request.
+#[post("/loki/api/v1/push", data = "<data>")]
+#[allow(clippy::too_many_arguments)]
+pub async fn loki_push_intake(
+    user: AuthenticatedUser,
+    quota: IntakeQuota<'_>,
+    greptime: GreptimeConnection,
+    fluvio: FluvioConnection,
+    redaction: &State<Redaction>,
+    content_type: &ContentType,
+    encoding: ContentEncoding,
+    data: Data<'_>,
+) -> Result<Status, DataIntakeError> {
+    let format = PushFormat::from_content_type(content_type)?;
+    let streams = decode_streams(format, &read_body(data, encoding, &quota).await?)?;
+    let (pods, rejected) = into_pod_logs(streams);
+    quota
+        .add_records(pods.iter().map(|(_, logs)| logs.len()).sum::<usize>() + rejected)
+        .await;
+
+    let db = DbName::Log.id(&user.customer_id);
+    greptime.create_database(&db).await?;
+    for (metadata, mut logs) in pods {
+        redaction.logs(&user.customer_id, &mut logs).await;
+        store_logs(
+            &greptime,
+            &fluvio,
+            &user.customer_id,
+            &db,
+            &metadata,
+            &mut logs,
+        )
+        .await?;
+    }
+    if rejected > 0 {
+        return Err(LokiError::MissingLabels(rejected).into());
+    }
+    Ok(Status::NoContent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DataIntakeError;
+    use crate::server::initialize_data_intake;
+
+    use rstest::rstest;
+    use serde_json::json;
+    use shared::mock::rocket::get_test_client;
+    use shared::setup_tracing;
+    use shared::utils::mock::mock_client::{generate_podname, post_test};
+
+    #[tokio::test]
+    #[rstest]
+    async fn test_loki_push_intake_route() -> Result<(), DataIntakeError> {
+        setup_tracing(false);
+
+        // rocket client
+        let server = initialize_data_intake().await?;
+        let client = get_test_client(server).await?;
+
+        // test data
+        let request = json!({"streams": [{
+            "stream": {
+                "namespace": "test-ns-loki",
+                "pod": generate_podname("loki"),
+                "pod_uid": "9c5e1c4a-1d2b-4f5e-8a7b-0c1d2e3f4a5b",
+                "container": "some-container"
+            },
+            "values": [["1710566898100412000", "ERROR request failed"]]
+        }]});
+
+        // test route
+        let status = post_test(&client, "/loki/api/v1/push", request).await;
+        assert_eq!(status.code, 204);
+
+        let request = json!({"streams": [{
+            "stream": {"job": "varlogs"},
+            "values": [["1710566898100412000", "kernel: oom"]]
+        }]});
+        let status = post_test(&client, "/loki/api/v1/push", request).await;
+        assert_eq!(status.code, 400);
+        Ok(())
+    }
+}
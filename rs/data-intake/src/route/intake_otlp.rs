@@ -0,0 +1,117 @@
+use crate::error::DataIntakeError;
+use crate::process::encoding::{read_body, ContentEncoding};
+use crate::process::format::PushFormat;
+use crate::process::logs::store_logs;
+use crate::process::otlp::{decode_request, into_pod_logs, response};
+use crate::process::quota::IntakeQuota;
+use crate::process::redaction::Redaction;
+
+use rocket::http::ContentType;
+use rocket::post;
+use rocket::Data;
+use rocket::State;
+use shared::router::auth::guard::AuthenticatedUser;
+use shared::DbName;
+use shared::FluvioConnection;
+use shared::GreptimeConnection;
+use tracing::warn;
+
+// OTLP/HTTP logs endpoint of the OpenTelemetry collector's `otlphttp` exporter, the logs take
+// the same path as the ones of the `/logs` route.
+#[post("/v1/logs", data = "<data>")]
+#[allow(clippy::too_many_arguments)]
+pub async fn otlp_logs_intake(
+    user: AuthenticatedUser,
+    quota: IntakeQuota<'_>,
+    greptime: GreptimeConnection,
+    fluvio: FluvioConnection,
+    redaction: &State<Redaction>,
+    content_type: &ContentType,
+    encoding: ContentEncoding,
+    data: Data<'_>,
+) -> Result<(ContentType, Vec<u8>), DataIntakeError> {
+    let format = PushFormat::from_content_type(content_type)?;
+    let request = decode_request(format, &read_body(data, encoding, &quota).await?)?;
+    let (pods, rejected) = into_pod_logs(request);
+    quota
+        .add_records(pods.iter().map(|(_, logs)| logs.len()).sum::<usize>() + rejected as usize)
+        .await;
+    if rejected > 0 {
+        warn!(
+            "Rejected {} OTLP log records without pod attributes of customer {}",
+            rejected, user.customer_id
+        );
+    }
+
+    let db = DbName::Log.id(&user.customer_id);
+    greptime.create_database(&db).await?;
+    for (metadata, mut logs) in pods {
+        redaction.logs(&user.customer_id, &mut logs).await;
+        store_logs(
+            &greptime,
+            &fluvio,
+            &user.customer_id,
+            &db,
+            &metadata,
+            &mut logs,
+        )
+        .await?;
+    }
+    Ok(response(format, rejected))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DataIntakeError;
+    use crate::server::initialize_data_intake;
+
+    use rocket::http::ContentType;
+    use rstest::rstest;
+    use serde_json::json;
+    use shared::mock::rocket::get_test_client;
+    use shared::setup_tracing;
+    use shared::utils::mock::mock_client::{generate_podname, post_test, post_test_encoded};
+
+    #[tokio::test]
+    #[rstest]
+    async fn test_otlp_logs_intake_route() -> Result<(), DataIntakeError> {
+        setup_tracing(false);
+
+        // rocket client
+        let server = initialize_data_intake().await?;
+        let client = get_test_client(server).await?;
+
+        // test data
+        let attribute =
+            |key: &str, value: &str| json!({"key": key, "value": {"stringValue": value}});
+        let request = json!({
+            "resourceLogs": [{
+                "resource": {"attributes": [
+                    attribute("k8s.namespace.name", "test-ns-otlp"),
+                    attribute("k8s.pod.name", &generate_podname("otlp")),
+                    attribute("k8s.pod.uid", "9c5e1c4a-1d2b-4f5e-8a7b-0c1d2e3f4a5b"),
+                    attribute("k8s.container.name", "some-container")
+                ]},
+                "scopeLogs": [{"logRecords": [{
+                    "timeUnixNano": "1710566898100412000",
+                    "body": {"stringValue": "ERROR request failed"}
+                }]}]
+            }]
+        });
+
+        // test route
+        let status = post_test(&client, "/v1/logs", request).await;
+        assert_eq!(status.code, 200);
+
+        let status = post_test_encoded(
+            &client,
+            "/v1/logs",
+            ContentType::Plain,
+            "identity",
+            b"ERROR request failed".to_vec(),
+        )
+        .await;
+        assert_eq!(status.code, 415);
+        Ok(())
+    }
+}
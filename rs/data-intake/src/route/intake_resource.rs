@@ -1,86 +1,146 @@
 use crate::error::DataIntakeError;
+use crate::process::dedup::{resource_key, Deduplication};
+use crate::process::encoding::{json_limit, read_json, ContentEncoding};
+use crate::process::filter::{IntakeFilters, IntakeReport, ObjectType};
+use crate::process::quota::IntakeQuota;
+use crate::process::redaction::Redaction;
 
+use rocket::data::Limits;
 use rocket::post;
 use rocket::serde::json::Json;
+use rocket::{Data, State};
 use shared::fluvio::TopicName;
 use shared::router::auth::guard::AuthenticatedUser;
 use shared::types::kubeapidata::KubeApiData;
-use shared::utils::get_as_ref;
-use shared::{log_error, FluvioConnection};
+use shared::{log_error, DbName, FluvioConnection, FluvioConnectionError};
 
 #[post("/resource", format = "json", data = "<resource>")]
+#[allow(clippy::too_many_arguments)]
 pub async fn resource_intake(
     user: AuthenticatedUser,
+    quota: IntakeQuota<'_>,
     fluvio: FluvioConnection,
-    resource: Json<serde_json::Value>,
-) -> Result<String, DataIntakeError> {
-    let data: KubeApiData = resource
-        .into_inner()
-        .try_into()
-        .map_err(|e| DataIntakeError::DeserializationError(log_error!(e)))?;
-    let producer = fluvio.get_producer(TopicName::Resource);
+    redaction: &State<Redaction>,
+    dedup: &State<Deduplication>,
+    filters: &State<IntakeFilters>,
+    encoding: ContentEncoding,
+    limits: &Limits,
+    resource: Data<'_>,
+) -> Result<Json<IntakeReport>, DataIntakeError> {
+    let resource: serde_json::Value =
+        read_json(resource, encoding, &quota, json_limit(limits)).await?;
+    quota.add_records(1).await;
+    let mut report = IntakeReport::default();
+    let (mut resources, reserved) = dedup
+        .reserve(
+            &user.customer_id,
+            DbName::Resource,
+            vec![resource],
+            resource_key,
+        )
+        .await;
+    let Some(resource) = resources.pop() else {
+        report.duplicates = 1;
+        return Ok(Json(report));
+    };
+    let produced = async {
+        let mut data: KubeApiData = resource
+            .try_into()
+            .map_err(|e| DataIntakeError::DeserializationError(log_error!(e)))?;
+        let producer = fluvio.get_producer(TopicName::Resource);
 
-    let metadata = get_as_ref(&data.json, "metadata").map_err(|e| log_error!(e))?;
-    if let Some(owner_refs) = metadata.get("ownerReferences") {
-        if let Some(refs) = owner_refs.as_array() {
-            if refs.len() == 1 {
-                if let Some(owner) = refs.first() {
-                    if owner.get("kind").and_then(|k| k.as_str()) == Some("Job") {
-                        return Ok("Skipping Job pod".to_string());
-                    }
-                }
-            }
+        let filter = filters
+            .filter(&user.customer_id, ObjectType::Resource)
+            .await;
+        if !report.admit(&filter, &data.json) {
+            return Ok(vec![]);
         }
+        redaction
+            .kube_api_data(&user.customer_id, DbName::Resource, &mut data)
+            .await;
+        let data_ser: Vec<u8> = data
+            .try_into()
+            .map_err(|e| DataIntakeError::SerializationError(log_error!(e)))?;
+        producer
+            .send(user.customer_id.clone(), data_ser)
+            .await
+            .map_err(FluvioConnectionError::ProducerSend)?;
+        producer
+            .flush()
+            .await
+            .map_err(FluvioConnectionError::ProducerFlush)?;
+        Ok::<_, DataIntakeError>(reserved.clone())
     }
-    let data_ser: Vec<u8> = data
-        .try_into()
-        .map_err(|e| DataIntakeError::SerializationError(log_error!(e)))?;
-    producer
-        .send(user.customer_id.clone(), data_ser)
-        .await
-        .map_err(|e| log_error!(e))
-        .ok();
-    producer.flush().await.map_err(|e| log_error!(e)).ok();
+    .await;
+    dedup
+        .settle(&user.customer_id, DbName::Resource, &reserved, produced)
+        .await?;
 
-    Ok("Success".to_string())
+    Ok(Json(report))
 }
 
 #[post("/resources", format = "json", data = "<resources>")]
+#[allow(clippy::too_many_arguments)]
 pub async fn resources_intake(
     user: AuthenticatedUser,
+    quota: IntakeQuota<'_>,
     fluvio: FluvioConnection,
-    resources: Json<Vec<serde_json::Value>>,
-) -> Result<String, DataIntakeError> {
+    redaction: &State<Redaction>,
+    dedup: &State<Deduplication>,
+    filters: &State<IntakeFilters>,
+    encoding: ContentEncoding,
+    limits: &Limits,
+    resources: Data<'_>,
+) -> Result<Json<IntakeReport>, DataIntakeError> {
+    let resources: Vec<serde_json::Value> =
+        read_json(resources, encoding, &quota, json_limit(limits)).await?;
+    quota.add_records(resources.len()).await;
+    let total = resources.len();
+    let (resources, reserved) = dedup
+        .reserve(&user.customer_id, DbName::Resource, resources, resource_key)
+        .await;
+    let mut report = IntakeReport {
+        duplicates: total - resources.len(),
+        ..Default::default()
+    };
+    let filter = filters
+        .filter(&user.customer_id, ObjectType::Resource)
+        .await;
     let producer = fluvio.get_producer(TopicName::Resource);
 
-    for resource in resources.into_inner() {
-        let data: KubeApiData = resource
-            .try_into()
-            .map_err(|e| DataIntakeError::DeserializationError(log_error!(e)))?;
-
-        let metadata = get_as_ref(&data.json, "metadata").map_err(|e| log_error!(e))?;
-        if let Some(owner_refs) = metadata.get("ownerReferences") {
-            if let Some(refs) = owner_refs.as_array() {
-                if refs.len() == 1 {
-                    if let Some(owner) = refs.first() {
-                        if owner.get("kind").and_then(|k| k.as_str()) == Some("Job") {
-                            continue;
-                        }
-                    }
-                }
+    let produced = async {
+        let mut seen_keys = Vec::new();
+        for resource in resources {
+            let seen_key = resource_key(&resource);
+            let mut data: KubeApiData = resource
+                .try_into()
+                .map_err(|e| DataIntakeError::DeserializationError(log_error!(e)))?;
+            if !report.admit(&filter, &data.json) {
+                continue;
             }
+
+            redaction
+                .kube_api_data(&user.customer_id, DbName::Resource, &mut data)
+                .await;
+            let data_ser: Vec<u8> = data
+                .try_into()
+                .map_err(|e| DataIntakeError::SerializationError(log_error!(e)))?;
+            producer
+                .send(user.customer_id.clone(), data_ser)
+                .await
+                .map_err(FluvioConnectionError::ProducerSend)?;
+            seen_keys.extend(seen_key);
         }
 
-        let data_ser: Vec<u8> = data
-            .try_into()
-            .map_err(|e| DataIntakeError::SerializationError(log_error!(e)))?;
         producer
-            .send(user.customer_id.clone(), data_ser)
+            .flush()
             .await
-            .map_err(|e| log_error!(e))
-            .ok();
+            .map_err(FluvioConnectionError::ProducerFlush)?;
+        Ok::<_, DataIntakeError>(seen_keys)
     }
-
-    producer.flush().await.map_err(|e| log_error!(e)).ok();
-    Ok("Success".to_string())
+    .await;
+    dedup
+        .settle(&user.customer_id, DbName::Resource, &reserved, produced)
+        .await?;
+    Ok(Json(report))
 }
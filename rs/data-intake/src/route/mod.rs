@@ -1,9 +1,21 @@
+mod explain_classification;
+mod filters;
+mod intake_audit;
 mod intake_customresource;
 mod intake_event;
 mod intake_log;
+mod intake_loki;
+mod intake_otlp;
 mod intake_resource;
+mod usage;
 
+pub use explain_classification::classify_explain;
+pub use filters::{delete_intake_filters, get_intake_filters, put_intake_filters};
+pub use intake_audit::audit_intake;
 pub use intake_customresource::{customresource_intake, customresources_intake};
 pub use intake_event::{event_intake, events_intake};
 pub use intake_log::log_intake;
+pub use intake_loki::loki_push_intake;
+pub use intake_otlp::otlp_logs_intake;
 pub use intake_resource::{resource_intake, resources_intake};
+pub use usage::intake_usage;
@@ -0,0 +1,53 @@
+use crate::error::DataIntakeError;
+use crate::process::quota::{Quotas, UsageReport};
+
+use rocket::get;
+use rocket::serde::json::Json;
+use rocket::State;
+use shared::router::auth::guard::AuthenticatedUser;
+
+// The intake usage of the customer per day for billing, with the usage of the current quota
+// interval, e.g. `/usage?from=2024-03-01&to=2024-03-31`.
+#[get("/usage?<from>&<to>")]
+pub async fn intake_usage(
+    user: AuthenticatedUser,
+    quotas: &State<Quotas>,
+    from: Option<&str>,
+    to: Option<&str>,
+) -> Result<Json<UsageReport>, DataIntakeError> {
+    let report = quotas.usage(&user.customer_id, from, to).await?;
+    Ok(Json(report))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::DataIntakeError;
+    use crate::server::initialize_data_intake;
+
+    use rstest::rstest;
+    use shared::mock::rocket::get_test_client;
+    use shared::setup_tracing;
+    use shared::utils::mock::mock_client::get_test;
+
+    #[tokio::test]
+    #[rstest]
+    #[case("/usage", 200)]
+    #[case("/usage?from=2024-03-01&to=2024-03-31", 200)]
+    #[case("/usage?from=2024-03-31&to=2024-03-01", 400)]
+    #[case("/usage?from=yesterday", 400)]
+    async fn test_intake_usage_route(
+        #[case] route: &str,
+        #[case] expected: u16,
+    ) -> Result<(), DataIntakeError> {
+        setup_tracing(false);
+
+        // rocket client
+        let server = initialize_data_intake().await?;
+        let client = get_test_client(server).await?;
+
+        // test route
+        let status = get_test(&client, route).await;
+        assert_eq!(status.code, expected);
+        Ok(())
+    }
+}
@@ -1,27 +1,65 @@
 use crate::error::DataIntakeError;
+use crate::process::dedup::Deduplication;
+use crate::process::filter::IntakeFilters;
+use crate::process::multiline::MultilineConfig;
+use crate::process::quota::{too_many_requests, Quotas};
+use crate::process::redaction::Redaction;
 use crate::route::{
-    customresource_intake, customresources_intake, event_intake, events_intake, log_intake,
-    resource_intake, resources_intake,
+    audit_intake, classify_explain, customresource_intake, customresources_intake,
+    delete_intake_filters, event_intake, events_intake, get_intake_filters, intake_usage,
+    log_intake, loki_push_intake, otlp_logs_intake, put_intake_filters, resource_intake,
+    resources_intake,
 };
-use rocket::{routes, Build, Rocket};
+use algorithm::classification::log_classifier::{new_classifier, LogClassifier};
+use rocket::{catchers, routes, Build, Rocket};
+use shared::preprocessing::pipeline::Pipelines;
+use shared::preprocessing::redaction::Redactor;
 use shared::router::rocket::{build_rocket, Connection};
-use shared::{FluvioConnection, GreptimeConnection};
+use shared::{AsyncRedisConnection, FluvioConnection, GreptimeConnection, RedisConnection};
+use std::sync::{Arc, Mutex};
 
 pub async fn initialize_data_intake() -> Result<Rocket<Build>, DataIntakeError> {
     let greptime = GreptimeConnection::new().await?;
     let fluvio = FluvioConnection::new().await?;
+    let multiline = MultilineConfig::from_env()?;
+    // the requests share one multiplexed connection instead of waiting for each other
+    let redis = AsyncRedisConnection::new().await?;
+    let redaction = Redaction::new(Redactor::from_env()?, redis.clone());
+    let quotas = Quotas::from_env(redis.clone())?;
+    let dedup = Deduplication::from_env(redis.clone())?;
+    let filters = IntakeFilters::new(redis);
+    // only used for dry runs, classes are never written by data intake
+    let classifier: Arc<Mutex<Box<dyn LogClassifier>>> =
+        Arc::new(Mutex::new(new_classifier(None, RedisConnection::new()?)?));
+    let pipelines = Arc::new(Mutex::new(Pipelines::from_env()?));
 
     let connections: Vec<Connection> = vec![greptime.into(), fluvio.into()];
     let routes = routes![
         log_intake,
+        otlp_logs_intake,
+        loki_push_intake,
         event_intake,
         events_intake,
         resource_intake,
         resources_intake,
         customresource_intake,
-        customresources_intake
+        customresources_intake,
+        audit_intake,
+        intake_usage,
+        get_intake_filters,
+        put_intake_filters,
+        delete_intake_filters,
+        classify_explain
     ];
 
-    let server = build_rocket(&connections, routes);
+    let server = build_rocket(&connections, routes)
+        .register("/", catchers![too_many_requests])
+        .manage(multiline)
+        .manage(redaction)
+        .manage(quotas)
+        .manage(dedup)
+        .manage(filters)
+        .manage(classifier)
+        .manage(pipelines);
     Ok(server)
 }
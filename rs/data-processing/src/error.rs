@@ -19,4 +19,6 @@ pub enum DataProcessingError {
     CustomResourceProcessingExit(#[source] ProcessThreadError),
     #[error("Event processing thread exited with error: {0}")]
     EventProcessingExit(#[source] ProcessThreadError),
+    #[error("Audit processing thread exited with error: {0}")]
+    AuditProcessingExit(#[source] ProcessThreadError),
 }
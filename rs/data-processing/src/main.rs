@@ -1,6 +1,6 @@
 use data_processing::error::DataProcessingError;
 use data_processing::run::{
-    run_customresource_processing, run_event_processing, run_log_processing,
+    run_audit_processing, run_customresource_processing, run_event_processing, run_log_processing,
     run_resource_processing,
 };
 use shared::setup_tracing;
@@ -13,6 +13,7 @@ async fn main() -> Result<(), DataProcessingError> {
     threads.extend(run_resource_processing()?);
     threads.extend(run_customresource_processing()?);
     threads.extend(run_event_processing()?);
+    threads.extend(run_audit_processing()?);
 
     for thread in threads {
         thread.await??;
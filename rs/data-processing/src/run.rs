@@ -1,4 +1,5 @@
 use crate::error::DataProcessingError;
+use crate::threads::process_audit::process_audit;
 use crate::threads::process_event::process_event;
 use crate::threads::process_log::process_logs;
 use crate::threads::process_resource::process_resource;
@@ -85,3 +86,21 @@ pub fn run_event_processing(
 
     Ok(threads)
 }
+
+pub fn run_audit_processing(
+) -> Result<Vec<JoinHandle<Result<(), DataProcessingError>>>, DataProcessingError> {
+    let mut threads: Vec<JoinHandle<Result<(), DataProcessingError>>> = Vec::new();
+
+    threads.push(tokio::spawn(async move {
+        let fluvio = FluvioConnection::new().await?;
+        let consumer = fluvio.create_consumer(0, TopicName::Audit).await?;
+        let producer = fluvio.get_producer(TopicName::ProcessedAudit).clone();
+        process_audit(consumer, producer)
+            .await
+            .map_err(DataProcessingError::AuditProcessingExit)
+            .inspect_err(|e| error!("{e:?}"))?;
+        Ok(())
+    }));
+
+    Ok(threads)
+}
@@ -1,7 +1,9 @@
+use std::num::ParseIntError;
 use std::str::Utf8Error;
 
 use shared::{
-    types::classifier::error::ClassifierError, GreptimeConnectionError, RedisConnectionError,
+    preprocessing::pipeline::PipelineConfigError, types::classifier::error::ClassifierError,
+    GreptimeConnectionError, RedisConnectionError,
 };
 use thiserror::Error;
 
@@ -9,6 +11,10 @@ use thiserror::Error;
 pub enum ProcessThreadError {
     #[error("Classifier error: {0}")]
     Classifier(#[from] ClassifierError),
+    #[error("Invalid configuration: {0}")]
+    Config(#[from] ParseIntError),
+    #[error("Preprocessing pipeline error: {0}")]
+    PipelineConfig(#[from] PipelineConfigError),
     #[error("Greptime connection error: {0}")]
     GreptimeConnection(#[from] GreptimeConnectionError),
     #[error("Redis get error: {0}")]
@@ -1,4 +1,5 @@
 pub mod error;
+pub mod process_audit;
 pub mod process_event;
 pub mod process_log;
 pub mod process_resource;
@@ -0,0 +1,78 @@
+use std::sync::Arc;
+
+use fluvio::consumer::ConsumerStream;
+use fluvio::dataplane::{link::ErrorCode, record::ConsumerRecord};
+use fluvio::spu::SpuSocketPool;
+use fluvio::TopicProducer;
+use futures_util::StreamExt;
+use shared::connections::dbname::DbName;
+use shared::connections::fluvio::util::get_record_key;
+use shared::connections::greptime::greptime_connection::GreptimeTable;
+use shared::connections::greptime::middleware::insert::audit_to_insert_request;
+use shared::constant::{DEFAULT_KIND, DEFAULT_NAME, DEFAULT_NS};
+use shared::fluvio::commit_and_flush_offsets;
+use shared::types::audit::AuditEvent;
+use shared::{log_error, log_error_continue, log_warn_continue, GreptimeConnection};
+
+use super::error::ProcessThreadError;
+
+pub async fn process_audit(
+    mut consumer: impl ConsumerStream<Item = Result<ConsumerRecord, ErrorCode>>,
+    producer: Arc<TopicProducer<SpuSocketPool>>,
+) -> Result<(), ProcessThreadError> {
+    let greptime = GreptimeConnection::new().await?;
+
+    while let Some(result) = consumer.next().await {
+        let record = log_warn_continue!(result);
+        let customer_id = log_warn_continue!(get_record_key(&record));
+        let db = DbName::Audit.id(&customer_id);
+
+        greptime.create_database(&db).await?;
+
+        let event: AuditEvent = log_warn_continue!(record
+            .try_into()
+            .map_err(ProcessThreadError::DeserializationError));
+
+        let table = audit_table(&event);
+        let insert_request = audit_to_insert_request(&event, table);
+        let stream_inserter = greptime.streaming_inserter(&db)?;
+        stream_inserter.insert(vec![insert_request]).await?;
+        stream_inserter.finish().await?;
+
+        // only changes to the cluster are vectorized
+        if event.is_completed_write() {
+            let data_serialized: Vec<u8> = log_warn_continue!((&event)
+                .try_into()
+                .map_err(ProcessThreadError::SerializationError));
+            producer
+                .send(customer_id.clone(), data_serialized)
+                .await
+                .map_err(|e| log_error!(e))
+                .ok();
+            producer.flush().await.map_err(|e| log_error!(e)).ok();
+        }
+
+        // commit fluvio offset
+        log_error_continue!(commit_and_flush_offsets(&mut consumer).await);
+    }
+    Ok(())
+}
+
+// The entries of an object share a table by its resource, namespace and name. The uid is only
+// known for some requests, it is stored as a column instead.
+fn audit_table(event: &AuditEvent) -> GreptimeTable {
+    let object = event.object_ref.clone().unwrap_or_default();
+    let or_default = |value: String, default: &str| {
+        if value.is_empty() {
+            default.to_string()
+        } else {
+            value
+        }
+    };
+    GreptimeTable::new(
+        or_default(object.resource, DEFAULT_KIND),
+        or_default(object.namespace, DEFAULT_NS),
+        or_default(object.name, DEFAULT_NAME),
+        "",
+    )
+}
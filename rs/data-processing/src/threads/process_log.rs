@@ -3,31 +3,176 @@ use fluvio::dataplane::{link::ErrorCode, record::ConsumerRecord};
 use fluvio::spu::SpuSocketPool;
 use fluvio::TopicProducer;
 use shared::connections::fluvio::util::get_record_key;
+use shared::connections::greptime::greptime_connection::GreptimeTable;
+use shared::connections::greptime::middleware::insert::classified_logs_to_insert_request;
 use shared::constant::TOPIC_CLASS_BYTES_PER_RECORD;
 use shared::fluvio::commit_and_flush_offsets;
-use shared::preprocessing::log::preprocess_message;
-use shared::{log_error, log_error_continue, log_warn_continue, DbName};
+use shared::preprocessing::pipeline::Pipelines;
+use shared::{log_error, log_warn_continue, DbName, GreptimeConnection};
 
+use std::collections::HashMap;
+use std::env::var;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use futures_util::StreamExt;
+use greptimedb_ingester::api::v1::InsertRequest;
 use shared::{
-    types::record::{log::LogRecord, preprocessed::PreprocessedLogRecord},
+    types::record::{
+        classified::ClassifiedLogRecord, log::LogRecord, preprocessed::PreprocessedLogRecord,
+    },
     RedisConnection,
 };
-use tracing::warn;
+use tracing::{error, info, warn};
 
-use algorithm::classification::deterministic::classifier::Classifier;
+use algorithm::classification::log_classifier::new_classifier;
 
 use super::error::ProcessThreadError;
+use crate::util::shutdown::shutdown_signal;
+
+const DEFAULT_GREPTIME_BATCH_SIZE: usize = 1000;
+const DEFAULT_GREPTIME_BATCH_INTERVAL_MS: u64 = 1000;
+const MAX_GREPTIME_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+// Classified logs waiting to be written to greptime, by database and table. They are written with
+// one request per database once `size` logs are buffered or `interval` elapsed. Logs that could
+// not be written stay buffered and are retried with an increasing delay.
+struct ClassifiedLogBatch {
+    logs: HashMap<String, HashMap<String, (GreptimeTable, Vec<ClassifiedLogRecord>)>>,
+    len: usize,
+    size: usize,
+    interval: Duration,
+    last_flush: Instant,
+    // delay before the next attempt after a failed write
+    retry_delay: Option<Duration>,
+}
+
+impl ClassifiedLogBatch {
+    fn from_env() -> Result<Self, ProcessThreadError> {
+        let size = var("GREPTIME_BATCH_SIZE")
+            .unwrap_or(DEFAULT_GREPTIME_BATCH_SIZE.to_string())
+            .parse::<usize>()?;
+        let interval = var("GREPTIME_BATCH_INTERVAL_MS")
+            .unwrap_or(DEFAULT_GREPTIME_BATCH_INTERVAL_MS.to_string())
+            .parse::<u64>()?;
+        Ok(ClassifiedLogBatch {
+            logs: HashMap::new(),
+            len: 0,
+            size: size.max(1),
+            interval: Duration::from_millis(interval),
+            last_flush: Instant::now(),
+            retry_delay: None,
+        })
+    }
+
+    fn push(&mut self, db: &str, log: ClassifiedLogRecord) {
+        let table = GreptimeTable::from(&log);
+        self.logs
+            .entry(db.to_owned())
+            .or_default()
+            .entry(table.format_name())
+            .or_insert_with(|| (table, Vec::new()))
+            .1
+            .push(log);
+        self.len += 1;
+    }
+
+    fn is_due(&self) -> bool {
+        match self.retry_delay {
+            Some(delay) => self.len > 0 && self.last_flush.elapsed() >= delay,
+            None => self.is_full() || (self.len > 0 && self.last_flush.elapsed() >= self.interval),
+        }
+    }
+
+    fn is_full(&self) -> bool {
+        self.len >= self.size
+    }
+
+    // The `flush` function writes the buffered logs and returns whether all of them were written.
+    // The logs of a database that could not be written stay buffered.
+    async fn flush(&mut self, greptime: &GreptimeConnection) -> bool {
+        for (db, tables) in std::mem::take(&mut self.logs) {
+            let (count, requests) =
+                tables
+                    .values()
+                    .fold((0, Vec::new()), |(count, mut requests), (table, logs)| {
+                        requests.push(classified_logs_to_insert_request(logs, table.clone()));
+                        (count + logs.len(), requests)
+                    });
+            match insert(greptime, &db, requests).await {
+                Ok(()) => self.len -= count,
+                Err(e) => {
+                    error!("Failed to write {} classified logs to {}: {}", count, db, e);
+                    self.logs.insert(db, tables);
+                }
+            }
+        }
+        self.last_flush = Instant::now();
+        self.retry_delay = match (self.len, self.retry_delay) {
+            (0, _) => None,
+            (_, Some(delay)) => Some((delay * 2).min(MAX_GREPTIME_RETRY_DELAY)),
+            (_, None) => Some(self.interval),
+        };
+        self.len == 0
+    }
+}
+
+// The `write_batch` function flushes the batch and commits the offsets of the consumed records once
+// all buffered logs were written, so logs that could not be written are consumed again after a
+// restart.
+async fn write_batch(
+    batch: &mut ClassifiedLogBatch,
+    greptime: &GreptimeConnection,
+    consumer: &mut impl ConsumerStream<Item = Result<ConsumerRecord, ErrorCode>>,
+) {
+    if batch.flush(greptime).await {
+        if let Err(e) = commit_and_flush_offsets(consumer).await {
+            warn!("Failed to commit offsets: {}", e);
+        }
+    }
+}
+
+async fn insert(
+    greptime: &GreptimeConnection,
+    db: &str,
+    requests: Vec<InsertRequest>,
+) -> Result<(), ProcessThreadError> {
+    let stream_inserter = greptime.streaming_inserter(db)?;
+    stream_inserter.insert(requests).await?;
+    stream_inserter.finish().await?;
+    Ok(())
+}
 
 pub async fn process_logs(
     mut consumer: impl ConsumerStream<Item = Result<ConsumerRecord, ErrorCode>>,
     producer: Arc<TopicProducer<SpuSocketPool>>,
 ) -> Result<(), ProcessThreadError> {
+    let greptime = GreptimeConnection::new().await?;
     let redis = RedisConnection::new().map_err(ProcessThreadError::RedisInit)?;
-    let mut classifier = Classifier::new(None, redis)?;
-    while let Some(result) = consumer.next().await {
+    let mut classifier = new_classifier(None, redis)?;
+    let mut pipelines = Pipelines::from_env()?;
+    let mut batch = ClassifiedLogBatch::from_env()?;
+    let mut flush_interval = tokio::time::interval(batch.interval);
+    let shutdown = shutdown_signal();
+    tokio::pin!(shutdown);
+    loop {
+        let result = tokio::select! {
+            result = consumer.next() => match result {
+                Some(result) => Some(result),
+                None => break,
+            },
+            _ = flush_interval.tick() => None,
+            _ = &mut shutdown => {
+                info!("Shutdown signal received, stop processing logs");
+                break;
+            }
+        };
+        let Some(result) = result else {
+            if batch.is_due() {
+                write_batch(&mut batch, &greptime, &mut consumer).await;
+            }
+            continue;
+        };
         let record = log_warn_continue!(result);
 
         let customer_id = log_warn_continue!(get_record_key(&record));
@@ -36,16 +181,27 @@ pub async fn process_logs(
             LogRecord::try_from(record).map_err(ProcessThreadError::DeserializationError)
         );
 
-        // preprocess
-        let preprocessed_message = preprocess_message(&log.message, &db, &log.key, &log.record_id);
+        // preprocess with the pipeline of the container
+        pipelines.reload_if_changed();
+        let preprocessed_message = pipelines.preprocess(&customer_id, &log);
         let preprocessed_log = PreprocessedLogRecord::from((log, preprocessed_message));
 
         // classify
-        let (updated_class, _classified_log) = classifier.classify(&preprocessed_log, &db)?;
+        let (updated_class, classified_log) = classifier.classify(&preprocessed_log, &db)?;
 
-        // produce to fluvio
-        if updated_class.is_some() {
-            let class = updated_class.unwrap();
+        // insert to greptime in batches, next to the raw log line
+        batch.push(&db, classified_log);
+        if batch.is_due() {
+            write_batch(&mut batch, &greptime, &mut consumer).await;
+        }
+        // a full batch that could not be written stops the consumption until greptime is back
+        while batch.is_full() {
+            tokio::time::sleep(batch.retry_delay.unwrap_or(batch.interval)).await;
+            write_batch(&mut batch, &greptime, &mut consumer).await;
+        }
+
+        // produce to fluvio, including classes changed or deleted by the class lifecycle
+        for class in updated_class.into_iter().chain(classifier.take_updates()) {
             let key = class.key.clone();
             let class_id = class.class_id.clone();
             let serialized_record: String = log_warn_continue!(class
@@ -69,9 +225,10 @@ pub async fn process_logs(
                 .map_err(|e| log_error!(e))?;
             producer.flush().await.map_err(|e| log_error!(e))?;
         }
-
-        // commit fluvio offset
-        log_error_continue!(commit_and_flush_offsets(&mut consumer).await);
     }
+    // write the buffered classified logs and the cached classifier states, the offsets are only
+    // committed if the logs were written
+    write_batch(&mut batch, &greptime, &mut consumer).await;
+    classifier.flush()?;
     Ok(())
 }
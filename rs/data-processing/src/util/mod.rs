@@ -1,2 +1,3 @@
 pub mod extract_metadata_owner;
 pub mod json_diff;
+pub mod shutdown;
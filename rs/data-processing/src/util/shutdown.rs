@@ -0,0 +1,20 @@
+use tokio::signal::unix::{signal, SignalKind};
+
+// The `shutdown_signal` function resolves on SIGTERM (sent by kubernetes) or ctrl-c.
+pub async fn shutdown_signal() {
+    let terminate = async {
+        match signal(SignalKind::terminate()) {
+            Ok(mut stream) => {
+                stream.recv().await;
+            }
+            Err(e) => {
+                tracing::error!("Failed to install SIGTERM handler: {e}");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {},
+        _ = terminate => {},
+    }
+}
@@ -2,7 +2,10 @@ use std::sync::Arc;
 
 use data_vectorizer::{
     error::DataVectorizationError,
-    run::{run_vectorize_class, run_vectorize_customresource, run_vectorize_resource},
+    run::{
+        run_vectorize_audit, run_vectorize_class, run_vectorize_customresource,
+        run_vectorize_resource,
+    },
 };
 use shared::{constant::OPENAI_EMBEDDING_TOKEN_LIMIT, setup_tracing, RateLimiter};
 
@@ -14,6 +17,7 @@ async fn main() -> Result<(), DataVectorizationError> {
     let mut threads = run_vectorize_class(Arc::clone(&rate_limiter))?;
     threads.extend(run_vectorize_resource(Arc::clone(&rate_limiter))?);
     threads.extend(run_vectorize_customresource(Arc::clone(&rate_limiter))?);
+    threads.extend(run_vectorize_audit(Arc::clone(&rate_limiter))?);
     // threads.extend(run_vectorize_event(rate_limiter)?);
 
     for thread in threads {
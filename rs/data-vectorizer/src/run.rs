@@ -4,8 +4,9 @@ use shared::{fluvio::TopicName, get_env_var_as_vec, log_error_with_message, DbNa
 use tokio::task::JoinHandle;
 
 use crate::{
-    error::DataVectorizationError, vectorize::vectorize_event::vectorize_event, vectorize_class,
-    vectorize_resource,
+    error::DataVectorizationError,
+    vectorize::{vectorize_audit::vectorize_audit, vectorize_event::vectorize_event},
+    vectorize_class, vectorize_resource,
 };
 
 pub fn run_vectorize_class(
@@ -80,3 +81,20 @@ pub fn run_vectorize_event(
 
     Ok(threads)
 }
+
+pub fn run_vectorize_audit(
+    limiter: Arc<RateLimiter>,
+) -> Result<Vec<JoinHandle<Result<(), DataVectorizationError>>>, DataVectorizationError> {
+    let mut threads: Vec<JoinHandle<Result<(), DataVectorizationError>>> = Vec::new();
+
+    threads.push(tokio::spawn(async move {
+        let dbname = DbName::Audit;
+        let topic = TopicName::ProcessedAudit;
+        vectorize_audit(limiter, dbname, topic)
+            .await
+            .map_err(|e| log_error_with_message!("Audit vectorizer thread exited with error", e))?;
+        Ok(())
+    }));
+
+    Ok(threads)
+}
@@ -1,4 +1,5 @@
 pub mod resource_state;
+pub mod vectorize_audit;
 pub mod vectorize_class;
 pub mod vectorize_event;
 pub mod vectorize_resource;
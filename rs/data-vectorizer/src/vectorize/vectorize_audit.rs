@@ -0,0 +1,66 @@
+use std::{sync::Arc, time::Duration};
+
+use shared::{
+    connections::qdrant::AuditQdrantMetadata,
+    fluvio::{commit_and_flush_offsets, TopicName},
+    log_error_continue, log_warn_continue,
+    types::{audit::AuditEvent, tokenizer::Tokenizer},
+    DbName, FluvioConnection, QdrantConnection, RateLimiter,
+};
+
+use crate::error::DataVectorizationError;
+
+use super::vectorizer::vectorize_chunk;
+
+// The audit entries are forwarded by the processing once their request completed and only if it
+// may have changed the cluster. The one-line summary is embedded.
+pub async fn vectorize_audit(
+    limiter: Arc<RateLimiter>,
+    dbname: DbName,
+    topic: TopicName,
+) -> Result<(), DataVectorizationError> {
+    let fluvio = FluvioConnection::new().await?;
+    let qdrant = QdrantConnection::new().await?;
+    let mut consumer = fluvio.create_consumer(0, topic).await?;
+    let tokenizer = Tokenizer::new()?;
+    let polling_interval = Duration::from_millis(10);
+    loop {
+        // Accumulate batch
+        let mut batch = fluvio.next_batch(&mut consumer, polling_interval).await?;
+
+        // Process batch
+        for (customer_id, records) in batch.drain() {
+            let db = dbname.id(&customer_id);
+
+            let mut chunk = vec![];
+            let mut metachunk = vec![];
+
+            let mut total_token_count = 0;
+            for record in records {
+                let event: AuditEvent = log_warn_continue!(record
+                    .try_into()
+                    .map_err(DataVectorizationError::DeserializationError));
+
+                let (data_clip, token_count) = tokenizer.clip_tail(event.summary());
+                metachunk.push(AuditQdrantMetadata::new(&event, data_clip.clone()));
+                chunk.push(data_clip);
+                total_token_count += token_count;
+
+                if total_token_count > 100000 {
+                    limiter.check_rate_limit(total_token_count).await;
+                    vectorize_chunk(&mut chunk, &mut metachunk, &qdrant, &db, total_token_count)
+                        .await;
+                    total_token_count = 0;
+                }
+            }
+
+            limiter.check_rate_limit(total_token_count).await;
+            vectorize_chunk(&mut chunk, &mut metachunk, &qdrant, &db, total_token_count).await;
+            chunk.clear();
+            metachunk.clear();
+
+            // commit fluvio offset
+            log_error_continue!(commit_and_flush_offsets(&mut consumer).await);
+        }
+    }
+}
@@ -26,24 +26,41 @@ pub async fn vectorize_class(limiter: Arc<RateLimiter>) -> Result<(), DataVector
         // Process batch
         for (customer_id, records) in batch.drain() {
             let db = DbName::Log.id(&customer_id);
-            let classes: Vec<Class> = records
+            let (deleted, classes): (Vec<Class>, Vec<Class>) = records
                 .into_iter()
                 .map(|record| record.try_into())
                 .collect::<Result<Vec<Class>, _>>()
-                .map_err(DataVectorizationError::ClassDeserialization)?;
+                .map_err(DataVectorizationError::ClassDeserialization)?
+                .into_iter()
+                .partition(|class| class.deleted);
+
+            // skip classes that were evicted or merged into another class within this batch
+            let deleted_ids: Vec<String> = deleted.into_iter().map(|c| c.class_id).collect();
+            let classes: Vec<Class> = classes
+                .into_iter()
+                .filter(|c| !deleted_ids.contains(&c.class_id))
+                .collect();
+
+            if !classes.is_empty() {
+                let (points, token_count) = log_error_continue!(
+                    vectorize_class_batch(&classes, &tokenizer, &limiter).await
+                );
 
-            let (points, token_count) =
-                log_error_continue!(vectorize_class_batch(&classes, &tokenizer, &limiter).await);
+                log_error_continue!(qdrant.upsert_points(points, &db).await);
 
-            log_error_continue!(qdrant.upsert_points(points, &db).await);
+                info!(
+                    "Vectorized {} classes with {} tokens. Total used tokens: {}, ID: {}",
+                    classes.len(),
+                    token_count,
+                    limiter.tokens_used.lock().await,
+                    customer_id
+                );
+            }
 
-            info!(
-                "Vectorized {} classes with {} tokens. Total used tokens: {}, ID: {}",
-                classes.len(),
-                token_count,
-                limiter.tokens_used.lock().await,
-                customer_id
-            );
+            if !deleted_ids.is_empty() {
+                log_error_continue!(qdrant.delete_points(&deleted_ids, &db).await);
+                info!("Deleted {} classes, ID: {}", deleted_ids.len(), customer_id);
+            }
             log_error_continue!(commit_and_flush_offsets(&mut consumer).await);
         }
     }
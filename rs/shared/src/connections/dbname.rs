@@ -6,6 +6,7 @@ pub enum DbName {
     Resource,
     CustomResource,
     Event,
+    Audit,
 }
 
 impl fmt::Display for DbName {
@@ -15,6 +16,7 @@ impl fmt::Display for DbName {
             DbName::Resource => "resource",
             DbName::CustomResource => "customresource",
             DbName::Event => "event",
+            DbName::Audit => "audit",
         };
         write!(f, "{}", name)
     }
@@ -3,12 +3,14 @@ use tracing::info;
 
 use crate::{
     constant::{
-        TOPIC_CLASS_BYTES_PER_RECORD, TOPIC_CLASS_NAME, TOPIC_CLASS_PARTITIONS,
-        TOPIC_CLASS_REPLICAS, TOPIC_CUSTOM_RESOURCE_BYTES_PER_RECORD, TOPIC_CUSTOM_RESOURCE_NAME,
-        TOPIC_CUSTOM_RESOURCE_PARTITIONS, TOPIC_CUSTOM_RESOURCE_REPLICAS,
-        TOPIC_EVENT_BYTES_PER_RECORD, TOPIC_EVENT_NAME, TOPIC_EVENT_PARTITIONS,
-        TOPIC_EVENT_REPLICAS, TOPIC_LOG_BYTES_PER_RECORD, TOPIC_LOG_NAME, TOPIC_LOG_PARTITIONS,
-        TOPIC_LOG_REPLICAS, TOPIC_PROCESSED_CUSTOM_RESOURCE_NAME, TOPIC_PROCESSED_EVENT_NAME,
+        TOPIC_AUDIT_BYTES_PER_RECORD, TOPIC_AUDIT_NAME, TOPIC_AUDIT_PARTITIONS,
+        TOPIC_AUDIT_REPLICAS, TOPIC_CLASS_BYTES_PER_RECORD, TOPIC_CLASS_NAME,
+        TOPIC_CLASS_PARTITIONS, TOPIC_CLASS_REPLICAS, TOPIC_CUSTOM_RESOURCE_BYTES_PER_RECORD,
+        TOPIC_CUSTOM_RESOURCE_NAME, TOPIC_CUSTOM_RESOURCE_PARTITIONS,
+        TOPIC_CUSTOM_RESOURCE_REPLICAS, TOPIC_EVENT_BYTES_PER_RECORD, TOPIC_EVENT_NAME,
+        TOPIC_EVENT_PARTITIONS, TOPIC_EVENT_REPLICAS, TOPIC_LOG_BYTES_PER_RECORD, TOPIC_LOG_NAME,
+        TOPIC_LOG_PARTITIONS, TOPIC_LOG_REPLICAS, TOPIC_PROCESSED_AUDIT_NAME,
+        TOPIC_PROCESSED_CUSTOM_RESOURCE_NAME, TOPIC_PROCESSED_EVENT_NAME,
         TOPIC_PROCESSED_RESOURCE_NAME, TOPIC_RESOURCE_BYTES_PER_RECORD, TOPIC_RESOURCE_NAME,
         TOPIC_RESOURCE_PARTITIONS, TOPIC_RESOURCE_REPLICAS,
     },
@@ -23,10 +25,12 @@ use strum::EnumIter;
 pub enum TopicName {
     Log,
     Event,
+    Audit,
     Resource,
     CustomResource,
     Class,
     ProcessedEvent,
+    ProcessedAudit,
     ProcessedResource,
     ProcessedCustomResource,
 }
@@ -60,6 +64,12 @@ impl FluvioTopic {
                 replicas: TOPIC_EVENT_REPLICAS,
                 max_bytes: TOPIC_EVENT_BYTES_PER_RECORD,
             },
+            TopicName::Audit => FluvioTopic {
+                name: TOPIC_AUDIT_NAME.to_owned(),
+                partitions: TOPIC_AUDIT_PARTITIONS,
+                replicas: TOPIC_AUDIT_REPLICAS,
+                max_bytes: TOPIC_AUDIT_BYTES_PER_RECORD,
+            },
             TopicName::Resource => FluvioTopic {
                 name: TOPIC_RESOURCE_NAME.to_owned(),
                 partitions: TOPIC_RESOURCE_PARTITIONS,
@@ -78,6 +88,12 @@ impl FluvioTopic {
                 replicas: TOPIC_EVENT_REPLICAS,
                 max_bytes: TOPIC_EVENT_BYTES_PER_RECORD,
             },
+            TopicName::ProcessedAudit => FluvioTopic {
+                name: TOPIC_PROCESSED_AUDIT_NAME.to_owned(),
+                partitions: TOPIC_AUDIT_PARTITIONS,
+                replicas: TOPIC_AUDIT_REPLICAS,
+                max_bytes: TOPIC_AUDIT_BYTES_PER_RECORD,
+            },
             TopicName::ProcessedResource => FluvioTopic {
                 name: TOPIC_PROCESSED_RESOURCE_NAME.to_owned(),
                 partitions: TOPIC_RESOURCE_PARTITIONS,
@@ -1,9 +1,12 @@
-use crate::constant::GREPTIME_TABLE_KEY;
+use crate::constant::{GREPTIME_CLASSIFIED_LOG_KIND, GREPTIME_TABLE_KEY};
 use crate::log_error;
 use crate::types::metadata::Metadata;
+use crate::types::record::classified::ClassifiedLogRecord;
+use crate::types::record::log::LogRecord;
 use crate::ConfigError;
 
 use super::config::GreptimeConfig;
+use chrono::NaiveDateTime;
 use greptimedb_ingester::{Client as GreptimeClient, ClientBuilder, Database, StreamInserter};
 use rocket::{request::FromRequest, State};
 use sqlx::postgres::PgRow;
@@ -172,6 +175,57 @@ impl GreptimeConnection {
         let rows = psql.fetch_all(&*query).await?;
         Ok(rows)
     }
+
+    // The `query_logs` function returns the log lines of a pod table, ordered by timestamp.
+    // The range is given in milliseconds, the start is inclusive and the end exclusive.
+    // Lines written before the container was stored have no container, it is left empty.
+    pub async fn query_logs(
+        &self,
+        db: &str,
+        table: &GreptimeTable,
+        start: Option<i64>,
+        end: Option<i64>,
+    ) -> Result<Vec<LogRecord>, GreptimeConnectionError> {
+        let psql = self.connect_db(db).await?;
+        let mut conditions = Vec::new();
+        if let Some(start) = start {
+            conditions.push(format!("timestamp >= to_timestamp_millis({start})"));
+        }
+        if let Some(end) = end {
+            conditions.push(format!("timestamp < to_timestamp_millis({end})"));
+        }
+        let filter = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!(" WHERE {}", conditions.join(" and "))
+        };
+        let query = format!(
+            "SELECT * FROM \"{}\"{} ORDER BY timestamp",
+            table.format_name(),
+            filter
+        );
+        let rows = psql.fetch_all(&*query).await?;
+
+        let metadata = Metadata::from_pod(&table.namespace, &table.name, &table.uid, "");
+        rows.iter()
+            .map(|row| {
+                let timestamp = row.try_get::<NaiveDateTime, _>("timestamp")?;
+                let message = row.try_get::<String, _>("message")?;
+                let record_id = row.try_get::<String, _>("record_id")?;
+                let mut log = LogRecord::new(
+                    timestamp.and_utc().timestamp_millis(),
+                    &message,
+                    record_id,
+                    &metadata,
+                );
+                // the column is missing in tables of older lines
+                if let Ok(Some(container)) = row.try_get::<Option<String>, _>("container") {
+                    log.container = container;
+                }
+                Ok(log)
+            })
+            .collect()
+    }
 }
 
 #[rocket::async_trait]
@@ -305,6 +359,19 @@ impl From<&Metadata> for GreptimeTable {
     }
 }
 
+// Classified logs are stored next to the raw logs of the pod, they share the `record_id`
+impl From<&ClassifiedLogRecord> for GreptimeTable {
+    fn from(log: &ClassifiedLogRecord) -> Self {
+        GreptimeTable {
+            kind: GREPTIME_CLASSIFIED_LOG_KIND.to_string(),
+            namespace: log.namespace.clone(),
+            name: log.key.clone(),
+            uid: log.pod_uid.clone(),
+            is_deleted: false,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::collections::HashMap;
@@ -3,16 +3,50 @@ use std::collections::HashMap;
 use greptimedb_ingester::api::v1::{column, Column, ColumnDataType, InsertRequest, SemanticType};
 
 use crate::{
-    connections::greptime::greptime_connection::GreptimeTable, types::record::log::LogRecord,
+    connections::greptime::greptime_connection::GreptimeTable,
+    types::{
+        audit::AuditEvent,
+        record::{classified::ClassifiedLogRecord, log::LogRecord},
+    },
 };
 
 pub fn logs_to_insert_request(logs: &Vec<LogRecord>, table: GreptimeTable) -> InsertRequest {
     let (timestamps, message, record_id) = fold_log_records(logs);
+    let severity = logs.iter().map(|log| log.severity.to_string()).collect();
+    let stream = logs.iter().map(|log| log.stream.to_string()).collect();
+    let container = logs.iter().map(|log| log.container.to_owned()).collect();
 
     let columns: Vec<Column> = vec![
         timestamp_column(timestamps),
         string_column("message", message),
         tag_column("record_id", record_id),
+        string_column("severity", severity),
+        string_column("stream", stream),
+        string_column("container", container),
+    ];
+
+    InsertRequest {
+        table_name: table.format_name(),
+        columns,
+        row_count: logs.len() as u32,
+    }
+}
+
+pub fn classified_logs_to_insert_request(
+    logs: &Vec<ClassifiedLogRecord>,
+    table: GreptimeTable,
+) -> InsertRequest {
+    let (timestamps, record_id, class_id, similarity, preprocessed_message) =
+        fold_classified_log_records(logs);
+    let severity = logs.iter().map(|log| log.severity.to_string()).collect();
+
+    let columns: Vec<Column> = vec![
+        timestamp_column(timestamps),
+        tag_column("record_id", record_id),
+        tag_column("class_id", class_id),
+        float_column("similarity", similarity),
+        string_column("preprocessed_message", preprocessed_message),
+        string_column("severity", severity),
     ];
 
     InsertRequest {
@@ -76,6 +110,46 @@ pub fn resource_to_insert_request(
     }
 }
 
+// The user, the object and the response of an audit event, the object is empty for requests of
+// non-resource URLs.
+pub fn audit_to_insert_request(event: &AuditEvent, table: GreptimeTable) -> InsertRequest {
+    let object = event.object_ref.clone().unwrap_or_default();
+    let response = event.response_status.clone().unwrap_or_default();
+    let impersonated_user = event
+        .impersonated_user
+        .as_ref()
+        .map(|user| user.username.to_owned())
+        .unwrap_or_default();
+    let columns = vec![
+        timestamp_column(vec![event.timestamp()]),
+        tag_column("audit_id", vec![event.audit_id.to_owned()]),
+        string_column("stage", vec![event.stage.to_owned()]),
+        string_column("verb", vec![event.verb.to_owned()]),
+        string_column("request_uri", vec![event.request_uri.to_owned()]),
+        string_column("username", vec![event.user.username.to_owned()]),
+        string_column("groups", vec![event.user.groups.join(",")]),
+        string_column("impersonated_user", vec![impersonated_user]),
+        string_column("source_ips", vec![event.source_ips.join(",")]),
+        string_column("user_agent", vec![event.user_agent.to_owned()]),
+        string_column("api_group", vec![object.api_group]),
+        string_column("resource", vec![object.resource]),
+        string_column("subresource", vec![object.subresource]),
+        string_column("namespace", vec![object.namespace]),
+        string_column("name", vec![object.name]),
+        string_column("uid", vec![object.uid]),
+        int_column("response_code", vec![response.code]),
+        string_column("response_status", vec![response.status]),
+        string_column("response_reason", vec![response.reason]),
+        string_column("response_message", vec![response.message]),
+    ];
+
+    InsertRequest {
+        table_name: table.format_name(),
+        columns,
+        row_count: 1,
+    }
+}
+
 pub fn create_string_columns(map: HashMap<&str, String>, ts: Option<i64>) -> Vec<Column> {
     let mut columns: Vec<Column> = vec![];
     if let Some(ts) = ts {
@@ -125,6 +199,32 @@ fn string_column(column_name: &str, data: Vec<String>) -> Column {
     }
 }
 
+fn float_column(column_name: &str, data: Vec<f64>) -> Column {
+    Column {
+        column_name: column_name.to_owned(),
+        values: Some(column::Values {
+            f64_values: data,
+            ..Default::default()
+        }),
+        semantic_type: SemanticType::Field as i32,
+        datatype: ColumnDataType::Float64 as i32,
+        ..Default::default()
+    }
+}
+
+fn int_column(column_name: &str, data: Vec<i64>) -> Column {
+    Column {
+        column_name: column_name.to_owned(),
+        values: Some(column::Values {
+            i64_values: data,
+            ..Default::default()
+        }),
+        semantic_type: SemanticType::Field as i32,
+        datatype: ColumnDataType::Int64 as i32,
+        ..Default::default()
+    }
+}
+
 fn tag_column(column_name: &str, data: Vec<String>) -> Column {
     Column {
         column_name: column_name.to_owned(),
@@ -174,3 +274,27 @@ fn fold_log_records(logs: &Vec<LogRecord>) -> (Vec<i64>, Vec<String>, Vec<String
     // Use the macro to extract the specified fields from the logs
     fold_records!(logs, timestamp, message, record_id)
 }
+
+/// Extracts the classification fields from a vector of `ClassifiedLogRecord` into separate vectors.
+///
+/// # Returns
+///
+/// A tuple of vectors containing the extracted fields:
+/// - timestamps: `Vec<i64>` representing the timestamps.
+/// - record_ids: `Vec<String>` representing the record IDs of the raw log lines.
+/// - class_ids: `Vec<String>` representing the IDs of the matched classes.
+/// - similarities: `Vec<f64>` representing the similarity of the line to its class.
+/// - preprocessed_messages: `Vec<String>` representing the preprocessed messages.
+#[allow(clippy::type_complexity)]
+fn fold_classified_log_records(
+    logs: &Vec<ClassifiedLogRecord>,
+) -> (Vec<i64>, Vec<String>, Vec<String>, Vec<f64>, Vec<String>) {
+    fold_records!(
+        logs,
+        timestamp,
+        record_id,
+        class_id,
+        similarity,
+        preprocessed_message
+    )
+}
@@ -1,7 +1,7 @@
 use serde::{Deserialize, Serialize};
 use tracing::error;
 
-use crate::testdata::UserTestData;
+use crate::{preprocessing::severity::Severity, testdata::UserTestData};
 
 pub fn format_tool_args<T: fmt::Display>(args: &T) -> String {
     args.to_string()
@@ -79,6 +79,9 @@ impl From<String> for CreateDeploymentArgs {
 pub struct LogRetrievalArgs {
     pub namespace: Option<String>,
     pub application: Option<String>,
+    // minimum severity, e.g. `error` also returns `fatal` logs
+    #[serde(default)]
+    pub severity: Option<Severity>,
     pub intention: String,
 }
 impl LogRetrievalArgs {
@@ -86,6 +89,7 @@ impl LogRetrievalArgs {
         LogRetrievalArgs {
             namespace: testdata.namespace.to_owned(),
             application: testdata.application.to_owned(),
+            severity: None,
             intention: "".to_owned(),
         }
     }
@@ -116,6 +120,30 @@ impl From<String> for EventRetrievalArgs {
     }
 }
 
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+pub struct AuditRetrievalArgs {
+    pub namespace: Option<String>,
+    pub name: Option<String>,
+    pub resource: Option<String>,
+    pub verb: Option<String>,
+    pub intention: String,
+}
+
+impl From<String> for AuditRetrievalArgs {
+    fn from(json_string: String) -> Self {
+        serde_json::from_str(&json_string).unwrap_or_else(|e| {
+            error!("Failed to parse AuditRetrievalArgs: {}, using default", e);
+            Self::default()
+        })
+    }
+}
+
+impl AuditRetrievalArgs {
+    pub fn search_prompt(&self, user_message: &str) -> String {
+        format_search_prompt(self, user_message)
+    }
+}
+
 use std::fmt;
 
 impl fmt::Display for ResourceStatusRetrievalArgs {
@@ -158,6 +186,12 @@ impl fmt::Display for LogRetrievalArgs {
                 "application={}",
                 self.application.as_deref().unwrap_or("None")
             ),
+            format!(
+                "severity={}",
+                self.severity
+                    .map(|severity| severity.to_string())
+                    .unwrap_or("None".to_string())
+            ),
             format!("intention=\"{}\"", self.intention),
         ];
 
@@ -180,3 +214,17 @@ impl fmt::Display for EventRetrievalArgs {
         write!(f, "{}", parts.join(", "))
     }
 }
+
+impl fmt::Display for AuditRetrievalArgs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts = [
+            format!("namespace={}", self.namespace.as_deref().unwrap_or("None")),
+            format!("name={}", self.name.as_deref().unwrap_or("None")),
+            format!("resource={}", self.resource.as_deref().unwrap_or("None")),
+            format!("verb={}", self.verb.as_deref().unwrap_or("None")),
+            format!("intention=\"{}\"", self.intention),
+        ];
+
+        write!(f, "{}", parts.join(", "))
+    }
+}
@@ -13,13 +13,15 @@ use crate::{
     connections::{
         greptime::greptime_connection::GreptimeTable,
         openai::tool_args::{
-            ClusterOverviewArgs, CreateDeploymentArgs, EventRetrievalArgs, LogRetrievalArgs,
-            ResourceStatusRetrievalArgs,
+            AuditRetrievalArgs, ClusterOverviewArgs, CreateDeploymentArgs, EventRetrievalArgs,
+            LogRetrievalArgs, ResourceStatusRetrievalArgs,
         },
-        qdrant::{EventQdrantMetadata, ResourceQdrantMetadata},
+        qdrant::{AuditQdrantMetadata, EventQdrantMetadata, ResourceQdrantMetadata},
     },
     log_error,
-    qdrant_util::{create_filter, create_filter_with_data_type},
+    qdrant_util::{
+        create_audit_filter, create_filter, create_filter_with_data_type, create_log_filter,
+    },
     types::class::vectorized::{from_scored_point, VectorizedClass},
     DbName, GreptimeConnection, QdrantConnection,
 };
@@ -31,6 +33,7 @@ pub fn list_all_tools() -> Vec<ChatCompletionTool> {
         Tool::ClusterOverview(ClusterOverviewArgs::default()).into(),
         Tool::LogRetrieval(LogRetrievalArgs::default()).into(),
         Tool::EventRetrieval(EventRetrievalArgs::default()).into(),
+        Tool::AuditRetrieval(AuditRetrievalArgs::default()).into(),
         Tool::ResourceStatusRetrieval(ResourceStatusRetrievalArgs::default()).into(),
         Tool::CustomResourceStatusRetrieval(ResourceStatusRetrievalArgs::default()).into(),
         Tool::ResourceSpecRetrieval(ResourceStatusRetrievalArgs::default()).into(),
@@ -45,6 +48,7 @@ pub enum Tool {
     CreateDeployment(CreateDeploymentArgs),
     LogRetrieval(LogRetrievalArgs),
     EventRetrieval(EventRetrievalArgs),
+    AuditRetrieval(AuditRetrievalArgs),
     ResourceStatusRetrieval(ResourceStatusRetrievalArgs),
     ResourceSpecRetrieval(ResourceStatusRetrievalArgs),
     CustomResourceStatusRetrieval(ResourceStatusRetrievalArgs),
@@ -58,6 +62,7 @@ impl fmt::Display for Tool {
             Tool::CreateDeployment(_) => "deployment-options",
             Tool::LogRetrieval(_) => "log-retrieval",
             Tool::EventRetrieval(_) => "event-retrieval",
+            Tool::AuditRetrieval(_) => "audit-retrieval",
             Tool::ResourceStatusRetrieval(_) => "resource-status-retrieval",
             Tool::ResourceSpecRetrieval(_) => "resource-spec-retrieval",
             Tool::CustomResourceStatusRetrieval(_) => "customresource-status-retrieval",
@@ -76,6 +81,7 @@ impl Tool {
             Tool::CustomResourceStatusRetrieval(args) => args,
             Tool::CustomResourceSpecRetrieval(args) => args,
             Tool::EventRetrieval(args) => args,
+            Tool::AuditRetrieval(args) => args,
             Tool::LogRetrieval(args) => args,
             Tool::CreateDeployment(args) => args,
         }
@@ -93,6 +99,7 @@ impl TryFrom<FunctionCall> for Tool {
             "deployment-options" => Ok(Tool::CreateDeployment(arguments.into())),
             "log-retrieval" => Ok(Tool::LogRetrieval(arguments.into())),
             "event-retrieval" => Ok(Tool::EventRetrieval(arguments.into())),
+            "audit-retrieval" => Ok(Tool::AuditRetrieval(arguments.into())),
             "resource-status-retrieval" => Ok(Tool::ResourceStatusRetrieval(arguments.into())),
             "resource-spec-retrieval" => Ok(Tool::ResourceSpecRetrieval(arguments.into())),
             "customresource-status-retrieval" => {
@@ -173,25 +180,30 @@ impl Tool {
                 // Args are then filled by the model
                 // TODO: derive the parameters from the args, e.g. special serialization impl
                 name: self.to_string(),
-                description: Some("Retrieve logs from the kubernetes cluster".to_string()),
+                description: Some("Retrieve logs from the kubernetes cluster, grouped by the workload that wrote them".to_string()),
                 parameters: Some(json!({
                     "type": "object",
                     "properties": {
                         "application": {
                             "type": ["string", "null"],
-                            "description": "Name of the application, service, or container",
+                            "description": "Name of the workload, e.g. a deployment, statefulset or daemonset, or of the container. Pod names are mapped to their workload.",
                         },
                         "namespace": {
                             "type": ["string", "null"],
                             "description": "Name of the namespace"
                         },
+                        "severity": {
+                            "type": ["string", "null"],
+                            "enum": ["trace", "debug", "info", "warn", "error", "fatal", null],
+                            "description": "Minimum severity of the logs, e.g. error when the user asks for errors or failures. Null to search logs of any severity."
+                        },
                         "intention": {
                             "type": "string",
                             "description": "The users intention. What does the user want to achieve with their question?"
                         }
                     },
                     "additionalProperties": false,
-                    "required": ["application", "namespace", "intention"]
+                    "required": ["application", "namespace", "severity", "intention"]
                 })),
                 strict: Some(true),
             },
@@ -203,7 +215,7 @@ impl Tool {
                     "properties": {
                         "application": {
                             "type": ["string", "null"],
-                            "description": "Name of the application, service, or container",
+                            "description": "Name of the workload, e.g. a deployment, statefulset or daemonset, or of the container. Pod names are mapped to their workload.",
                         },
                         "namespace": {
                             "type": ["string", "null"],
@@ -223,6 +235,39 @@ impl Tool {
                 })),
                 strict: Some(true),
             },
+            Tool::AuditRetrieval(_) => FunctionObject {
+                name: self.to_string(),
+                description: Some("Retrieve the audit log of the kubernetes API server, who created, changed or deleted resources".to_string()),
+                parameters: Some(json!({
+                    "type": "object",
+                    "properties": {
+                        "namespace": {
+                            "type": ["string", "null"],
+                            "description": "Name of the namespace"
+                        },
+                        "name": {
+                            "type": ["string", "null"],
+                            "description": "Name of the resource",
+                        },
+                        "resource": {
+                            "type": ["string", "null"],
+                            "description": "Resource type in plural, e.g. deployments, pods or configmaps"
+                        },
+                        "verb": {
+                            "type": ["string", "null"],
+                            "enum": ["create", "update", "patch", "delete", "deletecollection", null],
+                            "description": "The request verb, e.g. delete when the user asks who deleted a resource. Null to search any change."
+                        },
+                        "intention": {
+                            "type": "string",
+                            "description": "The users intention. What does the user want to know about the changes to the cluster?"
+                        }
+                    },
+                    "additionalProperties": false,
+                    "required": ["namespace", "name", "resource", "verb", "intention"]
+                })),
+                strict: Some(true),
+            },
             Tool::ResourceStatusRetrieval(_) => FunctionObject {
                 name: self.to_string(),
                 description: Some("Retrieve the status key of resources from the kubernetes cluster".to_string()),
@@ -419,7 +464,11 @@ if a databases are specified provide the exact yaml."###,
                 let db = DbName::Log.id(customer_id);
                 let search_prompt = create_search_prompt(user_message, &args);
                 let array = request_embedding(&vec![search_prompt]).await.unwrap()[0];
-                let filter = create_filter(args.namespace.as_ref(), args.application.as_ref());
+                let filter = create_log_filter(
+                    args.namespace.as_ref(),
+                    args.application.as_ref(),
+                    args.severity,
+                );
                 let points = qdrant.search_points(&db, array, filter, 30).await?;
                 let classes = from_scored_point(points)?;
                 let result = classes
@@ -442,6 +491,29 @@ if a databases are specified provide the exact yaml."###,
                     .collect::<String>();
                 Ok(format!("{header}\n{result}"))
             }
+            Tool::AuditRetrieval(args) => {
+                let db = DbName::Audit.id(customer_id);
+                let search_prompt = args.search_prompt(user_message);
+                let array = request_embedding(&vec![search_prompt]).await.unwrap()[0];
+                let filter = create_audit_filter(
+                    args.namespace.as_ref(),
+                    args.name.as_ref(),
+                    args.resource.as_ref(),
+                    args.verb.as_ref(),
+                );
+                let entries = qdrant.search_points(&db, array, filter, 10).await?;
+
+                let header = "These are audit log entries in the format. Time User Verb Resource Namespace/Name: Response code, Score: ...".to_string();
+                let result = entries
+                    .into_iter()
+                    .map(|sp| {
+                        format_audit(sp)
+                            .map_err(|e| log_error!(e))
+                            .unwrap_or_default()
+                    })
+                    .collect::<String>();
+                Ok(format!("{header}\n{result}"))
+            }
             Tool::ResourceStatusRetrieval(args) => {
                 let db = DbName::Resource.id(customer_id);
                 let search_prompt = args.search_prompt(user_message);
@@ -518,6 +590,10 @@ if a databases are specified provide the exact yaml."###,
             Tool::EventRetrieval(_) => {
                 "message: Stopping container hello-server\nreason: Killing".to_owned()
             }
+            Tool::AuditRetrieval(_) => {
+                "2024-03-16T05:28:18.351044Z jane@example.com delete deployments shop/cart: 200"
+                    .to_owned()
+            }
             Tool::ResourceStatusRetrieval(_) => {
                 "Resource status: OOMKilled exit code 137".to_owned()
             }
@@ -582,10 +658,24 @@ pub fn create_search_prompt(user_message: &str, args: &LogRetrievalArgs) -> Stri
 }
 
 pub fn format_log_entry(vc: &VectorizedClass) -> String {
-    format!(
-        "\n{}/{}, Score {}: {}",
-        vc.namespace, vc.key, vc.score, vc.representation
-    )
+    let mut entry = format!(
+        "\n{}/{}, Severity {}, Score {}: {}",
+        vc.namespace, vc.key, vc.severity, vc.score, vc.representation
+    );
+    for variable in &vc.variables {
+        let values = variable
+            .values
+            .iter()
+            .map(|v| format!("{} ({}x)", v.value, v.count))
+            .collect::<Vec<String>>()
+            .join(", ");
+        entry.push_str(&format!(
+            "\n  Observed values of token {}: {}",
+            variable.position + 1,
+            values
+        ));
+    }
+    entry
 }
 
 pub fn format_event(sp: ScoredPoint) -> Result<String, serde_json::Error> {
@@ -603,6 +693,12 @@ pub fn format_event(sp: ScoredPoint) -> Result<String, serde_json::Error> {
     ))
 }
 
+pub fn format_audit(sp: ScoredPoint) -> Result<String, serde_json::Error> {
+    let score = sp.score;
+    let audit = AuditQdrantMetadata::try_from(sp)?;
+    Ok(format!("{}, Score: {}\n", audit.data, score))
+}
+
 pub fn format_resource_status(sp: ScoredPoint) -> Result<String, serde_json::Error> {
     let score = sp.score;
     let resource = ResourceQdrantMetadata::try_from(sp)?;
@@ -622,7 +718,8 @@ mod tests {
 
     use crate::connections::openai::messages::create_iteration_loop_message;
     use crate::connections::openai::tool_args::{
-        ClusterOverviewArgs, EventRetrievalArgs, LogRetrievalArgs, ResourceStatusRetrievalArgs,
+        AuditRetrievalArgs, ClusterOverviewArgs, EventRetrievalArgs, LogRetrievalArgs,
+        ResourceStatusRetrievalArgs,
     };
     use crate::connections::openai::util::aggregate_answer;
     use crate::openai_util::{
@@ -814,6 +911,7 @@ mod tests {
     #[case(Tool::ClusterOverview(ClusterOverviewArgs::default()))]
     #[case(Tool::LogRetrieval(LogRetrievalArgs::default()))]
     #[case(Tool::EventRetrieval(EventRetrievalArgs::default()))]
+    #[case(Tool::AuditRetrieval(AuditRetrievalArgs::default()))]
     #[case(Tool::ResourceSpecRetrieval(ResourceStatusRetrievalArgs::default()))]
     #[case(Tool::ResourceStatusRetrieval(ResourceStatusRetrievalArgs::default()))]
     #[case(Tool::CustomResourceSpecRetrieval(ResourceStatusRetrievalArgs::default()))]
@@ -17,4 +17,6 @@ pub enum QdrantConnectionError {
     UpsertPoints(#[source] QdrantError),
     #[error("Qdrant set payload error: {0}")]
     SetPayload(#[source] QdrantError),
+    #[error("Qdrant delete points error: {0}")]
+    DeletePoints(#[source] QdrantError),
 }
@@ -5,7 +5,7 @@ use qdrant_client::qdrant::ScoredPoint;
 use serde::{Deserialize, Serialize};
 use uuid7::uuid4;
 
-use crate::types::class::vectorized::Id;
+use crate::types::{audit::AuditEvent, class::vectorized::Id};
 
 pub mod config;
 pub mod error;
@@ -123,3 +123,60 @@ impl Id for EventQdrantMetadata {
         &self.data
     }
 }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditQdrantMetadata {
+    pub qdrant_uid: String,
+    pub audit_id: String,
+    pub timestamp: i64,
+    pub verb: String,
+    pub username: String,
+    pub resource: String,
+    pub name: String,
+    pub namespace: String,
+    pub response_code: i64,
+    pub data: String,
+}
+
+impl AuditQdrantMetadata {
+    pub fn new(event: &AuditEvent, data: String) -> Self {
+        let object = event.object_ref.clone().unwrap_or_default();
+        let namespace = if object.namespace.is_empty() {
+            "not_namespaced".to_string()
+        } else {
+            object.namespace
+        };
+        Self {
+            qdrant_uid: uuid4().to_string(),
+            audit_id: event.audit_id.to_owned(),
+            timestamp: event.timestamp(),
+            verb: event.verb.to_owned(),
+            username: event.user.username.to_owned(),
+            resource: object.resource,
+            name: object.name,
+            namespace,
+            response_code: event.response_code(),
+            data,
+        }
+    }
+}
+
+impl TryFrom<ScoredPoint> for AuditQdrantMetadata {
+    type Error = serde_json::Error;
+
+    fn try_from(point: ScoredPoint) -> Result<Self, Self::Error> {
+        let payload: HashMap<String, qdrant::Value> = point.payload;
+        let json_value = serde_json::to_value(payload)?;
+        let audit: AuditQdrantMetadata = serde_json::from_value(json_value)?;
+        Ok(audit)
+    }
+}
+
+impl Id for AuditQdrantMetadata {
+    fn get_id(&self) -> &str {
+        &self.qdrant_uid
+    }
+    fn get_data(&self) -> &str {
+        &self.data
+    }
+}
@@ -2,9 +2,9 @@ use std::sync::Arc;
 
 use qdrant_client::{
     qdrant::{
-        Condition, CreateCollectionBuilder, Distance, Filter, PointStruct, PointsOperationResponse,
-        QueryPointsBuilder, ScoredPoint, SearchPointsBuilder, SetPayloadPointsBuilder,
-        UpsertPointsBuilder, VectorParamsBuilder,
+        Condition, CreateCollectionBuilder, DeletePointsBuilder, Distance, Filter, PointStruct,
+        PointsIdsList, PointsOperationResponse, QueryPointsBuilder, ScoredPoint,
+        SearchPointsBuilder, SetPayloadPointsBuilder, UpsertPointsBuilder, VectorParamsBuilder,
     },
     Payload, Qdrant, QdrantError,
 };
@@ -14,6 +14,8 @@ use tracing::info;
 
 use crate::{
     constant::{EMBEDDING_SIZE, EMBEDDING_USIZE},
+    preprocessing::severity::Severity,
+    types::metadata::workload_name,
     QdrantConnectionError,
 };
 
@@ -77,6 +79,20 @@ impl QdrantConnection {
             .await
             .map_err(QdrantConnectionError::UpsertPoints)
     }
+    pub async fn delete_points(
+        &self,
+        ids: &[String],
+        db: &str,
+    ) -> Result<PointsOperationResponse, QdrantConnectionError> {
+        let ids = ids.iter().map(|id| id.as_str().into()).collect();
+        let request = DeletePointsBuilder::new(db)
+            .points(PointsIdsList { ids })
+            .wait(false);
+        self.client
+            .delete_points(request)
+            .await
+            .map_err(QdrantConnectionError::DeletePoints)
+    }
     pub async fn set_payload(
         &self,
         db: &str,
@@ -137,6 +153,29 @@ pub fn create_filter(namespace: Option<&String>, application: Option<&String>) -
     Filter::must(conditions)
 }
 
+// The `create_log_filter` function matches log classes of the given severity or higher. Classes
+// are keyed by their workload, the application matches the workload, also when it is given as a
+// pod name, or the container.
+pub fn create_log_filter(
+    namespace: Option<&String>,
+    application: Option<&String>,
+    severity: Option<Severity>,
+) -> Filter {
+    let mut filter = create_filter(namespace, None);
+    if let Some(val) = application {
+        let application = Filter::should([
+            Condition::matches("key", workload_name(val)),
+            Condition::matches("container", val.to_owned()),
+        ]);
+        filter.must.push(application.into());
+    }
+    if let Some(val) = severity {
+        let severities: Vec<String> = val.at_least().iter().map(Severity::to_string).collect();
+        filter.must.push(Condition::matches("severity", severities));
+    }
+    filter
+}
+
 pub fn create_filter_with_data_type(
     namespace: Option<&String>,
     application: Option<&String>,
@@ -154,6 +193,31 @@ pub fn create_filter_with_data_type(
     Filter::must(conditions)
 }
 
+// The `create_audit_filter` function matches audit entries by the namespace, name and resource of
+// the object and the request verb. Resources are stored in lowercase plural, e.g. `deployments`.
+pub fn create_audit_filter(
+    namespace: Option<&String>,
+    name: Option<&String>,
+    resource: Option<&String>,
+    verb: Option<&String>,
+) -> Filter {
+    let mut conditions = Vec::new();
+    if let Some(val) = namespace {
+        conditions.push(Condition::matches("namespace", val.to_owned()));
+    }
+    if let Some(val) = name {
+        conditions.push(Condition::matches("name", val.to_owned()));
+    }
+    if let Some(val) = resource {
+        conditions.push(Condition::matches("resource", val.to_lowercase()));
+    }
+    if let Some(val) = verb {
+        conditions.push(Condition::matches("verb", val.to_owned()));
+    }
+
+    Filter::must(conditions)
+}
+
 pub fn match_any(key: &str, values: &[String]) -> Filter {
     let conditions: Vec<Condition> = values
         .iter()
@@ -0,0 +1,248 @@
+use chrono::Utc;
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+
+use super::config::RedisConfig;
+use super::redis_connection::RedisConnectionError;
+use redis::{aio::MultiplexedConnection, AsyncCommands, Client, FromRedisValue, Script};
+
+// Adds the counts to the fields of a hash unless a limited field is at its limit or would go over
+// it. KEYS[1] is the hash, ARGV holds the expiry in seconds, the number of counts, the field/count
+// pairs and then the field/limit pairs.
+static INCREMENT_BOUNDED_SCRIPT: Lazy<Script> = Lazy::new(|| {
+    Script::new(
+        r"
+local counts = tonumber(ARGV[2])
+local function added(field)
+    for i = 3, 2 + 2 * counts, 2 do
+        if ARGV[i] == field then
+            return tonumber(ARGV[i + 1])
+        end
+    end
+    return 0
+end
+for i = 3 + 2 * counts, #ARGV, 2 do
+    local current = tonumber(redis.call('HGET', KEYS[1], ARGV[i]) or '0')
+    local limit = tonumber(ARGV[i + 1])
+    if current >= limit or current + added(ARGV[i]) > limit then
+        return 0
+    end
+end
+for i = 3, 2 + 2 * counts, 2 do
+    redis.call('HINCRBY', KEYS[1], ARGV[i], ARGV[i + 1])
+end
+redis.call('EXPIRE', KEYS[1], ARGV[1])
+return 1
+",
+    )
+});
+
+// Reserves the members that were neither seen nor reserved. KEYS[1] is the sorted set of the seen
+// members, KEYS[2] the one of the reserved members. ARGV holds the current time, the seconds the
+// members are kept, the seconds of a reservation and then the members.
+static RESERVE_SCRIPT: Lazy<Script> = Lazy::new(|| {
+    Script::new(
+        r"
+local now = tonumber(ARGV[1])
+redis.call('ZREMRANGEBYSCORE', KEYS[1], '-inf', now - tonumber(ARGV[2]))
+redis.call('ZREMRANGEBYSCORE', KEYS[2], '-inf', now)
+local reserved = {}
+for i = 4, #ARGV do
+    if redis.call('ZSCORE', KEYS[1], ARGV[i]) or redis.call('ZSCORE', KEYS[2], ARGV[i]) then
+        reserved[#reserved + 1] = 0
+    else
+        redis.call('ZADD', KEYS[2], now + tonumber(ARGV[3]), ARGV[i])
+        reserved[#reserved + 1] = 1
+    end
+end
+redis.call('EXPIRE', KEYS[2], ARGV[3])
+return reserved
+",
+    )
+});
+
+// A Redis connection for async callers, e.g. the request guards of the intake. The connection is
+// multiplexed, so clones share it and concurrent requests don't wait for each other's commands.
+#[derive(Clone)]
+pub struct AsyncRedisConnection {
+    connection: MultiplexedConnection,
+}
+
+impl AsyncRedisConnection {
+    pub async fn new() -> Result<Self, RedisConnectionError> {
+        let config = RedisConfig::new()?;
+        let client = Client::open(config.get_uri()).map_err(RedisConnectionError::RedisInit)?;
+        let connection = client
+            .get_multiplexed_async_connection()
+            .await
+            .map_err(RedisConnectionError::RedisInit)?;
+        Ok(AsyncRedisConnection { connection })
+    }
+
+    pub async fn get<T: FromRedisValue>(
+        &self,
+        key: &str,
+    ) -> Result<Option<T>, RedisConnectionError> {
+        self.connection
+            .clone()
+            .get(key)
+            .await
+            .map_err(RedisConnectionError::GetError)
+    }
+
+    pub async fn set(&self, key: &str, value: &str) -> Result<(), RedisConnectionError> {
+        let _res: () = self
+            .connection
+            .clone()
+            .set(key, value)
+            .await
+            .map_err(RedisConnectionError::SetError)?;
+        Ok(())
+    }
+
+    pub async fn delete(&self, key: &str) -> Result<(), RedisConnectionError> {
+        let _res: () = self
+            .connection
+            .clone()
+            .del(key)
+            .await
+            .map_err(RedisConnectionError::SetError)?;
+        Ok(())
+    }
+
+    // The `increment` function adds the counts to the fields of a hash, e.g. to count what was
+    // done for a customer.
+    pub async fn increment(
+        &self,
+        key: &str,
+        counts: &[(&str, u64)],
+    ) -> Result<(), RedisConnectionError> {
+        let mut pipeline = redis::pipe();
+        for (field, count) in counts {
+            pipeline.hincr(key, *field, *count).ignore();
+        }
+        let _res: () = pipeline
+            .query_async(&mut self.connection.clone())
+            .await
+            .map_err(RedisConnectionError::SetError)?;
+        Ok(())
+    }
+
+    // The `increment_expiring` function increments like `increment` and lets the hash expire after
+    // `seconds`, e.g. for the counts of a time window.
+    pub async fn increment_expiring(
+        &self,
+        key: &str,
+        counts: &[(&str, u64)],
+        seconds: i64,
+    ) -> Result<(), RedisConnectionError> {
+        let mut pipeline = redis::pipe();
+        for (field, count) in counts {
+            pipeline.hincr(key, *field, *count).ignore();
+        }
+        pipeline.expire(key, seconds).ignore();
+        let _res: () = pipeline
+            .query_async(&mut self.connection.clone())
+            .await
+            .map_err(RedisConnectionError::SetError)?;
+        Ok(())
+    }
+
+    // The `increment_bounded` function increments like `increment_expiring`, unless one of the
+    // `limits` is reached or would be exceeded by the counts. A count of 0 only checks that the
+    // field is below its limit. The check and the increments are one atomic step, so concurrent
+    // callers can't go over a limit together. Returns whether the counts were added.
+    pub async fn increment_bounded(
+        &self,
+        key: &str,
+        counts: &[(&str, u64)],
+        limits: &[(&str, u64)],
+        seconds: i64,
+    ) -> Result<bool, RedisConnectionError> {
+        let mut invocation = INCREMENT_BOUNDED_SCRIPT.key(key);
+        invocation.arg(seconds).arg(counts.len());
+        for (field, count) in counts.iter().chain(limits) {
+            invocation.arg(*field).arg(*count);
+        }
+        let added: i64 = invocation
+            .invoke_async(&mut self.connection.clone())
+            .await
+            .map_err(RedisConnectionError::SetError)?;
+        Ok(added == 1)
+    }
+
+    // The `reserve` function reserves the members that are neither in the sorted set `seen`, scored
+    // by the time they were first seen, nor reserved in the sorted set `pending`, scored by the time
+    // their reservation ends. Members older than `seconds` and ended reservations are removed first.
+    // Checking and reserving is one atomic step, so of concurrent callers with the same member only
+    // one reserves it. Returns for each member whether it was reserved.
+    pub async fn reserve(
+        &self,
+        seen: &str,
+        pending: &str,
+        members: &[&str],
+        seconds: i64,
+        pending_seconds: i64,
+    ) -> Result<Vec<bool>, RedisConnectionError> {
+        let now = Utc::now().timestamp();
+        let mut invocation = RESERVE_SCRIPT.key(seen);
+        invocation
+            .key(pending)
+            .arg(now)
+            .arg(seconds)
+            .arg(pending_seconds)
+            .arg(members);
+        let reserved: Vec<i64> = invocation
+            .invoke_async(&mut self.connection.clone())
+            .await
+            .map_err(RedisConnectionError::SetError)?;
+        Ok(reserved.into_iter().map(|reserved| reserved == 1).collect())
+    }
+
+    // The `settle` function ends the reservations of `reserve`. The `confirmed` members are added
+    // to the sorted set `seen`, scored by the current time, the `released` ones can be reserved
+    // again. The set is trimmed to its `max_len` newest members and expires after `seconds`.
+    pub async fn settle(
+        &self,
+        seen: &str,
+        pending: &str,
+        confirmed: &[&str],
+        released: &[&str],
+        seconds: i64,
+        max_len: isize,
+    ) -> Result<(), RedisConnectionError> {
+        let now = Utc::now().timestamp();
+        let mut pipeline = redis::pipe();
+        pipeline.atomic();
+        for member in confirmed {
+            pipeline
+                .cmd("ZADD")
+                .arg(seen)
+                .arg("NX")
+                .arg(now)
+                .arg(*member)
+                .ignore();
+        }
+        if !confirmed.is_empty() {
+            pipeline.zremrangebyrank(seen, 0, -(max_len + 1)).ignore();
+            pipeline.expire(seen, seconds).ignore();
+        }
+        for member in confirmed.iter().chain(released) {
+            pipeline.zrem(pending, *member).ignore();
+        }
+        let _res: () = pipeline
+            .query_async(&mut self.connection.clone())
+            .await
+            .map_err(RedisConnectionError::SetError)?;
+        Ok(())
+    }
+
+    // The `counts` function reads a hash of counts, fields that were never incremented are missing.
+    pub async fn counts(&self, key: &str) -> Result<HashMap<String, u64>, RedisConnectionError> {
+        self.connection
+            .clone()
+            .hgetall(key)
+            .await
+            .map_err(RedisConnectionError::GetError)
+    }
+}
@@ -1,2 +1,4 @@
+pub mod async_redis_connection;
 pub mod config;
 pub mod redis_connection;
+pub mod state_cache;
@@ -3,7 +3,9 @@ use std::time::Duration;
 use crate::{types::classifier::state::ClassifierState, ConfigError};
 
 use super::config::RedisConfig;
-use redis::{Client, Commands, Connection, FromRedisValue, RedisError, ToRedisArgs};
+use redis::{
+    cmd, transaction, Client, Commands, Connection, FromRedisValue, RedisError, ToRedisArgs,
+};
 use serde::Serialize;
 use thiserror::Error;
 use tracing::info;
@@ -27,6 +29,14 @@ pub enum RedisConnectionError {
     RetryError(u32, RedisError),
 }
 
+pub fn format_key(key_prefix: &str, kind: Option<&str>, uid: &str) -> String {
+    if let Some(kind) = kind {
+        format!("{}_{}_{}", key_prefix, kind, uid)
+    } else {
+        format!("{}_{}", key_prefix, uid)
+    }
+}
+
 impl RedisConnection {
     pub fn new() -> Result<Self, RedisConnectionError> {
         let config = RedisConfig::new()?;
@@ -39,11 +49,7 @@ impl RedisConnection {
     }
 
     pub fn key(&self, key_prefix: &str, kind: Option<&str>, uid: &str) -> String {
-        if let Some(kind) = kind {
-            format!("{}_{}_{}", key_prefix, kind, uid)
-        } else {
-            format!("{}_{}", key_prefix, uid)
-        }
+        format_key(key_prefix, kind, uid)
     }
 
     pub fn get(&mut self, key: &str) -> Result<ClassifierState, RedisConnectionError> {
@@ -58,11 +64,17 @@ impl RedisConnection {
             }
             false => {
                 info!("Creating new state for key: {}", key);
-                Ok(ClassifierState { classes: vec![] })
+                Ok(ClassifierState::default())
             }
         }
     }
 
+    pub fn exists(&mut self, key: &str) -> Result<bool, RedisConnectionError> {
+        self.connection
+            .exists(key)
+            .map_err(RedisConnectionError::GetError)
+    }
+
     pub fn set(&mut self, key: &str, value: ClassifierState) -> Result<(), RedisConnectionError> {
         let serialized_value: String = serde_json::to_string(&value).unwrap();
         let _res: () = self
@@ -72,6 +84,39 @@ impl RedisConnection {
         Ok(())
     }
 
+    // The `set_many` function writes the states in one transaction that watches their keys. If
+    // another writer changed one of the keys in the meantime, the transaction is retried with the
+    // states rebased on the current values. Returns the states as they were written.
+    pub fn set_many(
+        &mut self,
+        values: &[(&str, &ClassifierState)],
+    ) -> Result<Vec<ClassifierState>, RedisConnectionError> {
+        let keys: Vec<&str> = values.iter().map(|(key, _)| *key).collect();
+        transaction(&mut self.connection, &keys, |connection, pipeline| {
+            let current: Vec<Option<ClassifierState>> = cmd("MGET").arg(&keys).query(connection)?;
+            let next: Vec<ClassifierState> = values
+                .iter()
+                .zip(current)
+                .map(|((_, state), current)| state.rebase(current))
+                .collect();
+            for (key, state) in keys.iter().zip(&next) {
+                pipeline.set(*key, state).ignore();
+            }
+            // `None` if a watched key changed, which makes `transaction` retry
+            let result: Option<()> = pipeline.query(connection)?;
+            Ok(result.map(|_| next))
+        })
+        .map_err(RedisConnectionError::SetError)
+    }
+
+    pub fn delete(&mut self, key: &str) -> Result<(), RedisConnectionError> {
+        let _res: () = self
+            .connection
+            .del(key)
+            .map_err(RedisConnectionError::SetError)?;
+        Ok(())
+    }
+
     pub async fn retry<T, F>(
         &mut self,
         mut f: F,
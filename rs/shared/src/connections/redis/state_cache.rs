@@ -0,0 +1,381 @@
+use std::collections::{HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+use tracing::{debug, info};
+
+use crate::types::{class::Class, classifier::state::ClassifierState};
+
+use super::redis_connection::{format_key, RedisConnection, RedisConnectionError};
+
+// How a state was changed while it was used, see `StateCache::release`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum StateChange {
+    Unchanged,
+    // only counts and times of the classes, written when the state is evicted or on `flush`
+    Counts,
+    // classes were created, changed or removed, or the threshold changed
+    Classes,
+}
+
+struct CacheEntry {
+    state: ClassifierState,
+    change: StateChange,
+    last_used: u64,
+    // legacy keys whose classes were moved into the state, deleted once the state was written
+    migrated: Vec<String>,
+}
+
+// In-process LRU of classifier states. States with changed classes are written back to Redis in
+// batches when the flush interval elapsed, states whose counts changed when they are evicted, all
+// changed states on `flush`.
+// Without a connection the cache is in-memory only, it starts empty and never evicts.
+pub struct StateCache {
+    redis: Option<RedisConnection>,
+    capacity: usize,
+    flush_interval: Duration,
+    entries: HashMap<String, CacheEntry>,
+    clock: u64,
+    last_flush: Instant,
+    // legacy keys that were already looked up by `migrate`
+    checked: HashSet<String>,
+}
+
+impl StateCache {
+    pub fn new(redis: RedisConnection, capacity: usize, flush_interval: Duration) -> Self {
+        Self {
+            redis: Some(redis),
+            capacity: capacity.max(1),
+            flush_interval,
+            entries: HashMap::new(),
+            clock: 0,
+            last_flush: Instant::now(),
+            checked: HashSet::new(),
+        }
+    }
+
+    // The `in_memory` function creates a cache that is never written to Redis, e.g. to replay
+    // logs without changing the production state.
+    pub fn in_memory() -> Self {
+        Self {
+            redis: None,
+            capacity: usize::MAX,
+            flush_interval: Duration::MAX,
+            entries: HashMap::new(),
+            clock: 0,
+            last_flush: Instant::now(),
+            checked: HashSet::new(),
+        }
+    }
+
+    pub fn key(&self, key_prefix: &str, kind: Option<&str>, uid: &str) -> String {
+        format_key(key_prefix, kind, uid)
+    }
+
+    // The `states` function returns the cached states by key.
+    pub fn states(&self) -> Vec<(&str, &ClassifierState)> {
+        self.entries
+            .iter()
+            .map(|(key, entry)| (key.as_str(), &entry.state))
+            .collect()
+    }
+
+    fn load(&mut self, key: &str) -> Result<ClassifierState, RedisConnectionError> {
+        match self.redis.as_mut() {
+            Some(redis) => redis.get(key),
+            None => Ok(ClassifierState::default()),
+        }
+    }
+
+    // The `get_mut` function returns the cached state, loading it from Redis on a miss. The state
+    // stays cached while it is used, changes have to be reported with `release`.
+    pub fn get_mut(&mut self, key: &str) -> Result<&mut ClassifierState, RedisConnectionError> {
+        if !self.entries.contains_key(key) {
+            debug!("State cache miss for key: {}", key);
+            let state = self.load(key)?;
+            self.entries.insert(
+                key.to_owned(),
+                CacheEntry {
+                    state,
+                    change: StateChange::Unchanged,
+                    last_used: self.clock,
+                    migrated: Vec::new(),
+                },
+            );
+        }
+        let entry = self.entries.get_mut(key).expect("state was cached");
+        Ok(&mut entry.state)
+    }
+
+    // The `migrate` function moves the classes of a state stored under a legacy key, e.g. the
+    // per-pod key used before states were kept per workload, into the state of `key` and sets
+    // their key to `class_key`. Each legacy key is looked up once, it is deleted when the state
+    // was written. Returns the moved classes.
+    pub fn migrate(
+        &mut self,
+        key: &str,
+        legacy_key: &str,
+        class_key: &str,
+    ) -> Result<Vec<Class>, RedisConnectionError> {
+        if key == legacy_key || !self.checked.insert(legacy_key.to_owned()) {
+            return Ok(Vec::new());
+        }
+        let Some(redis) = self.redis.as_mut() else {
+            return Ok(Vec::new());
+        };
+        if !redis.exists(legacy_key)? {
+            return Ok(Vec::new());
+        }
+        let legacy = redis.get(legacy_key)?;
+        info!("Migrating classifier state from {} to {}", legacy_key, key);
+        let state = self.get_mut(key)?;
+        let mut moved = Vec::new();
+        for mut class in legacy.classes {
+            if state.classes.iter().any(|c| c.class_id == class.class_id) {
+                continue;
+            }
+            class.key = class_key.to_owned();
+            state.classes.push(class.clone());
+            moved.push(class);
+        }
+        let entry = self.entries.get_mut(key).expect("state was cached");
+        entry.migrated.push(legacy_key.to_owned());
+        entry.change = StateChange::Classes;
+        Ok(moved)
+    }
+
+    // The `release` function records how the state of `get_mut` was changed, evicts the least
+    // recently used states if the cache is full and writes the changed states if the flush
+    // interval elapsed.
+    pub fn release(&mut self, key: &str, change: StateChange) -> Result<(), RedisConnectionError> {
+        self.clock += 1;
+        if let Some(entry) = self.entries.get_mut(key) {
+            entry.change = entry.change.max(change);
+            entry.last_used = self.clock;
+        }
+        self.evict()?;
+        if self.last_flush.elapsed() >= self.flush_interval {
+            self.write(StateChange::Classes)?;
+        }
+        Ok(())
+    }
+
+    // The `peek` function returns a copy of the state without caching it, e.g. for a dry run.
+    pub fn peek(&mut self, key: &str) -> Result<ClassifierState, RedisConnectionError> {
+        match self.entries.get(key) {
+            Some(entry) => Ok(entry.state.clone()),
+            None => self.load(key),
+        }
+    }
+
+    // The `put` function replaces the state of the key, e.g. with a state read elsewhere.
+    pub fn put(&mut self, key: &str, state: ClassifierState) -> Result<(), RedisConnectionError> {
+        *self.get_mut(key)? = state;
+        self.release(key, StateChange::Classes)
+    }
+
+    // The `flush` function writes all changed states to Redis.
+    pub fn flush(&mut self) -> Result<(), RedisConnectionError> {
+        self.write(StateChange::Counts)
+    }
+
+    // The `write` function writes the states changed at least as much as `change` to Redis.
+    fn write(&mut self, change: StateChange) -> Result<(), RedisConnectionError> {
+        let Some(redis) = self.redis.as_mut() else {
+            return Ok(());
+        };
+        let dirty: Vec<(&str, &ClassifierState)> = self
+            .entries
+            .iter()
+            .filter(|(_, entry)| entry.change >= change)
+            .map(|(key, entry)| (key.as_str(), &entry.state))
+            .collect();
+        if !dirty.is_empty() {
+            debug!("Flushing {} classifier states", dirty.len());
+            let keys: Vec<String> = dirty.iter().map(|(key, _)| key.to_string()).collect();
+            let written = redis.set_many(&dirty)?;
+            // the written states carry the new version and classes merged from other writers
+            for (key, state) in keys.into_iter().zip(written) {
+                if let Some(entry) = self.entries.get_mut(&key) {
+                    entry.state = state;
+                    entry.change = StateChange::Unchanged;
+                    for legacy_key in entry.migrated.drain(..) {
+                        redis.delete(&legacy_key)?;
+                    }
+                }
+            }
+        }
+        self.last_flush = Instant::now();
+        Ok(())
+    }
+
+    fn evict(&mut self) -> Result<(), RedisConnectionError> {
+        while self.entries.len() > self.capacity {
+            let lru_key = match self.entries.iter().min_by_key(|(_, entry)| entry.last_used) {
+                Some((key, _)) => key.clone(),
+                None => break,
+            };
+            // a changed state stays cached until it was written, so a failed write doesn't lose it
+            if let (Some(entry), Some(redis)) = (self.entries.get(&lru_key), self.redis.as_mut()) {
+                if entry.change != StateChange::Unchanged {
+                    redis.set_many(&[(&lru_key, &entry.state)])?;
+                }
+                for legacy_key in &entry.migrated {
+                    redis.delete(legacy_key)?;
+                }
+            }
+            self.entries.remove(&lru_key);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::time::Duration;
+
+    use uuid7::uuid7;
+
+    use super::{StateCache, StateChange};
+    use crate::{
+        setup_tracing,
+        types::classifier::state::ClassifierState,
+        utils::mock::mock_data::{get_test_data, TestCase},
+        RedisConnection, RedisConnectionError,
+    };
+
+    #[test]
+    fn test_state_cache_write_behind() -> Result<(), RedisConnectionError> {
+        setup_tracing(false);
+        let test_data = get_test_data(TestCase::Simple);
+        let class = test_data.expected_class;
+        let keys = [
+            format!("test_state_cache_{}_a", class.class_id),
+            format!("test_state_cache_{}_b", class.class_id),
+        ];
+
+        let mut cache = StateCache::new(RedisConnection::new()?, 1, Duration::from_secs(3600));
+        let mut redis = RedisConnection::new()?;
+
+        // changes stay in memory until the state is evicted or flushed
+        cache.get_mut(&keys[0])?.classes.push(class.clone());
+        cache.release(&keys[0], StateChange::Classes)?;
+        assert!(redis.get(&keys[0])?.classes.is_empty());
+
+        // the capacity of one evicts the first key
+        cache.put(
+            &keys[1],
+            ClassifierState {
+                classes: vec![class],
+                ..Default::default()
+            },
+        )?;
+        assert_eq!(redis.get(&keys[0])?.classes.len(), 1);
+        assert!(redis.get(&keys[1])?.classes.is_empty());
+
+        cache.flush()?;
+        assert_eq!(redis.get(&keys[1])?.classes.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_state_cache_writes_counts_on_flush() -> Result<(), RedisConnectionError> {
+        setup_tracing(false);
+        let class = get_test_data(TestCase::Simple).expected_class;
+        let key = format!("test_state_cache_counts_{}", class.class_id);
+
+        let mut cache = StateCache::new(RedisConnection::new()?, 10, Duration::ZERO);
+        let mut redis = RedisConnection::new()?;
+        cache.get_mut(&key)?.classes.push(class);
+        cache.release(&key, StateChange::Classes)?;
+        assert_eq!(redis.get(&key)?.classes[0].count, 1);
+
+        // changed counts are not written when the flush interval elapsed, only on `flush`
+        cache.get_mut(&key)?.classes[0].count += 1;
+        cache.release(&key, StateChange::Counts)?;
+        assert_eq!(redis.get(&key)?.classes[0].count, 1);
+        cache.flush()?;
+        assert_eq!(redis.get(&key)?.classes[0].count, 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_state_cache_migrates_legacy_state() -> Result<(), RedisConnectionError> {
+        setup_tracing(false);
+        let class = get_test_data(TestCase::Simple).expected_class;
+        let key = format!("test_state_cache_migrate_{}", class.class_id);
+        let legacy_key = format!("test_state_cache_migrate_{}_pod", class.class_id);
+        let mut redis = RedisConnection::new()?;
+        redis.set(
+            &legacy_key,
+            ClassifierState {
+                classes: vec![class.clone()],
+                ..Default::default()
+            },
+        )?;
+
+        let mut cache = StateCache::new(RedisConnection::new()?, 10, Duration::from_secs(3600));
+        let moved = cache.migrate(&key, &legacy_key, "workload")?;
+        assert_eq!(moved.len(), 1);
+        assert_eq!(moved[0].key, "workload");
+        // each legacy key is only looked up once
+        assert!(cache.migrate(&key, &legacy_key, "workload")?.is_empty());
+        assert_eq!(cache.get_mut(&key)?.classes[0].class_id, class.class_id);
+
+        cache.flush()?;
+        assert_eq!(redis.get(&key)?.classes[0].key, "workload");
+        assert!(!redis.exists(&legacy_key)?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_state_cache_in_memory() -> Result<(), RedisConnectionError> {
+        let class = get_test_data(TestCase::Simple).expected_class;
+        let mut cache = StateCache::in_memory();
+
+        let state = cache.get_mut("key")?;
+        assert!(state.classes.is_empty());
+        state.classes.push(class);
+        cache.release("key", StateChange::Classes)?;
+        cache.flush()?;
+
+        assert_eq!(cache.peek("key")?.classes.len(), 1);
+        assert_eq!(cache.states().len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_state_cache_concurrent_writers() -> Result<(), RedisConnectionError> {
+        setup_tracing(false);
+        const WRITERS: usize = 4;
+        const CLASSES_PER_WRITER: usize = 25;
+        let class = get_test_data(TestCase::Simple).expected_class;
+        let key = format!("test_state_cache_concurrent_{}", class.class_id);
+
+        let handles: Vec<_> = (0..WRITERS)
+            .map(|_| {
+                let (key, class) = (key.clone(), class.clone());
+                std::thread::spawn(move || -> Result<(), RedisConnectionError> {
+                    // flush on every put to provoke conflicting writes
+                    let mut cache = StateCache::new(RedisConnection::new()?, 1, Duration::ZERO);
+                    for _ in 0..CLASSES_PER_WRITER {
+                        let mut class = class.clone();
+                        class.class_id = uuid7().to_string();
+                        cache.get_mut(&key)?.classes.push(class);
+                        cache.release(&key, StateChange::Classes)?;
+                    }
+                    cache.flush()
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().expect("writer panicked")?;
+        }
+
+        let state = RedisConnection::new()?.get(&key)?;
+        let class_ids: HashSet<&String> = state.classes.iter().map(|c| &c.class_id).collect();
+        assert_eq!(state.classes.len(), WRITERS * CLASSES_PER_WRITER);
+        assert_eq!(class_ids.len(), WRITERS * CLASSES_PER_WRITER);
+        Ok(())
+    }
+}
@@ -6,6 +6,8 @@ pub const DEFAULT_ITERATION_DEPTH: usize = 10;
 
 // logs
 pub const LOG_PREFIX: &str = "logs";
+// characters kubernetes uses for generated name suffixes (no vowels and no easily confused characters)
+pub const KUBERNETES_NAME_SUFFIX_ALPHABET: &str = "bcdfghjklmnpqrstvwxz2456789";
 pub const DATA_INTAKE_LIMIT_MEMIBYTES: u64 = 32;
 
 // embedding
@@ -32,7 +34,13 @@ pub const TOPIC_EVENT_PARTITIONS: u32 = 1;
 pub const TOPIC_EVENT_REPLICAS: u32 = 1;
 pub const TOPIC_EVENT_BYTES_PER_RECORD: usize = 131072;
 
+pub const TOPIC_AUDIT_NAME: &str = "audit";
+pub const TOPIC_AUDIT_PARTITIONS: u32 = 1;
+pub const TOPIC_AUDIT_REPLICAS: u32 = 1;
+pub const TOPIC_AUDIT_BYTES_PER_RECORD: usize = 131072;
+
 pub const TOPIC_PROCESSED_EVENT_NAME: &str = "processedevent";
+pub const TOPIC_PROCESSED_AUDIT_NAME: &str = "processedaudit";
 pub const TOPIC_PROCESSED_RESOURCE_NAME: &str = "processedresource";
 pub const TOPIC_PROCESSED_CUSTOM_RESOURCE_NAME: &str = "processedcustomresource";
 
@@ -48,6 +56,7 @@ pub const TOPIC_CUSTOM_RESOURCE_BYTES_PER_RECORD: usize = 131072;
 
 // greptime
 pub const GREPTIME_TABLE_KEY: &str = "Tables";
+pub const GREPTIME_CLASSIFIED_LOG_KIND: &str = "classifiedlog";
 pub const DEFAULT_NS: &str = "NON4MESPACE";
 pub const DEFAULT_NAME: &str = "NON4ME";
 pub const DEFAULT_KIND: &str = "NOK1ND";
@@ -35,13 +35,17 @@ pub use crate::connections::qdrant::error::QdrantConnectionError;
 pub use crate::connections::qdrant::qdrant_connection::QdrantConnection;
 pub mod qdrant_util {
     pub use crate::connections::qdrant::qdrant_connection::{
-        create_filter, create_filter_with_data_type, match_any, parse_qdrant_value,
-        string_condition, string_filter, update_deleted_resources,
+        create_audit_filter, create_filter, create_filter_with_data_type, create_log_filter,
+        match_any, parse_qdrant_value, string_condition, string_filter, update_deleted_resources,
     };
 }
 
 // redis
-pub use crate::connections::redis::redis_connection::{RedisConnection, RedisConnectionError};
+pub use crate::connections::redis::async_redis_connection::AsyncRedisConnection;
+pub use crate::connections::redis::redis_connection::{
+    format_key, RedisConnection, RedisConnectionError,
+};
+pub use crate::connections::redis::state_cache::{StateCache, StateChange};
 
 // util
 pub use crate::connections::util::{get_env_var, get_env_var_as_vec};
@@ -1,3 +1,17 @@
+// Alignments above this many dynamic programming cells fall back to a positional comparison
+const MAX_ALIGNMENT_CELLS: usize = 1 << 20;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Alignment {
+    // (index in input1, index in input2)
+    Match(usize, usize),
+    Mismatch(usize, usize),
+    // token only present in input1
+    Insert(usize),
+    // token only present in input2
+    Delete(usize),
+}
+
 pub fn compare(input1: &[String], input2: &[String]) -> Vec<bool> {
     let mut result = Vec::new();
     let max_length = input1.len().max(input2.len());
@@ -8,6 +22,100 @@ pub fn compare(input1: &[String], input2: &[String]) -> Vec<bool> {
     result
 }
 
+// The `align` function computes a minimal edit script (match: 0, mismatch/insert/delete: 1) between
+// the two inputs. Mismatches are preferred over insertions and deletions of equal cost, so inputs
+// of the same length that only differ in single tokens align position by position.
+pub fn align(input1: &[String], input2: &[String]) -> Vec<Alignment> {
+    let (n, m) = (input1.len(), input2.len());
+    if (n + 1) * (m + 1) > MAX_ALIGNMENT_CELLS {
+        tracing::debug!("Alignment too large ({n}x{m}), comparing by position");
+        return align_positional(input1, input2);
+    }
+
+    // cost[i * width + j] is the edit distance between input1[i..] and input2[j..]
+    let width = m + 1;
+    let mut cost = vec![0u32; (n + 1) * width];
+    for i in (0..=n).rev() {
+        for j in (0..=m).rev() {
+            cost[i * width + j] = if i == n {
+                (m - j) as u32
+            } else if j == m {
+                (n - i) as u32
+            } else {
+                let diagonal = cost[(i + 1) * width + j + 1] + u32::from(input1[i] != input2[j]);
+                let insert = cost[(i + 1) * width + j] + 1;
+                let delete = cost[i * width + j + 1] + 1;
+                diagonal.min(insert).min(delete)
+            };
+        }
+    }
+
+    // Trace the cheapest path from the start of both inputs
+    let mut result = Vec::with_capacity(n.max(m));
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        let current = cost[i * width + j];
+        if i < n && j < m {
+            let equal = input1[i] == input2[j];
+            if current == cost[(i + 1) * width + j + 1] + u32::from(!equal) {
+                result.push(match equal {
+                    true => Alignment::Match(i, j),
+                    false => Alignment::Mismatch(i, j),
+                });
+                i += 1;
+                j += 1;
+                continue;
+            }
+        }
+        if i < n && current == cost[(i + 1) * width + j] + 1 {
+            result.push(Alignment::Insert(i));
+            i += 1;
+        } else {
+            result.push(Alignment::Delete(j));
+            j += 1;
+        }
+    }
+    result
+}
+
+fn align_positional(input1: &[String], input2: &[String]) -> Vec<Alignment> {
+    (0..input1.len().max(input2.len()))
+        .map(|i| match (input1.get(i), input2.get(i)) {
+            (Some(a), Some(b)) if a == b => Alignment::Match(i, i),
+            (Some(_), Some(_)) => Alignment::Mismatch(i, i),
+            (Some(_), None) => Alignment::Insert(i),
+            _ => Alignment::Delete(i),
+        })
+        .collect()
+}
+
+pub fn agreement(alignment: &[Alignment]) -> Vec<bool> {
+    alignment
+        .iter()
+        .map(|a| matches!(a, Alignment::Match(_, _)))
+        .collect()
+}
+
+pub fn similarity(alignment: &[Alignment]) -> f64 {
+    if alignment.is_empty() {
+        return 0.0;
+    }
+    let matches = alignment
+        .iter()
+        .filter(|a| matches!(a, Alignment::Match(_, _)))
+        .count();
+    matches as f64 / alignment.len() as f64
+}
+
+// Upper bound of the similarity of two inputs with the given lengths. At most the shorter input
+// can match and the alignment is at least as long as the longer input.
+pub fn similarity_bound(length1: usize, length2: usize) -> f64 {
+    match length1.max(length2) {
+        0 => 0.0,
+        max_length => length1.min(length2) as f64 / max_length as f64,
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -36,4 +144,65 @@ mod tests {
         let comparison = compare(&input1, &input2);
         assert_eq!(comparison, expected, "All items should match");
     }
+
+    #[rstest]
+    #[case((
+        "INFO This is a test log line 1",
+        "INFO This is a test log line 2"),
+        vec![true, true, true, true, true, true, true, false]
+    )]
+    #[case((
+        "failed to connect to primary db after 3 retries",
+        "failed to connect to db after 3 retries"),
+        vec![true, true, true, true, false, true, true, true, true]
+    )]
+    #[case((
+        "failed to connect to db after 3 retries",
+        "failed to connect to primary db after 4 retries"),
+        vec![true, true, true, true, false, true, true, false, true]
+    )]
+    #[case((
+        "removed nodes [ a b c ] from pool",
+        "removed nodes [ a ] from pool"),
+        vec![true, true, true, true, false, false, true, true, true]
+    )]
+    fn test_align_strings(#[case] inputs: (&str, &str), #[case] expected: Vec<bool>) {
+        setup_tracing(false);
+        let (input1, input2) = inputs;
+        let input1: Vec<String> = input1.split_whitespace().map(|s| s.to_string()).collect();
+        let input2: Vec<String> = input2.split_whitespace().map(|s| s.to_string()).collect();
+        let alignment = align(&input1, &input2);
+        assert_eq!(agreement(&alignment), expected);
+        let matches = expected.iter().filter(|&b| *b).count();
+        assert_eq!(
+            similarity(&alignment),
+            matches as f64 / expected.len() as f64
+        );
+        assert!(similarity(&alignment) <= similarity_bound(input1.len(), input2.len()));
+    }
+
+    #[test]
+    fn test_align_operations() {
+        setup_tracing(false);
+        let input1: Vec<String> = ["a", "x", "b", "c"].map(String::from).to_vec();
+        let input2: Vec<String> = ["a", "b", "d"].map(String::from).to_vec();
+        assert_eq!(
+            align(&input1, &input2),
+            vec![
+                Alignment::Match(0, 0),
+                Alignment::Insert(1),
+                Alignment::Match(2, 1),
+                Alignment::Mismatch(3, 2),
+            ]
+        );
+        assert_eq!(
+            align(&input2, &input1),
+            vec![
+                Alignment::Match(0, 0),
+                Alignment::Delete(1),
+                Alignment::Match(1, 2),
+                Alignment::Mismatch(2, 3),
+            ]
+        );
+    }
 }
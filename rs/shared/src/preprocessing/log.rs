@@ -1,6 +1,15 @@
 use serde_json::Value;
 
+// Lines with at least this many `key=value` pairs, making up at least half of the fields,
+// are tokenized as logfmt
+const LOGFMT_MIN_PAIRS: usize = 2;
+
 pub fn preprocess_message(message: &str, db: &str, key: &str, record_id: &str) -> Vec<String> {
+    // logfmt lines are recognized first, their quoted values may contain braces
+    if let Some(tokens) = logfmt_tokens(message) {
+        return tokens;
+    }
+
     let mut result = Vec::new();
 
     // Check if the input string contains a JSON object
@@ -41,6 +50,93 @@ pub fn preprocess_message(message: &str, db: &str, key: &str, record_id: &str) -
     result
 }
 
+// The `logfmt_tokens` function tokenizes `key=value key2="quoted value"` lines into keys and
+// values, like `flatten_json` does for JSON. Values that are JSON are flattened below their key.
+// Fields that are not pairs, e.g. the stream of the runtime, are split as plain text.
+// Returns `None` if the line is not logfmt.
+fn logfmt_tokens(message: &str) -> Option<Vec<String>> {
+    let fields = split_quoted(message);
+    let pairs: Vec<Option<(&str, &str)>> = fields.iter().map(|field| logfmt_pair(field)).collect();
+    let pair_count = pairs.iter().flatten().count();
+    if pair_count < LOGFMT_MIN_PAIRS || pair_count * 2 < fields.len() {
+        return None;
+    }
+
+    let mut result = Vec::new();
+    for (field, pair) in fields.iter().zip(pairs) {
+        match pair {
+            Some((key, value)) => {
+                result.push(key.to_string());
+                let value = unquote(value);
+                match parse_embedded_json(&value) {
+                    Some(json) => flatten_json_recursive(&json, &mut result, key.to_string()),
+                    None if value.is_empty() => {}
+                    None => result.push(value),
+                }
+            }
+            None => result.extend(split_string(field)),
+        }
+    }
+    Some(result)
+}
+
+// The `split_quoted` function splits on whitespace outside of double quotes.
+pub(crate) fn split_quoted(input: &str) -> Vec<&str> {
+    let mut fields = Vec::new();
+    let (mut start, mut quoted, mut escaped) = (None, false, false);
+    for (index, c) in input.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if let Some(start) = start.take() {
+                    fields.push(&input[start..index]);
+                }
+                continue;
+            }
+            _ => {}
+        }
+        start.get_or_insert(index);
+    }
+    if let Some(start) = start {
+        fields.push(&input[start..]);
+    }
+    fields
+}
+
+// e.g. `level=info` or `log.level="warn"`, keys start with a letter or an underscore
+pub(crate) fn logfmt_pair(field: &str) -> Option<(&str, &str)> {
+    let (key, value) = field.split_once('=')?;
+    let valid_key = key.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-' | '/'));
+    valid_key.then_some((key, value))
+}
+
+pub(crate) fn unquote(value: &str) -> String {
+    match value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+        true => serde_json::from_str::<String>(value)
+            .unwrap_or_else(|_| value[1..value.len() - 1].to_string()),
+        false => value.to_string(),
+    }
+}
+
+// The `parse_embedded_json` function parses strings that hold a JSON object or array, e.g. a
+// `msg` field with an escaped JSON payload.
+fn parse_embedded_json(value: &str) -> Option<Value> {
+    let trimmed = value.trim();
+    let delimited = (trimmed.starts_with('{') && trimmed.ends_with('}'))
+        || (trimmed.starts_with('[') && trimmed.ends_with(']'));
+    if !delimited {
+        return None;
+    }
+    serde_json::from_str::<Value>(trimmed)
+        .ok()
+        .filter(|json| json.is_object() || json.is_array())
+}
+
 fn split_string(input: &str) -> Vec<String> {
     input
         .replace("[", " [ ")
@@ -100,10 +196,14 @@ fn flatten_json_recursive(json: &Value, result: &mut Vec<String>, prefix: String
                 flatten_json_recursive(value, result, new_prefix);
             }
         }
-        Value::String(s) => {
-            let cleaned_string = s.replace("\\\"", "\"").replace("\\'", "'");
-            result.push(cleaned_string);
-        }
+        Value::String(s) => match parse_embedded_json(s) {
+            // decoded below the key of the string, e.g. `msg.user` for `{"msg": "{\"user\": 1}"}`
+            Some(embedded) => flatten_json_recursive(&embedded, result, prefix),
+            None => {
+                let cleaned_string = s.replace("\\\"", "\"").replace("\\'", "'");
+                result.push(cleaned_string);
+            }
+        },
         _ => {
             result.push(json.to_string());
         }
@@ -136,6 +236,22 @@ mod tests {
     )]
     #[case("stderr F I0315 09:37:55.934101       1 main.go:250] Node kind-worker2 has CIDR [10.244.2.0/24]", vec!["stderr", "F", "I0315", "09:37:55.934101", "1", "main.go:250", "]", "Node", "kind-worker2", "has", "CIDR", "[", "10.244.2.0/24", "]"])]
     #[case("stderr F I0315 10:44:54.473228       1 main.go:227] handling current node", vec!["stderr", "F", "I0315", "10:44:54.473228", "1", "main.go:227", "]", "handling", "current", "node"])]
+    #[case(
+        r#"time="2024-03-16T05:28:18Z" level=debug msg="reconciling object" name=cert-manager"#,
+        vec!["time", "2024-03-16T05:28:18Z", "level", "debug", "msg", "reconciling object", "name", "cert-manager"]
+    )]
+    #[case(
+        r#"stderr F level=info msg="payload received" body="{\"user\":\"alice\",\"roles\":[\"admin\"]}""#,
+        vec!["stderr", "F", "level", "info", "msg", "payload received", "body", "body.user", "alice", "body.roles", "admin"]
+    )]
+    #[case(
+        r#"stderr F {"level":"info","msg":"{\"event\":\"login\",\"ok\":true}"}"#,
+        vec!["stderr", "F", "level", "info", "msg", "msg.event", "login", "msg.ok", "true"]
+    )]
+    #[case(
+        "retrying request with timeout=30s",
+        vec!["retrying", "request", "with", "timeout=30s"]
+    )]
     fn test_preprocess_log(#[case] input: &str, #[case] expected: Vec<&str>) {
         setup_tracing(false);
         assert_eq!(
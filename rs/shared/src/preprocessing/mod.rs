@@ -1,2 +1,6 @@
 pub mod compare;
 pub mod log;
+pub mod pipeline;
+pub mod redaction;
+pub mod severity;
+pub mod variable;
@@ -0,0 +1,399 @@
+use std::borrow::Cow;
+use std::env::var;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use glob::Pattern;
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::Value;
+use thiserror::Error;
+use tracing::{info, warn};
+
+use crate::{
+    connections::dbname::DbName,
+    preprocessing::log::{logfmt_pair, preprocess_message, split_quoted, unquote},
+    types::record::log::LogRecord,
+};
+
+// how often the modification time of the configuration file is checked
+const RELOAD_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Error, Debug)]
+pub enum PipelineConfigError {
+    #[error("Failed to read pipeline configuration {0}: {1}")]
+    ReadError(String, #[source] std::io::Error),
+    #[error("Failed to parse pipeline configuration: {0}")]
+    JsonParseError(#[from] serde_json::Error),
+    #[error("Invalid pattern {0}: {1}")]
+    InvalidPattern(String, #[source] regex::Error),
+    #[error("Invalid glob {0}: {1}")]
+    InvalidGlob(String, #[source] glob::PatternError),
+}
+
+// A stage of a preprocessing pipeline. Stages rewrite the message of a log in order before it is
+// tokenized by `preprocess_message`.
+pub trait Preprocessor: Send + Sync + fmt::Debug {
+    fn apply(&self, message: String) -> String;
+}
+
+// Removes a prefix matching the pattern, e.g. a tag a sidecar puts in front of every line.
+#[derive(Debug)]
+pub struct StripPrefix {
+    pattern: Regex,
+}
+
+impl Preprocessor for StripPrefix {
+    fn apply(&self, message: String) -> String {
+        match self.pattern.find(&message) {
+            Some(prefix) if prefix.start() == 0 => message[prefix.end()..].to_string(),
+            _ => message,
+        }
+    }
+}
+
+// Replaces a JSON or logfmt line with the value of one of its fields, e.g. `msg`. Nested JSON
+// fields are separated by dots, e.g. `log.message`.
+#[derive(Debug)]
+pub struct MessageField {
+    field: String,
+}
+
+impl Preprocessor for MessageField {
+    fn apply(&self, message: String) -> String {
+        let json_value = embedded_json(&message).and_then(|(_, _, json)| {
+            let value = self
+                .field
+                .split('.')
+                .try_fold(&json, |value, key| value.get(key))?;
+            match value {
+                Value::String(value) => Some(value.clone()),
+                Value::Null => None,
+                value => Some(value.to_string()),
+            }
+        });
+        let logfmt_value = || {
+            split_quoted(&message)
+                .into_iter()
+                .filter_map(logfmt_pair)
+                .find(|(key, _)| *key == self.field)
+                .map(|(_, value)| unquote(value))
+        };
+        json_value.or_else(logfmt_value).unwrap_or(message)
+    }
+}
+
+// Removes top-level fields of a JSON or logfmt line, e.g. a request id that is different in every
+// line.
+#[derive(Debug)]
+pub struct DropKeys {
+    keys: Vec<String>,
+}
+
+impl Preprocessor for DropKeys {
+    fn apply(&self, message: String) -> String {
+        if let Some((start, end, Value::Object(mut map))) = embedded_json(&message) {
+            map.retain(|key, _| !self.keys.contains(key));
+            return format!(
+                "{}{}{}",
+                &message[..start],
+                Value::Object(map),
+                &message[end + 1..]
+            );
+        }
+        let fields = split_quoted(&message);
+        let kept: Vec<&str> = fields
+            .iter()
+            .copied()
+            .filter(|field| {
+                logfmt_pair(field).is_none_or(|(key, _)| !self.keys.iter().any(|k| k == key))
+            })
+            .collect();
+        match kept.len() == fields.len() {
+            true => message,
+            false => kept.join(" "),
+        }
+    }
+}
+
+// the JSON object of a message and its bounds, found like in `preprocess_message`
+fn embedded_json(message: &str) -> Option<(usize, usize, Value)> {
+    let (start, end) = (message.find('{')?, message.rfind('}')?);
+    if start >= end {
+        return None;
+    }
+    let json = serde_json::from_str::<Value>(&message[start..=end]).ok()?;
+    Some((start, end, json))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "stage", rename_all = "snake_case")]
+enum StageConfig {
+    StripPrefix { pattern: String },
+    MessageField { field: String },
+    DropKeys { keys: Vec<String> },
+}
+
+impl StageConfig {
+    fn build(self) -> Result<Box<dyn Preprocessor>, PipelineConfigError> {
+        Ok(match self {
+            StageConfig::StripPrefix { pattern } => Box::new(StripPrefix {
+                pattern: Regex::new(&pattern)
+                    .map_err(|e| PipelineConfigError::InvalidPattern(pattern.clone(), e))?,
+            }),
+            StageConfig::MessageField { field } => Box::new(MessageField { field }),
+            StageConfig::DropKeys { keys } => Box::new(DropKeys { keys }),
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct PipelineConfig {
+    customer: String,
+    #[serde(default)]
+    namespace: Option<String>,
+    #[serde(default)]
+    container: Option<String>,
+    stages: Vec<StageConfig>,
+}
+
+#[derive(Debug)]
+struct Pipeline {
+    customer: String,
+    namespace: Option<Pattern>,
+    container: Option<Pattern>,
+    stages: Vec<Box<dyn Preprocessor>>,
+}
+
+impl Pipeline {
+    fn matches(&self, customer_id: &str, namespace: &str, container: &str) -> bool {
+        let glob_matches =
+            |pattern: &Option<Pattern>, value| pattern.as_ref().is_none_or(|p| p.matches(value));
+        self.customer == customer_id
+            && glob_matches(&self.namespace, namespace)
+            && glob_matches(&self.container, container)
+    }
+}
+
+fn glob(pattern: Option<String>) -> Result<Option<Pattern>, PipelineConfigError> {
+    pattern
+        .map(|pattern| {
+            Pattern::new(&pattern).map_err(|e| PipelineConfigError::InvalidGlob(pattern.clone(), e))
+        })
+        .transpose()
+}
+
+fn parse_pipelines(config: &str) -> Result<Vec<Pipeline>, PipelineConfigError> {
+    serde_json::from_str::<Vec<PipelineConfig>>(config)?
+        .into_iter()
+        .map(|config| {
+            Ok(Pipeline {
+                customer: config.customer,
+                namespace: glob(config.namespace)?,
+                container: glob(config.container)?,
+                stages: config
+                    .stages
+                    .into_iter()
+                    .map(StageConfig::build)
+                    .collect::<Result<_, _>>()?,
+            })
+        })
+        .collect()
+}
+
+// The preprocessing pipelines of all customers, read from the JSON file at
+// `PREPROCESSING_PIPELINES`, e.g.
+// `[{"customer": "acme", "namespace": "shop-*", "container": "api", "stages": [
+//     {"stage": "strip_prefix", "pattern": "^\\[api\\] "},
+//     {"stage": "message_field", "field": "msg"},
+//     {"stage": "drop_keys", "keys": ["request_id"]}]}]`
+// Namespace and container are globs, the first matching pipeline is used. Logs without a pipeline
+// are only tokenized. The file is read again when it changed, an invalid file keeps the previous
+// pipelines.
+#[derive(Debug)]
+pub struct Pipelines {
+    path: Option<PathBuf>,
+    modified: Option<SystemTime>,
+    reload_interval: Duration,
+    last_check: Instant,
+    pipelines: Vec<Pipeline>,
+}
+
+impl Pipelines {
+    pub fn empty() -> Self {
+        Pipelines {
+            path: None,
+            modified: None,
+            reload_interval: RELOAD_INTERVAL,
+            last_check: Instant::now(),
+            pipelines: Vec::new(),
+        }
+    }
+
+    pub fn from_env() -> Result<Self, PipelineConfigError> {
+        match var("PREPROCESSING_PIPELINES") {
+            Ok(path) => Self::load(PathBuf::from(path)),
+            Err(_) => Ok(Self::empty()),
+        }
+    }
+
+    pub fn load(path: PathBuf) -> Result<Self, PipelineConfigError> {
+        let mut pipelines = Self::empty();
+        pipelines.modified = modified(&path);
+        pipelines.pipelines = read_pipelines(&path)?;
+        pipelines.path = Some(path);
+        Ok(pipelines)
+    }
+
+    // The `reload_if_changed` function reads the configuration file again if it was modified
+    // since it was last read. It checks at most every `RELOAD_INTERVAL`.
+    pub fn reload_if_changed(&mut self) {
+        if self.last_check.elapsed() < self.reload_interval {
+            return;
+        }
+        self.last_check = Instant::now();
+        let Some(path) = &self.path else {
+            return;
+        };
+        let modified = modified(path);
+        if modified == self.modified {
+            return;
+        }
+        self.modified = modified;
+        match read_pipelines(path) {
+            Ok(pipelines) => {
+                info!(
+                    "Reloaded {} preprocessing pipelines from {}",
+                    pipelines.len(),
+                    path.display()
+                );
+                self.pipelines = pipelines;
+            }
+            Err(e) => warn!("Keeping the previous preprocessing pipelines: {}", e),
+        }
+    }
+
+    // The `apply` function runs the stages of the pipeline of the container on the message.
+    pub fn apply<'a>(
+        &self,
+        customer_id: &str,
+        namespace: &str,
+        container: &str,
+        message: &'a str,
+    ) -> Cow<'a, str> {
+        let pipeline = self
+            .pipelines
+            .iter()
+            .find(|pipeline| pipeline.matches(customer_id, namespace, container));
+        match pipeline {
+            Some(pipeline) => Cow::Owned(
+                pipeline
+                    .stages
+                    .iter()
+                    .fold(message.to_string(), |message, stage| stage.apply(message)),
+            ),
+            None => Cow::Borrowed(message),
+        }
+    }
+
+    // The `preprocess` function applies the pipeline of the log and tokenizes its message.
+    pub fn preprocess(&self, customer_id: &str, log: &LogRecord) -> Vec<String> {
+        let db = DbName::Log.id(customer_id);
+        let message = self.apply(customer_id, &log.namespace, &log.container, &log.message);
+        preprocess_message(&message, &db, &log.key, &log.record_id)
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    path.metadata()
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+fn read_pipelines(path: &Path) -> Result<Vec<Pipeline>, PipelineConfigError> {
+    let config = std::fs::read_to_string(path)
+        .map_err(|e| PipelineConfigError::ReadError(path.display().to_string(), e))?;
+    parse_pipelines(&config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use uuid7::uuid7;
+
+    const CONFIG: &str = r#"[
+        {"customer": "acme", "namespace": "shop-*", "container": "api", "stages": [
+            {"stage": "strip_prefix", "pattern": "^\\[api\\] "},
+            {"stage": "drop_keys", "keys": ["request_id"]},
+            {"stage": "message_field", "field": "log.msg"}
+        ]},
+        {"customer": "acme", "stages": [{"stage": "drop_keys", "keys": ["trace_id"]}]}
+    ]"#;
+
+    #[rstest]
+    #[case(
+        "shop-eu",
+        "api",
+        "[api] {\"request_id\": \"a1\", \"log\": {\"msg\": \"order created\"}}",
+        "order created"
+    )]
+    #[case(
+        "shop-eu",
+        "worker",
+        "level=info trace_id=7f3a msg=\"job done\"",
+        "level=info msg=\"job done\""
+    )]
+    #[case(
+        "billing",
+        "api",
+        "[api] {\"trace_id\":\"7f3a\",\"msg\":\"paid\"}",
+        "[api] {\"msg\":\"paid\"}"
+    )]
+    fn test_pipeline_stages(
+        #[case] namespace: &str,
+        #[case] container: &str,
+        #[case] message: &str,
+        #[case] expected: &str,
+    ) {
+        let pipelines = Pipelines {
+            pipelines: parse_pipelines(CONFIG).unwrap(),
+            ..Pipelines::empty()
+        };
+        assert_eq!(
+            pipelines.apply("acme", namespace, container, message),
+            expected
+        );
+        // other customers have no pipeline
+        assert_eq!(
+            pipelines.apply("other", namespace, container, message),
+            message
+        );
+    }
+
+    #[test]
+    fn test_pipelines_reload() {
+        let path = std::env::temp_dir().join(format!("pipelines-{}.json", uuid7()));
+        std::fs::write(&path, "[]").unwrap();
+        let mut pipelines = Pipelines::load(path.clone()).unwrap();
+        pipelines.reload_interval = Duration::ZERO;
+        let message = "{\"trace_id\":\"7f3a\",\"msg\":\"paid\"}";
+        assert_eq!(pipelines.apply("acme", "shop", "api", message), message);
+
+        // an invalid file keeps the previous pipelines
+        std::fs::write(&path, "[{\"customer\": \"acme\"}]").unwrap();
+        pipelines.modified = None;
+        pipelines.reload_if_changed();
+        assert!(pipelines.pipelines.is_empty());
+
+        std::fs::write(&path, CONFIG).unwrap();
+        pipelines.modified = None;
+        pipelines.reload_if_changed();
+        assert_eq!(
+            pipelines.apply("acme", "shop", "api", message),
+            "{\"msg\":\"paid\"}"
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,206 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use strum::{Display, EnumIter, EnumString, IntoEnumIterator};
+
+const LEVEL_KEYS: [&str; 5] = ["level", "lvl", "severity", "levelname", "log.level"];
+// the level is expected within the first tokens, e.g. after the stream of the runtime and a
+// timestamp: `stderr F 2024-03-16 05:28:18.351 ERROR [main] ...`
+const MAX_PREFIX_TOKENS: usize = 6;
+
+// Severities are ordered, `Unknown` is the lowest so that any extracted level wins in `max`.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+    Display,
+    EnumString,
+    EnumIter,
+)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum Severity {
+    #[default]
+    Unknown,
+    Trace,
+    Debug,
+    Info,
+    Warn,
+    Error,
+    Fatal,
+}
+
+impl Severity {
+    // The `from_level` function normalizes the common spellings of a level, e.g. `WARNING`,
+    // `err` or `CRITICAL`.
+    pub fn from_level(level: &str) -> Option<Severity> {
+        match level.to_ascii_lowercase().as_str() {
+            "trace" | "trc" => Some(Severity::Trace),
+            "debug" | "dbg" => Some(Severity::Debug),
+            "info" | "inf" | "information" | "notice" => Some(Severity::Info),
+            "warn" | "wrn" | "warning" => Some(Severity::Warn),
+            "error" | "err" | "eror" => Some(Severity::Error),
+            "fatal" | "ftl" | "critical" | "crit" | "panic" | "alert" | "emerg" | "emergency" => {
+                Some(Severity::Fatal)
+            }
+            _ => None,
+        }
+    }
+
+    // numeric levels of pino and bunyan
+    fn from_number(level: i64) -> Option<Severity> {
+        match level {
+            10 => Some(Severity::Trace),
+            20 => Some(Severity::Debug),
+            30 => Some(Severity::Info),
+            40 => Some(Severity::Warn),
+            50 => Some(Severity::Error),
+            60 => Some(Severity::Fatal),
+            _ => None,
+        }
+    }
+
+    // The `at_least` function returns this severity and all higher ones.
+    pub fn at_least(self) -> Vec<Severity> {
+        Severity::iter()
+            .filter(|severity| *severity >= self)
+            .collect()
+    }
+}
+
+// The `extract_severity` function reads the level of a log message. Structured levels, a JSON
+// `level` field or a logfmt `level=` pair, take precedence over klog prefixes (`E0315`) and level
+// keywords at the start of the message.
+pub fn extract_severity(message: &str) -> Severity {
+    json_level(message)
+        .or_else(|| logfmt_level(message))
+        .or_else(|| prefix_level(message))
+        .unwrap_or_default()
+}
+
+fn json_level(message: &str) -> Option<Severity> {
+    let (start, end) = (message.find('{')?, message.rfind('}')?);
+    if start >= end {
+        return None;
+    }
+    let json = serde_json::from_str::<Value>(&message[start..=end]).ok()?;
+    LEVEL_KEYS
+        .iter()
+        .filter_map(|key| json.get(key))
+        .find_map(|level| match level {
+            Value::String(level) => Severity::from_level(level),
+            Value::Number(level) => level.as_i64().and_then(Severity::from_number),
+            _ => None,
+        })
+}
+
+fn logfmt_level(message: &str) -> Option<Severity> {
+    message.split_whitespace().find_map(|pair| {
+        let (key, value) = pair.split_once('=')?;
+        if LEVEL_KEYS.contains(&key) {
+            Severity::from_level(value.trim_matches('"'))
+        } else {
+            None
+        }
+    })
+}
+
+fn prefix_level(message: &str) -> Option<Severity> {
+    message
+        .split_whitespace()
+        .take(MAX_PREFIX_TOKENS)
+        .find_map(|token| klog_level(token).or_else(|| keyword_level(token)))
+}
+
+// e.g. `I0315`, the level followed by month and day
+fn klog_level(token: &str) -> Option<Severity> {
+    let mut chars = token.chars();
+    let level = match chars.next()? {
+        'I' => Severity::Info,
+        'W' => Severity::Warn,
+        'E' => Severity::Error,
+        'F' => Severity::Fatal,
+        _ => return None,
+    };
+    let digits = chars.as_str();
+    (digits.len() == 4 && digits.chars().all(|c| c.is_ascii_digit())).then_some(level)
+}
+
+// e.g. `ERROR`, `[warn]`, `error:` or `ERROR:root:message` of the python logging module.
+// Lowercase words are only taken as a level when marked as such, `failed with error` is not.
+fn keyword_level(token: &str) -> Option<Severity> {
+    let bracketed = token.starts_with(['[', '(', '<']);
+    let token = token.trim_matches(|c| matches!(c, '[' | ']' | '(' | ')' | '<' | '>'));
+    let (keyword, separated) = match token.split_once(':') {
+        Some((keyword, _)) => (keyword, true),
+        None => (token, false),
+    };
+    let capitalized = keyword.starts_with(|c: char| c.is_ascii_uppercase());
+    if bracketed || separated || capitalized {
+        Severity::from_level(keyword)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("stderr F I0315 09:37:55.934101       1 main.go:250] Node kind-worker2 has CIDR [10.244.2.0/24]", Severity::Info)]
+    #[case(
+        "stderr F E0315 10:44:54.473228       1 reflector.go:147] Failed to watch",
+        Severity::Error
+    )]
+    #[case("stderr F {\"level\":\"warn\",\"ts\":\"2024-03-16T05:28:18.752849Z\",\"msg\":\"slow fdatasync\"}", Severity::Warn)]
+    #[case(
+        "{\"level\":50,\"time\":1710566898752,\"msg\":\"request failed\"}",
+        Severity::Error
+    )]
+    #[case(
+        "{\"severity\":\"CRITICAL\",\"message\":\"disk full\"}",
+        Severity::Fatal
+    )]
+    #[case(
+        "time=\"2024-03-16T05:28:18Z\" level=debug msg=\"reconciling\"",
+        Severity::Debug
+    )]
+    #[case(
+        "2024-03-16 05:28:18.351 ERROR [main] c.e.OrderService - Failed to process order",
+        Severity::Error
+    )]
+    #[case("[WARN] connection pool exhausted", Severity::Warn)]
+    #[case("ERROR:worker:Job 17 failed", Severity::Error)]
+    #[case(
+        "panic: runtime error: index out of range [5] with length 3",
+        Severity::Fatal
+    )]
+    #[case(
+        "Trace[535324451]: [878.754588ms] [878.754588ms] END",
+        Severity::Unknown
+    )]
+    #[case("GET /health 200", Severity::Unknown)]
+    #[case("this request ended without an error code", Severity::Unknown)]
+    fn test_extract_severity(#[case] message: &str, #[case] expected: Severity) {
+        assert_eq!(extract_severity(message), expected);
+    }
+
+    #[test]
+    fn test_severity_at_least() {
+        assert_eq!(
+            Severity::Error.at_least(),
+            [Severity::Error, Severity::Fatal]
+        );
+        assert_eq!(Severity::Warn.to_string(), "warn");
+        assert_eq!("fatal".parse::<Severity>(), Ok(Severity::Fatal));
+    }
+}
@@ -0,0 +1,173 @@
+use std::fmt::{self, Display, Formatter};
+use std::net::{IpAddr, SocketAddr};
+
+use chrono::{DateTime, NaiveDate, NaiveTime};
+use serde::{Deserialize, Serialize};
+
+use crate::constant::KUBERNETES_NAME_SUFFIX_ALPHABET;
+
+const DURATION_UNITS: [&str; 8] = ["ns", "us", "µs", "ms", "s", "m", "h", "d"];
+const HEX_MIN_LENGTH: usize = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum VarKind {
+    Any,
+    Ip,
+    IpPort,
+    Uuid,
+    PodName,
+    Duration,
+    Hex,
+    Timestamp,
+}
+
+impl Display for VarKind {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let placeholder = match self {
+            VarKind::Any => "<var>",
+            VarKind::Ip => "<ip>",
+            VarKind::IpPort => "<ip>:<port>",
+            VarKind::Uuid => "<uuid>",
+            VarKind::PodName => "<pod>",
+            VarKind::Duration => "<duration>",
+            VarKind::Hex => "<hex>",
+            VarKind::Timestamp => "<timestamp>",
+        };
+        write!(f, "{}", placeholder)
+    }
+}
+
+// The `detect` function returns the kind of variable a token represents, if it is recognized.
+// Plain numbers are not typed, they are often part of the constant text of a log line.
+pub fn detect(token: &str) -> Option<VarKind> {
+    if token.parse::<SocketAddr>().is_ok() {
+        Some(VarKind::IpPort)
+    } else if is_ip(token) {
+        Some(VarKind::Ip)
+    } else if is_uuid(token) {
+        Some(VarKind::Uuid)
+    } else if is_timestamp(token) {
+        Some(VarKind::Timestamp)
+    } else if is_duration(token) {
+        Some(VarKind::Duration)
+    } else if is_hex(token) {
+        Some(VarKind::Hex)
+    } else if is_pod_name(token) {
+        Some(VarKind::PodName)
+    } else {
+        None
+    }
+}
+
+// The `mask_tokens` function replaces recognized variables with their placeholder, so that
+// tokens of the same kind compare equal.
+pub fn mask_tokens(tokens: &[String]) -> Vec<String> {
+    tokens
+        .iter()
+        .map(|token| match detect(token) {
+            Some(kind) => kind.to_string(),
+            None => token.clone(),
+        })
+        .collect()
+}
+
+fn is_ip(token: &str) -> bool {
+    // ipv6 requires at least two colons, avoiding matches on `key:value` tokens
+    token.parse::<IpAddr>().is_ok() && (token.contains('.') || token.matches(':').count() >= 2)
+}
+
+fn is_uuid(token: &str) -> bool {
+    token.len() == 36
+        && token.char_indices().all(|(i, c)| match i {
+            8 | 13 | 18 | 23 => c == '-',
+            _ => c.is_ascii_hexdigit(),
+        })
+}
+
+fn is_timestamp(token: &str) -> bool {
+    DateTime::parse_from_rfc3339(token).is_ok()
+        || NaiveDate::parse_from_str(token, "%Y-%m-%d").is_ok()
+        || (token.contains(':') && NaiveTime::parse_from_str(token, "%H:%M:%S%.f").is_ok())
+}
+
+// e.g. `878.754588ms`, `1h59m31.217943757s`
+fn is_duration(token: &str) -> bool {
+    let mut rest = token;
+    let mut parts = 0;
+    while !rest.is_empty() {
+        let number_length = rest
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(rest.len());
+        if number_length == 0 || !rest[..number_length].starts_with(|c: char| c.is_ascii_digit()) {
+            return false;
+        }
+        rest = &rest[number_length..];
+        // longest unit first, so `ms` is not read as `m`
+        match DURATION_UNITS
+            .iter()
+            .filter(|unit| rest.starts_with(*unit))
+            .max_by_key(|unit| unit.len())
+        {
+            Some(unit) => rest = &rest[unit.len()..],
+            None => return false,
+        }
+        parts += 1;
+    }
+    parts > 0
+}
+
+fn is_hex(token: &str) -> bool {
+    if let Some(digits) = token.strip_prefix("0x") {
+        return !digits.is_empty() && digits.chars().all(|c| c.is_ascii_hexdigit());
+    }
+    token.len() >= HEX_MIN_LENGTH
+        && token.chars().all(|c| c.is_ascii_hexdigit())
+        && token.chars().any(|c| c.is_ascii_digit())
+        && token.chars().any(|c| c.is_ascii_alphabetic())
+}
+
+// e.g. `coredns-7db6d8ff4d-x7k2p` (deployment) or `kindnet-8ctwq` (daemonset)
+fn is_pod_name(token: &str) -> bool {
+    match token.rsplit_once('-') {
+        Some((name, suffix)) => {
+            !name.is_empty()
+                && !name.split('-').any(str::is_empty)
+                && suffix.len() == 5
+                && suffix
+                    .chars()
+                    .all(|c| KUBERNETES_NAME_SUFFIX_ALPHABET.contains(c))
+                && suffix.chars().any(|c| c.is_ascii_digit())
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("10.244.1.86", Some(VarKind::Ip))]
+    #[case("fe80::1ff:fe23:4567:890a", Some(VarKind::Ip))]
+    #[case("10.96.0.1:443", Some(VarKind::IpPort))]
+    #[case("a8ccd550-7e16-40d2-9679-f9d80a6c57bd", Some(VarKind::Uuid))]
+    #[case("coredns-7db6d8ff4d-x7k2p", Some(VarKind::PodName))]
+    #[case("kindnet-8ctwq", Some(VarKind::PodName))]
+    #[case("878.754588ms", Some(VarKind::Duration))]
+    #[case("1h59m31.217943757s", Some(VarKind::Duration))]
+    #[case("0x1f", Some(VarKind::Hex))]
+    #[case("3f9a0c2be1", Some(VarKind::Hex))]
+    #[case("2024-03-16T05:28:18.752849Z", Some(VarKind::Timestamp))]
+    #[case("09:37:55.934101", Some(VarKind::Timestamp))]
+    #[case("2024-03-16", Some(VarKind::Timestamp))]
+    #[case("108791", None)]
+    #[case("kind-worker2", None)]
+    #[case("kube-proxy", None)]
+    #[case("main.go:250", None)]
+    #[case("deadbeef", None)]
+    #[case("ms", None)]
+    fn test_detect(#[case] token: &str, #[case] expected: Option<VarKind>) {
+        assert_eq!(detect(token), expected);
+    }
+}
@@ -13,6 +13,13 @@ impl AuthenticatedUser {
     pub fn new(customer_id: String) -> Self {
         AuthenticatedUser { customer_id }
     }
+
+    // The `cached` function returns the user authenticated by the guard of the request, so guards
+    // declared after the `AuthenticatedUser` parameter don't validate the token again. It must
+    // not be called before the guard ran, that would cache no user for the request.
+    pub fn cached<'r>(request: &'r Request<'_>) -> Option<&'r AuthenticatedUser> {
+        request.local_cache(|| None::<AuthenticatedUser>).as_ref()
+    }
 }
 
 #[rocket::async_trait]
@@ -27,7 +34,10 @@ impl<'r> FromRequest<'r> for AuthenticatedUser {
 
         let token = keys[0].trim_start_matches("Bearer ");
         match validate_token(token).await {
-            Ok(customer_id) => Outcome::Success(AuthenticatedUser::new(customer_id)),
+            Ok(customer_id) => {
+                request.local_cache(|| Some(AuthenticatedUser::new(customer_id.clone())));
+                Outcome::Success(AuthenticatedUser::new(customer_id))
+            }
             Err(e) => {
                 tracing::error!("Error validating token: {:?}", e);
                 Outcome::Error((Status::Unauthorized, e))
@@ -0,0 +1,225 @@
+use fluvio::dataplane::record::ConsumerRecord;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::convert::TryFrom;
+
+use crate::types::record::line::parse_timestamp;
+
+const READ_VERBS: [&str; 3] = ["get", "list", "watch"];
+// the stages after which the response of a request is known
+const FINAL_STAGES: [&str; 2] = ["ResponseComplete", "Panic"];
+
+// The body the audit webhook backend of the API server posts, an `EventList` of `audit.k8s.io/v1`.
+#[derive(Debug, Deserialize)]
+pub struct AuditEventList {
+    #[serde(default)]
+    pub items: Vec<Value>,
+}
+
+// An event of the API server audit log. The request and response objects are not kept, they are
+// large and may contain secrets the audit policy did not omit.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AuditEvent {
+    #[serde(rename = "auditID")]
+    pub audit_id: String,
+    pub stage: String,
+    pub verb: String,
+    #[serde(rename = "requestURI")]
+    pub request_uri: String,
+    pub user: UserInfo,
+    pub impersonated_user: Option<UserInfo>,
+    #[serde(rename = "sourceIPs")]
+    pub source_ips: Vec<String>,
+    pub user_agent: String,
+    pub object_ref: Option<ObjectReference>,
+    pub response_status: Option<ResponseStatus>,
+    pub request_received_timestamp: String,
+    pub stage_timestamp: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UserInfo {
+    pub username: String,
+    pub groups: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ObjectReference {
+    pub resource: String,
+    pub subresource: String,
+    pub namespace: String,
+    pub name: String,
+    pub uid: String,
+    pub api_group: String,
+    pub api_version: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ResponseStatus {
+    pub code: i64,
+    pub status: String,
+    pub reason: String,
+    pub message: String,
+}
+
+impl AuditEvent {
+    // The time of the stage in milliseconds, 0 if neither timestamp can be parsed.
+    pub fn timestamp(&self) -> i64 {
+        parse_timestamp(&self.stage_timestamp)
+            .or_else(|| parse_timestamp(&self.request_received_timestamp))
+            .unwrap_or_default()
+    }
+
+    pub fn is_read(&self) -> bool {
+        READ_VERBS.contains(&self.verb.as_str())
+    }
+
+    // The `is_completed_write` function is true for requests that may have changed the cluster
+    // once their response is known. Only these are vectorized, reads make up most of the log.
+    pub fn is_completed_write(&self) -> bool {
+        !self.is_read() && FINAL_STAGES.contains(&self.stage.as_str())
+    }
+
+    pub fn response_code(&self) -> i64 {
+        self.response_status
+            .as_ref()
+            .map(|status| status.code)
+            .unwrap_or_default()
+    }
+
+    // The `summary` function describes the request in one line, e.g.
+    // `2024-03-16T05:28:18.351Z jane@example.com delete deployments shop/cart: 200`
+    pub fn summary(&self) -> String {
+        let mut summary = format!("{} {}", self.stage_timestamp, self.user.username);
+        if let Some(impersonated) = &self.impersonated_user {
+            summary.push_str(&format!(" as {}", impersonated.username));
+        }
+        summary.push_str(&format!(" {}", self.verb));
+        match &self.object_ref {
+            Some(object) => {
+                summary.push_str(&format!(" {}", object.resource));
+                if !object.subresource.is_empty() {
+                    summary.push_str(&format!("/{}", object.subresource));
+                }
+                match (object.namespace.is_empty(), object.name.is_empty()) {
+                    (false, false) => {
+                        summary.push_str(&format!(" {}/{}", object.namespace, object.name))
+                    }
+                    (false, true) => summary.push_str(&format!(" in {}", object.namespace)),
+                    (true, false) => summary.push_str(&format!(" {}", object.name)),
+                    (true, true) => {}
+                }
+            }
+            None => summary.push_str(&format!(" {}", self.request_uri)),
+        }
+        if let Some(status) = &self.response_status {
+            summary.push_str(&format!(": {}", status.code));
+            if !status.reason.is_empty() {
+                summary.push_str(&format!(" {}", status.reason));
+            }
+            if !status.message.is_empty() {
+                summary.push_str(&format!(" {}", status.message));
+            }
+        }
+        if !self.user_agent.is_empty() {
+            summary.push_str(&format!(" (user agent: {})", self.user_agent));
+        }
+        summary
+    }
+}
+
+impl TryFrom<ConsumerRecord> for AuditEvent {
+    type Error = serde_json::Error;
+
+    fn try_from(record: ConsumerRecord) -> Result<Self, Self::Error> {
+        serde_json::from_slice::<AuditEvent>(record.value())
+    }
+}
+
+impl TryFrom<&AuditEvent> for Vec<u8> {
+    type Error = serde_json::Error;
+
+    fn try_from(event: &AuditEvent) -> Result<Self, Self::Error> {
+        serde_json::to_vec(event)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+    use serde_json::json;
+
+    fn audit_event(verb: &str, stage: &str) -> AuditEvent {
+        serde_json::from_value(json!({
+            "kind": "Event",
+            "apiVersion": "audit.k8s.io/v1",
+            "level": "RequestResponse",
+            "auditID": "5b1fa1c4-0a2f-4c7e-9f51-2b8c8f3d6a10",
+            "stage": stage,
+            "requestURI": "/apis/apps/v1/namespaces/shop/deployments/cart",
+            "verb": verb,
+            "user": {"username": "jane@example.com", "groups": ["system:authenticated"]},
+            "sourceIPs": ["10.0.0.12"],
+            "userAgent": "kubectl/v1.31.0",
+            "objectRef": {
+                "resource": "deployments",
+                "namespace": "shop",
+                "name": "cart",
+                "apiGroup": "apps",
+                "apiVersion": "v1"
+            },
+            "responseStatus": {"metadata": {}, "status": "Success", "code": 200},
+            "requestObject": {"kind": "DeleteOptions", "propagationPolicy": "Background"},
+            "requestReceivedTimestamp": "2024-03-16T05:28:18.100412Z",
+            "stageTimestamp": "2024-03-16T05:28:18.351044Z"
+        }))
+        .unwrap()
+    }
+
+    #[rstest]
+    #[case("delete", "ResponseComplete", true)]
+    #[case("patch", "Panic", true)]
+    #[case("delete", "RequestReceived", false)]
+    #[case("get", "ResponseComplete", false)]
+    #[case("watch", "ResponseStarted", false)]
+    fn test_is_completed_write(#[case] verb: &str, #[case] stage: &str, #[case] expected: bool) {
+        assert_eq!(audit_event(verb, stage).is_completed_write(), expected);
+    }
+
+    #[rstest]
+    fn test_audit_event() {
+        let event = audit_event("delete", "ResponseComplete");
+        assert_eq!(event.timestamp(), 1710566898351);
+        assert_eq!(event.response_code(), 200);
+        assert_eq!(event.object_ref.as_ref().unwrap().api_group, "apps");
+        assert_eq!(
+            event.summary(),
+            "2024-03-16T05:28:18.351044Z jane@example.com delete deployments shop/cart: 200 (user agent: kubectl/v1.31.0)"
+        );
+
+        let event = AuditEvent {
+            impersonated_user: Some(UserInfo {
+                username: "system:serviceaccount:shop:deployer".to_owned(),
+                groups: vec![],
+            }),
+            object_ref: None,
+            response_status: Some(ResponseStatus {
+                code: 403,
+                reason: "Forbidden".to_owned(),
+                ..Default::default()
+            }),
+            user_agent: String::new(),
+            request_uri: "/version".to_owned(),
+            ..event
+        };
+        assert_eq!(
+            event.summary(),
+            "2024-03-16T05:28:18.351044Z jane@example.com as system:serviceaccount:shop:deployer delete /version: 403 Forbidden"
+        );
+    }
+}
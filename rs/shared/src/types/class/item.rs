@@ -2,16 +2,87 @@ use std::fmt;
 
 use serde::{Deserialize, Serialize};
 
+use crate::preprocessing::variable::{detect, VarKind};
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(from = "StoredItem")]
 pub enum Item {
     Fix(String),
+    Var(VarKind),
+    // token that is missing in some log lines of the class
+    Opt(String),
+    // any number of variable tokens, including none
+    Variadic,
+}
+
+// Items as stored in redis. States written before variables were typed store `Var` without a
+// kind, it is read as `Var(VarKind::Any)`.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredItem {
+    Typed(TypedItem),
+    Untyped(UntypedItem),
+}
+
+#[derive(Deserialize)]
+enum TypedItem {
+    Fix(String),
+    Var(VarKind),
+    Opt(String),
+    Variadic,
+}
+
+#[derive(Deserialize)]
+enum UntypedItem {
     Var,
 }
+
+impl From<StoredItem> for Item {
+    fn from(item: StoredItem) -> Self {
+        match item {
+            StoredItem::Typed(TypedItem::Fix(s)) => Item::Fix(s),
+            StoredItem::Typed(TypedItem::Var(kind)) => Item::Var(kind),
+            StoredItem::Typed(TypedItem::Opt(s)) => Item::Opt(s),
+            StoredItem::Typed(TypedItem::Variadic) => Item::Variadic,
+            StoredItem::Untyped(UntypedItem::Var) => Item::Var(VarKind::Any),
+        }
+    }
+}
+
+impl Item {
+    pub fn from_token(token: &str) -> Item {
+        match detect(token) {
+            Some(kind) => Item::Var(kind),
+            None => Item::Fix(token.to_string()),
+        }
+    }
+
+    // The item after a log line had a different token at its position
+    pub fn to_variable(&self) -> Item {
+        match self {
+            Item::Fix(_) | Item::Var(_) => Item::Var(VarKind::Any),
+            Item::Opt(_) => Item::Variadic,
+            item => item.clone(),
+        }
+    }
+
+    // The item after a log line had no token at its position
+    pub fn to_optional(&self) -> Item {
+        match self {
+            Item::Fix(s) => Item::Opt(s.clone()),
+            Item::Var(_) => Item::Variadic,
+            item => item.clone(),
+        }
+    }
+}
+
 impl fmt::Display for Item {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Item::Fix(s) => write!(f, "{}", s),
-            Item::Var => write!(f, "<var>"),
+            Item::Var(kind) => write!(f, "{}", kind),
+            Item::Opt(s) => write!(f, "<opt:{}>", s),
+            Item::Variadic => write!(f, "<var*>"),
         }
     }
 }
@@ -1,4 +1,5 @@
 pub mod item;
+pub mod sample;
 pub mod vectorized;
 
 use std::convert::TryInto;
@@ -6,12 +7,16 @@ use std::fmt::{self, Display, Formatter};
 
 use fluvio::dataplane::record::ConsumerRecord;
 use item::Item;
+use sample::{ValueSample, VariableSample};
 use serde::{Deserialize, Serialize};
 use serde_json::{from_str, to_string};
 use tracing::debug;
 use uuid7::uuid7;
 use vectorized::VectorizedClass;
 
+use crate::preprocessing::compare::{align, compare, Alignment};
+use crate::preprocessing::severity::Severity;
+use crate::preprocessing::variable::{detect, mask_tokens, VarKind};
 use crate::types::record::preprocessed::PreprocessedLogRecord;
 
 use super::tokenizer::Tokenizer;
@@ -27,15 +32,31 @@ pub struct Class {
     pub key: String,
     pub namespace: String,
     pub container: String,
+    // timestamps of the first and last log of the class
+    #[serde(default)]
+    pub first_seen: i64,
+    #[serde(default)]
+    pub last_seen: i64,
+    // set when the class was evicted or merged into another class
+    #[serde(default)]
+    pub deleted: bool,
+    // sampled values of the variable items, by position of the item
+    #[serde(default)]
+    pub samples: Vec<Option<ValueSample>>,
+    // highest severity of the logs of the class
+    #[serde(default)]
+    pub severity: Severity,
 }
 impl Class {
     pub fn new(log: &PreprocessedLogRecord, token_count: u32) -> Self {
-        let items: Vec<Item> = log
+        let (items, samples): (Vec<Item>, Vec<Option<ValueSample>>) = log
             .preprocessed_message
-            .clone()
-            .into_iter()
-            .map(Item::Fix)
-            .collect();
+            .iter()
+            .map(|token| match Item::from_token(token) {
+                item @ Item::Var(_) => (item, Some(ValueSample::with_value(token, 1))),
+                item => (item, None),
+            })
+            .unzip();
         Self {
             length: items.len(),
             items,
@@ -43,30 +64,172 @@ impl Class {
             class_id: uuid7().to_string(),
             similarity: 0.0,
             token_count,
-            key: log.key.to_owned(),
+            key: log.workload.to_owned(),
             namespace: log.namespace.to_owned(),
             container: log.container.to_owned(),
+            first_seen: log.timestamp,
+            last_seen: log.timestamp,
+            deleted: false,
+            samples,
+            severity: log.severity,
         }
     }
 
+    pub fn seen(&mut self, timestamp: i64) {
+        self.first_seen = self.first_seen.min(timestamp);
+        self.last_seen = self.last_seen.max(timestamp);
+    }
+
     pub fn mask_items(&self) -> Vec<String> {
         self.items
             .iter()
             .map(|item| match item {
-                Item::Fix(text) => text.clone(),
+                Item::Fix(text) | Item::Opt(text) => text.clone(),
+                // typed variables match any token of the same kind
+                Item::Var(kind) if *kind != VarKind::Any => kind.to_string(),
                 _ => "".to_string(),
             })
             .collect()
     }
 
+    // The `update_items` function aligns the log with the items of the class. Differing tokens
+    // become variables, missing tokens become optional and additional tokens are inserted as
+    // optional items, or as a variadic item if several of them appear in a row. It has to be
+    // called before the log is added to `count`.
     pub fn update_items(&mut self, log: &PreprocessedLogRecord) {
-        for (item, text) in self.items.iter_mut().zip(&log.preprocessed_message) {
-            if let Item::Fix(item_str) = item {
-                if text != item_str {
-                    *item = Item::Var;
+        let tokens = &log.preprocessed_message;
+        let alignment = align(&mask_tokens(tokens), &self.mask_items());
+        let mut entries: Vec<(Item, Option<ValueSample>)> = Vec::with_capacity(alignment.len());
+        let mut inserted: Vec<&String> = Vec::new();
+
+        for step in alignment {
+            if !matches!(step, Alignment::Insert(_)) {
+                push_inserted(&mut entries, &mut inserted);
+            }
+            match step {
+                Alignment::Match(i, j) => push_entry(
+                    &mut entries,
+                    self.record(j, self.items[j].clone(), &tokens[i]),
+                ),
+                Alignment::Mismatch(i, j) => push_entry(
+                    &mut entries,
+                    self.record(j, self.items[j].to_variable(), &tokens[i]),
+                ),
+                Alignment::Delete(j) => {
+                    push_entry(&mut entries, (self.items[j].to_optional(), None))
                 }
+                Alignment::Insert(i) => inserted.push(&tokens[i]),
             }
         }
+        push_inserted(&mut entries, &mut inserted);
+        self.set_entries(entries);
+        self.severity = self.severity.max(log.severity);
+    }
+
+    // The `update_items_positional` function updates the items of a class with the same number
+    // of items as the log, position by position.
+    pub fn update_items_positional(&mut self, log: &PreprocessedLogRecord) {
+        let tokens = &log.preprocessed_message;
+        let agreement = compare(&mask_tokens(tokens), &self.mask_items());
+        let entries = (0..self.items.len())
+            .map(|j| match (tokens.get(j), agreement.get(j)) {
+                (Some(token), Some(true)) => self.record(j, self.items[j].clone(), token),
+                (Some(token), _) => self.record(j, self.items[j].to_variable(), token),
+                (None, _) => (self.items[j].to_optional(), None),
+            })
+            .collect();
+        self.set_entries(entries);
+        self.severity = self.severity.max(log.severity);
+    }
+
+    // The `merge` function folds another class into this one. Items that differ become variables,
+    // items present in only one of the classes become optional.
+    pub fn merge(&mut self, other: &Class) {
+        let alignment = align(&other.mask_items(), &self.mask_items());
+        let mut entries: Vec<(Item, Option<ValueSample>)> = Vec::with_capacity(alignment.len());
+        for step in alignment {
+            entries.push(match step {
+                Alignment::Match(i, j) => match other.items[i] {
+                    Item::Opt(_) | Item::Variadic => (self.items[j].to_optional(), None),
+                    _ => self.merge_samples(j, self.items[j].clone(), other, i),
+                },
+                Alignment::Mismatch(i, j) => {
+                    self.merge_samples(j, self.items[j].to_variable(), other, i)
+                }
+                Alignment::Delete(j) => (self.items[j].to_optional(), None),
+                Alignment::Insert(i) => (other.items[i].to_optional(), None),
+            });
+        }
+        // consecutive variadic items are redundant
+        entries.dedup_by(|a, b| matches!((&a.0, &b.0), (Item::Variadic, Item::Variadic)));
+
+        self.set_entries(entries);
+        self.count += other.count;
+        self.severity = self.severity.max(other.severity);
+        self.seen(other.first_seen);
+        self.seen(other.last_seen);
+    }
+
+    // The `variable_samples` function returns the sampled values of the variable items.
+    pub fn variable_samples(&self) -> Vec<VariableSample> {
+        self.samples
+            .iter()
+            .enumerate()
+            .filter_map(|(position, sample)| {
+                sample.as_ref().map(|sample| VariableSample {
+                    position,
+                    values: sample.values.clone(),
+                })
+            })
+            .collect()
+    }
+
+    // The sample of the item at `index`. A fixed item that becomes variable contributed its
+    // value to all logs of the class so far.
+    fn sample_or_seed(&self, index: usize) -> ValueSample {
+        match (
+            self.samples.get(index).cloned().flatten(),
+            &self.items[index],
+        ) {
+            (Some(sample), _) => sample,
+            (None, Item::Fix(text)) => ValueSample::with_value(text, self.count),
+            (None, _) => ValueSample::default(),
+        }
+    }
+
+    fn record(&self, index: usize, item: Item, token: &str) -> (Item, Option<ValueSample>) {
+        match item {
+            Item::Var(_) => {
+                let mut sample = self.sample_or_seed(index);
+                sample.add(token, 1);
+                (item, Some(sample))
+            }
+            _ => (item, None),
+        }
+    }
+
+    fn merge_samples(
+        &self,
+        index: usize,
+        item: Item,
+        other: &Class,
+        other_index: usize,
+    ) -> (Item, Option<ValueSample>) {
+        match item {
+            Item::Var(_) => {
+                let mut sample = self.sample_or_seed(index);
+                sample.merge(&other.sample_or_seed(other_index));
+                (item, Some(sample))
+            }
+            _ => (item, None),
+        }
+    }
+
+    fn set_entries(&mut self, entries: Vec<(Item, Option<ValueSample>)>) {
+        let (items, samples): (Vec<Item>, Vec<Option<ValueSample>>) = entries.into_iter().unzip();
+        self.length = items.len();
+        self.items = items;
+        self.samples = samples;
     }
 
     pub fn from_log_and_token_count(log: &PreprocessedLogRecord, token_count: u32) -> Self {
@@ -83,6 +246,26 @@ impl Class {
         VectorizedClass::new(self.clone(), token_count_cut, representation)
     }
 }
+
+fn push_inserted(entries: &mut Vec<(Item, Option<ValueSample>)>, inserted: &mut Vec<&String>) {
+    match inserted.as_slice() {
+        [] => return,
+        // a variadic item already covers any number of tokens
+        _ if matches!(entries.last(), Some((Item::Variadic, _))) => {}
+        [token] if detect(token).is_none() => entries.push((Item::Opt(token.to_string()), None)),
+        _ => entries.push((Item::Variadic, None)),
+    }
+    inserted.clear();
+}
+
+// A variadic item directly after another one is merged into it.
+fn push_entry(entries: &mut Vec<(Item, Option<ValueSample>)>, entry: (Item, Option<ValueSample>)) {
+    if matches!(entry.0, Item::Variadic) && matches!(entries.last(), Some((Item::Variadic, _))) {
+        return;
+    }
+    entries.push(entry);
+}
+
 impl TryInto<String> for Class {
     type Error = serde_json::Error;
 
@@ -121,3 +304,66 @@ impl Display for Class {
         write!(f, "{items}")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Class, Item};
+    use crate::{
+        preprocessing::{log::preprocess_message, severity::Severity, variable::VarKind},
+        types::record::{log::LogRecord, preprocessed::PreprocessedLogRecord},
+        utils::mock::mock_client::get_test_metadata,
+    };
+
+    fn preprocessed_log(message: &str) -> PreprocessedLogRecord {
+        let metadata = get_test_metadata("test-pod");
+        let log = LogRecord::new(0, message, "record_id".to_string(), &metadata);
+        let tokens = preprocess_message(message, "db", "key", "record_id");
+        PreprocessedLogRecord::from((log, tokens))
+    }
+
+    #[test]
+    fn test_update_items_samples_variable_values() {
+        let mut class = Class::new(&preprocessed_log("GET /health 200"), 0);
+        for message in ["GET /health 404", "GET /health 200"] {
+            class.update_items(&preprocessed_log(message));
+            class.count += 1;
+        }
+
+        assert_eq!(class.to_string(), "GET /health <var>");
+        let samples = class.variable_samples();
+        assert_eq!(samples.len(), 1);
+        assert_eq!(samples[0].position, 2);
+        let values: Vec<(&str, u32)> = samples[0]
+            .values
+            .iter()
+            .map(|v| (v.value.as_str(), v.count))
+            .collect();
+        assert_eq!(values, [("200", 2), ("404", 1)]);
+    }
+
+    #[test]
+    fn test_update_items_merges_adjacent_variadic_items() {
+        let mut class = Class::new(&preprocessed_log("worker started 5 now"), 0);
+        class.items = vec![
+            Item::Fix("worker".to_string()),
+            Item::Variadic,
+            Item::Var(VarKind::Any),
+            Item::Fix("now".to_string()),
+        ];
+        // the missing variable becomes variadic and is merged into the preceding one
+        class.update_items(&preprocessed_log("worker now"));
+
+        assert_eq!(class.to_string(), "worker <var*> now");
+        assert_eq!(class.length, 3);
+        assert_eq!(class.samples.len(), 3);
+    }
+
+    #[test]
+    fn test_update_items_keeps_highest_severity() {
+        let mut class = Class::new(&preprocessed_log("INFO request to /health took 5ms"), 0);
+        class.update_items(&preprocessed_log("ERROR request to /health took 5s"));
+        class.update_items(&preprocessed_log("WARN request to /health took 1s"));
+
+        assert_eq!(class.severity, Severity::Error);
+    }
+}
@@ -0,0 +1,99 @@
+use serde::{Deserialize, Serialize};
+
+const MAX_SAMPLED_VALUES: usize = 10;
+const MAX_VALUE_LENGTH: usize = 128;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SampledValue {
+    pub value: String,
+    pub count: u32,
+}
+
+// Bounded top-k of the values observed at a variable position. Uses the space-saving algorithm:
+// when full, the least frequent value is replaced and its count is inherited, so counts of
+// frequent values are overestimated by at most the smallest count.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ValueSample {
+    pub values: Vec<SampledValue>,
+}
+
+// The sampled values of the item at `position` of a class.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct VariableSample {
+    pub position: usize,
+    pub values: Vec<SampledValue>,
+}
+
+impl ValueSample {
+    pub fn with_value(value: &str, count: u32) -> Self {
+        let mut sample = Self::default();
+        sample.add(value, count);
+        sample
+    }
+
+    pub fn add(&mut self, value: &str, count: u32) {
+        let value: String = value.chars().take(MAX_VALUE_LENGTH).collect();
+        if let Some(sampled) = self.values.iter_mut().find(|v| v.value == value) {
+            sampled.count += count;
+        } else if self.values.len() < MAX_SAMPLED_VALUES {
+            self.values.push(SampledValue { value, count });
+        } else if let Some(last) = self.values.last_mut() {
+            // values are kept sorted, the last one is the least frequent
+            last.count += count;
+            last.value = value;
+        }
+        self.values.sort_by_key(|v| std::cmp::Reverse(v.count));
+    }
+
+    pub fn merge(&mut self, other: &ValueSample) {
+        for sampled in &other.values {
+            self.add(&sampled.value, sampled.count);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SampledValue, ValueSample, MAX_SAMPLED_VALUES};
+
+    #[test]
+    fn test_value_sample_top_k() {
+        let mut sample = ValueSample::default();
+        for _ in 0..5 {
+            sample.add("200", 1);
+        }
+        sample.add("404", 2);
+        for i in 0..MAX_SAMPLED_VALUES {
+            sample.add(&format!("rare-{i}"), 1);
+        }
+
+        assert_eq!(sample.values.len(), MAX_SAMPLED_VALUES);
+        assert_eq!(
+            sample.values[..2],
+            [
+                SampledValue {
+                    value: "200".to_string(),
+                    count: 5
+                },
+                SampledValue {
+                    value: "404".to_string(),
+                    count: 2
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_value_sample_merge() {
+        let mut sample = ValueSample::with_value("a", 3);
+        sample.merge(&ValueSample::with_value("b", 4));
+        sample.merge(&ValueSample::with_value("a", 2));
+
+        let values: Vec<(&str, u32)> = sample
+            .values
+            .iter()
+            .map(|v| (v.value.as_str(), v.count))
+            .collect();
+        assert_eq!(values, [("a", 5), ("b", 4)]);
+    }
+}
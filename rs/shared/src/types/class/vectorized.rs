@@ -1,12 +1,14 @@
 use std::collections::HashMap;
 
-use crate::{constant::EMBEDDING_USIZE, types::tokenizer::Tokenizer};
+use crate::{
+    constant::EMBEDDING_USIZE, preprocessing::severity::Severity, types::tokenizer::Tokenizer,
+};
 use qdrant_client::qdrant::{PointStruct, ScoredPoint, Value};
 use serde::{Deserialize, Serialize};
 use serde_json::Error as JsonError;
 use tracing::debug;
 
-use super::Class;
+use super::{sample::VariableSample, Class};
 
 pub trait Id {
     fn get_id(&self) -> &str;
@@ -33,10 +35,16 @@ pub struct VectorizedClass {
     pub token_count_original: u32,
     pub token_count_cut: u32,
     pub score: f32,
+    // observed values of the variables in the representation
+    #[serde(default)]
+    pub variables: Vec<VariableSample>,
+    #[serde(default)]
+    pub severity: Severity,
 }
 
 impl VectorizedClass {
     pub fn new(class: Class, token_count_cut: usize, representation: String) -> Self {
+        let variables = class.variable_samples();
         Self {
             class_id: class.class_id,
             container: class.container,
@@ -47,6 +55,8 @@ impl VectorizedClass {
             token_count_original: class.token_count,
             token_count_cut: token_count_cut as u32,
             score: 0.0,
+            variables,
+            severity: class.severity,
         }
     }
 }
@@ -1,4 +1,5 @@
-use std::num::ParseFloatError;
+use std::num::{ParseFloatError, ParseIntError};
+use std::str::ParseBoolError;
 
 use thiserror::Error;
 
@@ -10,8 +11,16 @@ pub enum ClassifierError {
     ConfigError(#[from] ConfigError),
     #[error("Error: {0}")]
     ParseFloatError(#[from] ParseFloatError),
+    #[error("Error: {0}")]
+    ParseIntError(#[from] ParseIntError),
+    #[error("Error: {0}")]
+    ParseBoolError(#[from] ParseBoolError),
+    #[error("Invalid threshold rules: {0}")]
+    ThresholdRules(#[from] serde_json::Error),
     #[error("Redis connection error: {0}")]
     RedisConnectionError(#[from] RedisConnectionError),
+    #[error("Unknown classifier backend: {0}")]
+    UnknownBackend(String),
     #[error("Anyhow error: {0}")]
     AnyhowError(#[from] anyhow::Error),
 }
@@ -1,11 +1,182 @@
+use std::collections::{HashMap, HashSet};
+
 use redis::{from_redis_value, FromRedisValue, RedisResult, RedisWrite, ToRedisArgs, Value};
 use serde::{Deserialize, Serialize};
 
+use crate::preprocessing::compare::{align, similarity, similarity_bound};
 use crate::types::class::Class;
+use crate::types::tokenizer::Tokenizer;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct ClassifierState {
     pub classes: Vec<Class>,
+    // incremented on every write to redis, states without version are treated as version 0
+    #[serde(default)]
+    pub version: u64,
+    // ids of classes removed since the last write, so they are not restored by `rebase`
+    #[serde(skip)]
+    pub removed: HashSet<String>,
+    // counts of the classes as last read from or written to redis, to tell the changes of this
+    // state apart from the changes of other writers in `rebase`
+    #[serde(skip)]
+    pub loaded: HashMap<String, u32>,
+    // thresholds used for the containers of the key, see `KeyThreshold`
+    #[serde(default)]
+    pub thresholds: HashMap<String, KeyThreshold>,
+}
+
+// The threshold chosen for a container of a key. `configured` is the threshold of the matching
+// rule, `current` starts there and is adapted to the class growth of the container in adaptive
+// mode. Logs and new classes are counted since the last adaptation.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct KeyThreshold {
+    pub configured: f64,
+    pub current: f64,
+    #[serde(default)]
+    pub logs: u32,
+    #[serde(default)]
+    pub new_classes: u32,
+}
+
+impl KeyThreshold {
+    pub fn new(configured: f64) -> Self {
+        KeyThreshold {
+            configured,
+            current: configured,
+            logs: 0,
+            new_classes: 0,
+        }
+    }
+}
+
+impl ClassifierState {
+    // The `rebase` function returns the state that replaces `current` in redis. If `current` was
+    // written since this state was loaded, classes created by other writers are kept, classes
+    // changed by both are merged and classes removed by other writers stay removed.
+    pub fn rebase(&self, current: Option<ClassifierState>) -> ClassifierState {
+        let mut next = self.clone();
+        let current_version = match current {
+            Some(current) => {
+                if current.version != self.version {
+                    next.merge(current.classes);
+                }
+                current.version
+            }
+            None => 0,
+        };
+        next.version = current_version + 1;
+        next.removed.clear();
+        next.loaded = next.counts();
+        next
+    }
+
+    // The `evict` function removes classes until at most `capacity` classes are left. Classes not
+    // seen for `ttl` milliseconds before the newest log of the state go first, least recently seen
+    // first, then the classes with the lowest count.
+    pub fn evict(&mut self, capacity: usize, ttl: i64) -> Vec<Class> {
+        let mut evicted = Vec::new();
+        let newest = self.classes.iter().map(|class| class.last_seen).max();
+        let stale_before = newest.unwrap_or_default().saturating_sub(ttl);
+        while self.classes.len() > capacity {
+            let index = match self.classes.iter().enumerate().min_by_key(|(_, class)| {
+                match class.last_seen < stale_before {
+                    true => (false, 0, class.last_seen),
+                    false => (true, class.count, class.last_seen),
+                }
+            }) {
+                Some((index, _)) => index,
+                None => break,
+            };
+            evicted.push(self.remove(index));
+        }
+        evicted
+    }
+
+    // The `merge_similar` function folds classes whose items agree above the threshold into the
+    // earlier class. Returns the merged classes and the classes that were folded into them.
+    pub fn merge_similar(
+        &mut self,
+        threshold: f64,
+        tokenizer: &Tokenizer,
+    ) -> (Vec<Class>, Vec<Class>) {
+        let mut merged: Vec<usize> = Vec::new();
+        let mut folded = Vec::new();
+        let mut i = 0;
+        while i < self.classes.len() {
+            let mut j = i + 1;
+            while j < self.classes.len() {
+                let (class, other) = (&self.classes[i], &self.classes[j]);
+                if similarity_bound(class.length, other.length) >= threshold
+                    && similarity(&align(&other.mask_items(), &class.mask_items())) >= threshold
+                {
+                    let other = self.remove(j);
+                    self.classes[i].merge(&other);
+                    folded.push(other);
+                    if !merged.contains(&i) {
+                        merged.push(i);
+                    }
+                } else {
+                    j += 1;
+                }
+            }
+            i += 1;
+        }
+        let merged = merged
+            .into_iter()
+            .map(|i| {
+                let class = &mut self.classes[i];
+                class.token_count = tokenizer.calculate_token_length(&class.to_string()) as u32;
+                class.clone()
+            })
+            .collect();
+        (merged, folded)
+    }
+
+    fn remove(&mut self, index: usize) -> Class {
+        let mut class = self.classes.remove(index);
+        self.removed.insert(class.class_id.clone());
+        class.deleted = true;
+        class
+    }
+
+    fn merge(&mut self, classes: Vec<Class>) {
+        // loaded classes missing from `classes` were evicted or merged away by another writer
+        let current: HashSet<&str> = classes.iter().map(|c| c.class_id.as_str()).collect();
+        let loaded = &self.loaded;
+        self.classes.retain(|local| {
+            !loaded.contains_key(&local.class_id) || current.contains(local.class_id.as_str())
+        });
+
+        for class in classes
+            .into_iter()
+            .filter(|c| !self.removed.contains(&c.class_id))
+        {
+            let loaded = self.loaded.get(&class.class_id).copied();
+            match self
+                .classes
+                .iter_mut()
+                .find(|local| local.class_id == class.class_id)
+            {
+                // only changed by other writers
+                Some(local) if Some(local.count) == loaded => *local = class,
+                // only changed by this state
+                Some(_) if Some(class.count) == loaded => {}
+                Some(local) => {
+                    local.merge(&class);
+                    // both counts include the count at the time the state was loaded
+                    local.count -= loaded.unwrap_or(0).min(local.count);
+                }
+                None => self.classes.push(class),
+            }
+        }
+    }
+
+    fn counts(&self) -> HashMap<String, u32> {
+        self.classes
+            .iter()
+            .map(|class| (class.class_id.clone(), class.count))
+            .collect()
+    }
 }
 
 impl ToRedisArgs for ClassifierState {
@@ -21,6 +192,167 @@ impl ToRedisArgs for ClassifierState {
 impl FromRedisValue for ClassifierState {
     fn from_redis_value(v: &Value) -> RedisResult<Self> {
         let json_str: String = from_redis_value(v)?;
-        Ok(serde_json::from_str::<ClassifierState>(&json_str)?)
+        let mut state = serde_json::from_str::<ClassifierState>(&json_str)?;
+        state.loaded = state.counts();
+        Ok(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ClassifierState;
+    use crate::{
+        preprocessing::variable::VarKind,
+        types::{
+            class::{item::Item, Class},
+            tokenizer::Tokenizer,
+        },
+        utils::mock::{mock_client::get_test_metadata, mock_data::class_from_items},
+    };
+
+    fn class(text: &str, count: u32, last_seen: i64) -> Class {
+        let metadata = get_test_metadata("test-pod");
+        let items = text.split(' ').map(|t| Item::Fix(t.to_string())).collect();
+        let mut class = class_from_items(items, 0.0, &metadata);
+        class.count = count;
+        class.last_seen = last_seen;
+        class
+    }
+
+    #[test]
+    fn test_evict() {
+        let mut state = ClassifierState {
+            classes: vec![
+                class("a b c", 5, 1),
+                class("d e f", 1, 3),
+                class("g h i", 1, 2),
+            ],
+            ..Default::default()
+        };
+        let evicted = state.evict(2, 1000);
+        assert_eq!(evicted.len(), 1);
+        assert_eq!(evicted[0].to_string(), "g h i");
+        assert!(evicted[0].deleted);
+        assert!(state.removed.contains(&evicted[0].class_id));
+    }
+
+    #[test]
+    fn test_evict_stale_first() {
+        let mut state = ClassifierState {
+            classes: vec![
+                class("a b c", 500, 1_000),
+                class("d e f", 1, 90_000),
+                class("g h i", 2, 100_000),
+            ],
+            ..Default::default()
+        };
+        // the idle class goes first, even though it was seen most often
+        let evicted = state.evict(2, 50_000);
+        assert_eq!(evicted[0].to_string(), "a b c");
+        let evicted = state.evict(1, 50_000);
+        assert_eq!(evicted[0].to_string(), "d e f");
+    }
+
+    #[test]
+    fn test_merge_similar() {
+        let mut state = ClassifierState {
+            classes: vec![
+                class("INFO Connection to primary db established", 3, 1),
+                class("ERROR something else", 1, 2),
+                class("INFO Connection to db established", 2, 3),
+            ],
+            ..Default::default()
+        };
+        let tokenizer = Tokenizer::new().unwrap();
+        let (merged, folded) = state.merge_similar(0.8, &tokenizer);
+        assert_eq!(merged.len(), 1);
+        assert_eq!(
+            merged[0].to_string(),
+            "INFO Connection to <opt:primary> db established"
+        );
+        assert_eq!((merged[0].count, merged[0].last_seen), (5, 3));
+        assert_eq!(
+            merged[0].token_count as usize,
+            tokenizer.calculate_token_length(&merged[0].to_string())
+        );
+        assert_eq!(folded.len(), 1);
+        assert_eq!(state.classes.len(), 2);
+    }
+
+    #[test]
+    fn test_rebase_keeps_removed_classes_deleted() {
+        let remote = ClassifierState {
+            classes: vec![class("a b c", 1, 1), class("d e f", 1, 1)],
+            version: 2,
+            ..Default::default()
+        };
+        let mut local = ClassifierState {
+            classes: remote.classes.clone(),
+            version: 1,
+            ..Default::default()
+        };
+        local.evict(1, 1000);
+        let next = local.rebase(Some(remote));
+        assert_eq!(next.classes.len(), 1);
+        assert_eq!(next.version, 3);
+        assert!(next.removed.is_empty());
+    }
+
+    #[test]
+    fn test_rebase_merges_classes_changed_by_both() {
+        let mut loaded = ClassifierState {
+            classes: vec![class("GET /health 200", 5, 1)],
+            version: 1,
+            ..Default::default()
+        };
+        loaded.loaded = loaded.counts();
+        let mut remote = loaded.clone();
+        remote.classes[0].items[2] = Item::Var(VarKind::Any);
+        remote.classes[0].count = 8;
+        remote.version = 2;
+        let mut local = loaded;
+        local.classes[0].count = 7;
+
+        let next = local.rebase(Some(remote));
+        assert_eq!(next.classes.len(), 1);
+        assert_eq!(next.classes[0].to_string(), "GET /health <var>");
+        assert_eq!(next.classes[0].count, 10);
+        assert_eq!(next.loaded[&next.classes[0].class_id], 10);
+    }
+
+    #[test]
+    fn test_rebase_drops_classes_removed_by_other_writers() {
+        let mut local = ClassifierState {
+            classes: vec![class("a b c", 2, 1), class("d e f", 1, 1)],
+            version: 1,
+            ..Default::default()
+        };
+        local.loaded = local.counts();
+        let mut remote = local.clone();
+        remote.evict(1, 1000);
+        remote.version = 2;
+        local.classes[1].count += 1;
+        local.classes.push(class("g h i", 1, 2));
+
+        let next = local.rebase(Some(remote));
+        let classes: Vec<String> = next.classes.iter().map(|c| c.to_string()).collect();
+        assert_eq!(classes, ["a b c", "g h i"]);
+        assert_eq!(next.version, 3);
+    }
+
+    #[test]
+    fn test_deserialize_untyped_var_state() {
+        // a state as written before variables were typed
+        let json = r#"{"classes":[{"items":[{"Fix":"GET"},"Var",{"Fix":"200"}],"count":3,"length":3,"class_id":"0190a3a2-7b6c-7c4e-9d1e-1f2a3b4c5d6e","similarity":0.8,"token_count":3,"key":"api","namespace":"default","container":"api"}]}"#;
+        let state: ClassifierState = serde_json::from_str(json).unwrap();
+        assert_eq!(state.version, 0);
+        let class = &state.classes[0];
+        assert!(matches!(class.items[1], Item::Var(VarKind::Any)));
+        assert_eq!(class.to_string(), "GET <var> 200");
+        assert_eq!(class.count, 3);
+
+        let json = serde_json::to_string(&state).unwrap();
+        let state: ClassifierState = serde_json::from_str(&json).unwrap();
+        assert!(matches!(state.classes[0].items[1], Item::Var(VarKind::Any)));
     }
 }
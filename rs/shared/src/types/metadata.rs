@@ -1,6 +1,8 @@
 use serde::{Deserialize, Serialize};
 use std::error::Error;
 
+use crate::constant::KUBERNETES_NAME_SUFFIX_ALPHABET;
+
 #[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct Metadata {
     pub filename: String,
@@ -12,6 +14,20 @@ pub struct Metadata {
 }
 
 impl Metadata {
+    // The `from_pod` function creates the metadata of a container whose logs are not read from a
+    // file, e.g. logs pushed by a collector or read from the database. The path is the one the
+    // kubelet would use.
+    pub fn from_pod(namespace: &str, pod_name: &str, pod_uid: &str, container: &str) -> Self {
+        Self {
+            filename: String::new(),
+            path: format!("/var/log/pods/{namespace}_{pod_name}_{pod_uid}/{container}"),
+            namespace: namespace.to_owned(),
+            pod_name: pod_name.to_owned(),
+            pod_uid: pod_uid.to_owned(),
+            container: container.to_owned(),
+        }
+    }
+
     pub fn from_path(filename: &str, path: &str) -> Result<Self, Box<dyn Error>> {
         // Deconstruct namespace, pod and container
         let components: Vec<&str> = path.split('/').collect();
@@ -59,3 +75,68 @@ impl Metadata {
         })
     }
 }
+
+// The `workload_name` function derives the name of the owning workload from a pod name, so that
+// replicas and rollouts of a workload share their log classes:
+// - Deployment: `<name>-<pod-template-hash>-<suffix>`
+// - DaemonSet, Job: `<name>-<suffix>`
+// - StatefulSet: `<name>-<ordinal>`
+// - CronJob: `<name>-<scheduled time>-<suffix>`
+// Pods that do not follow these patterns, e.g. static pods, keep their name.
+pub fn workload_name(pod_name: &str) -> String {
+    let mut name = pod_name;
+    if let Some((prefix, suffix)) = name.rsplit_once('-') {
+        if is_generated(suffix, 5..=5) {
+            name = prefix;
+            if let Some((prefix, hash)) = name.rsplit_once('-') {
+                if is_generated(hash, 6..=10) && hash.chars().any(|c| c.is_ascii_digit()) {
+                    name = prefix;
+                }
+            }
+        }
+    }
+    if let Some((prefix, ordinal)) = name.rsplit_once('-') {
+        if !ordinal.is_empty() && ordinal.chars().all(|c| c.is_ascii_digit()) {
+            name = prefix;
+        }
+    }
+    if name.is_empty() {
+        pod_name.to_owned()
+    } else {
+        name.to_owned()
+    }
+}
+
+fn is_generated(segment: &str, length: std::ops::RangeInclusive<usize>) -> bool {
+    length.contains(&segment.len())
+        && segment
+            .chars()
+            .all(|c| KUBERNETES_NAME_SUFFIX_ALPHABET.contains(c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{workload_name, Metadata};
+    use rstest::rstest;
+
+    #[test]
+    fn test_from_pod_matches_path() {
+        let metadata = Metadata::from_pod("shop", "cart-0", "9c5e1c4a", "cart");
+        assert_eq!(Metadata::from_path("", &metadata.path).unwrap(), metadata);
+    }
+
+    #[rstest]
+    #[case("coredns-7db6d8ff4d-x7k2p", "coredns")]
+    #[case("kindnet-8ctwq", "kindnet")]
+    #[case("kube-proxy-bhlzq", "kube-proxy")]
+    #[case("redis-master-0", "redis-master")]
+    #[case("backup-28470960-x7k2p", "backup")]
+    #[case(
+        "kube-apiserver-kind-control-plane",
+        "kube-apiserver-kind-control-plane"
+    )]
+    #[case("kind-worker2", "kind-worker2")]
+    fn test_workload_name(#[case] pod_name: &str, #[case] expected: &str) {
+        assert_eq!(workload_name(pod_name), expected);
+    }
+}
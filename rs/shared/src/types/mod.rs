@@ -1,3 +1,4 @@
+pub mod audit;
 pub mod class;
 pub mod classifier;
 pub mod kubeapidata;
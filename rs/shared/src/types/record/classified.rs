@@ -1,4 +1,4 @@
-use crate::types::class::Class;
+use crate::{preprocessing::severity::Severity, types::class::Class};
 
 use super::preprocessed::PreprocessedLogRecord;
 
@@ -12,9 +12,11 @@ pub struct ClassifiedLogRecord {
     pub class_id: String,
     pub similarity: f64,
     pub key: String,
+    pub workload: String,
     pub namespace: String,
     pub pod_uid: String,
     pub container: String,
+    pub severity: Severity,
 }
 impl ClassifiedLogRecord {
     pub fn new(log: &PreprocessedLogRecord, class: &Class) -> Self {
@@ -28,9 +30,11 @@ impl ClassifiedLogRecord {
             class_id: class.class_id.to_owned(),
             similarity: class.similarity,
             key: log.key.to_owned(),
+            workload: log.workload.to_owned(),
             namespace: log.namespace.to_owned(),
             pod_uid: log.pod_uid.to_owned(),
             container: log.container.to_owned(),
+            severity: log.severity,
         }
     }
 }
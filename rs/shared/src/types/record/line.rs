@@ -0,0 +1,197 @@
+use chrono::DateTime;
+use serde::{Deserialize, Serialize};
+use strum::{Display, EnumString};
+
+use super::log::dt_from_ts;
+
+// The output stream of the container, `Unknown` for lines without a runtime prefix.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize, Display, EnumString,
+)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum Stream {
+    #[default]
+    Unknown,
+    Stdout,
+    Stderr,
+}
+
+// A physical line of a container log file. `partial` is set for lines the runtime split because
+// they exceeded its buffer, the rest follows in the next lines of the same stream.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContainerLine {
+    pub timestamp: Option<i64>,
+    pub stream: Stream,
+    pub partial: bool,
+    pub message: String,
+}
+
+impl ContainerLine {
+    fn unparsed(timestamp: Option<i64>, message: &str) -> Self {
+        ContainerLine {
+            timestamp,
+            stream: Stream::Unknown,
+            partial: false,
+            message: message.to_owned(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct DockerLine {
+    log: String,
+    stream: String,
+    time: String,
+}
+
+// The `parse_line` function reads a line written by a container runtime:
+// - CRI: `2024-03-16T07:28:18.752849123+02:00 stderr F message`, `P` marks a partial line
+// - docker json-file: `{"log":"message\n","stream":"stderr","time":"2024-03-16T05:28:18.75Z"}`
+// Lines with a timestamp but without stream and tag, e.g. `2024-03-16T05:28:18Z message`, keep
+// the timestamp. Other lines are returned as a whole without a timestamp.
+pub fn parse_line(line: &str) -> ContainerLine {
+    let line = line.trim_end_matches(['\r', '\n']);
+    docker_line(line).unwrap_or_else(|| cri_line(line))
+}
+
+// The `parse_timestamp` function reads RFC 3339 timestamps with a `Z` or an offset as milliseconds
+// since the epoch. Timestamps without a zone are taken as UTC.
+pub fn parse_timestamp(ts: &str) -> Option<i64> {
+    DateTime::parse_from_rfc3339(ts)
+        .map(|dt| dt.timestamp_millis())
+        .ok()
+        .or_else(|| dt_from_ts(ts).ok())
+}
+
+// docker marks a line it split by the missing newline at the end of `log`
+fn docker_line(line: &str) -> Option<ContainerLine> {
+    if !line.starts_with('{') {
+        return None;
+    }
+    let docker = serde_json::from_str::<DockerLine>(line).ok()?;
+    let (message, partial) = match docker.log.strip_suffix('\n') {
+        Some(message) => (message.trim_end_matches('\r').to_owned(), false),
+        None => (docker.log, true),
+    };
+    Some(ContainerLine {
+        timestamp: parse_timestamp(&docker.time),
+        stream: docker.stream.parse().unwrap_or_default(),
+        partial,
+        message,
+    })
+}
+
+fn cri_line(line: &str) -> ContainerLine {
+    let (ts, rest) = line.split_once(' ').unwrap_or((line, ""));
+    let Some(timestamp) = parse_timestamp(ts) else {
+        return ContainerLine::unparsed(None, line);
+    };
+    let mut fields = rest.splitn(3, ' ');
+    let stream = fields
+        .next()
+        .and_then(|stream| stream.parse::<Stream>().ok());
+    // the first of the `:` separated tags is the partial flag
+    let partial = fields.next().and_then(|tags| match tags.split(':').next() {
+        Some("P") => Some(true),
+        Some("F") => Some(false),
+        _ => None,
+    });
+    match (stream, partial) {
+        (Some(stream), Some(partial)) if stream != Stream::Unknown => ContainerLine {
+            timestamp: Some(timestamp),
+            stream,
+            partial,
+            message: fields.next().unwrap_or_default().to_owned(),
+        },
+        _ => ContainerLine::unparsed(Some(timestamp), rest),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(
+        "2024-03-16T05:28:18.100412Z stderr F ERROR request failed",
+        Some(1710566898100),
+        Stream::Stderr,
+        false,
+        "ERROR request failed"
+    )]
+    #[case(
+        "2024-03-16T07:28:18.100412345+02:00 stdout P {\"level\":\"info\",",
+        Some(1710566898100),
+        Stream::Stdout,
+        true,
+        "{\"level\":\"info\","
+    )]
+    #[case(
+        "2024-03-16T05:28:18.100412Z stdout F ",
+        Some(1710566898100),
+        Stream::Stdout,
+        false,
+        ""
+    )]
+    #[case(
+        "{\"log\":\"Zone Z1 unavailable\\n\",\"stream\":\"stderr\",\"time\":\"2024-03-16T05:28:18.100412Z\"}",
+        Some(1710566898100),
+        Stream::Stderr,
+        false,
+        "Zone Z1 unavailable"
+    )]
+    #[case(
+        "{\"log\":\"GET /health \",\"stream\":\"stdout\",\"time\":\"2024-03-16T05:28:18.1Z\"}",
+        Some(1710566898100),
+        Stream::Stdout,
+        true,
+        "GET /health "
+    )]
+    #[case(
+        "2024-03-16T05:28:18.100Z INFO Zone Z1 ready\r",
+        Some(1710566898100),
+        Stream::Unknown,
+        false,
+        "INFO Zone Z1 ready"
+    )]
+    #[case(
+        "2024-03-16T05:28:18.100Z stdout ready",
+        Some(1710566898100),
+        Stream::Unknown,
+        false,
+        "stdout ready"
+    )]
+    #[case(
+        "{\"level\":\"info\",\"log\":\"ready\"}",
+        None,
+        Stream::Unknown,
+        false,
+        "{\"level\":\"info\",\"log\":\"ready\"}"
+    )]
+    #[case(
+        "Trace[535324451]: [878.754588ms] END",
+        None,
+        Stream::Unknown,
+        false,
+        "Trace[535324451]: [878.754588ms] END"
+    )]
+    fn test_parse_line(
+        #[case] line: &str,
+        #[case] timestamp: Option<i64>,
+        #[case] stream: Stream,
+        #[case] partial: bool,
+        #[case] message: &str,
+    ) {
+        assert_eq!(
+            parse_line(line),
+            ContainerLine {
+                timestamp,
+                stream,
+                partial,
+                message: message.to_owned(),
+            }
+        );
+    }
+}
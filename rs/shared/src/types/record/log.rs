@@ -9,15 +9,17 @@ use uuid7::uuid7;
 
 use crate::{
     constant::FLUVIO_BYTES_SAFTY_MARGIN,
-    types::metadata::Metadata,
+    preprocessing::severity::{extract_severity, Severity},
+    types::{
+        metadata::Metadata,
+        record::line::{parse_line, ContainerLine, Stream},
+    },
     utils::mock::{
         mock_client::{generate_podname, get_test_metadata},
         mock_data::TestCase,
     },
 };
 
-const DEFAULT_TS: &str = "1970-01-01T00:00:00Z";
-
 #[derive(Debug, Error)]
 pub enum LogParseError {
     #[error("Timestamp not found in record: {0}")]
@@ -35,6 +37,10 @@ pub struct LogRecord {
     pub namespace: String,
     pub pod_uid: String,
     pub container: String,
+    #[serde(default)]
+    pub severity: Severity,
+    #[serde(default)]
+    pub stream: Stream,
 }
 
 impl LogRecord {
@@ -47,6 +53,8 @@ impl LogRecord {
             namespace: metadata.namespace.to_owned(),
             pod_uid: metadata.pod_uid.to_owned(),
             container: metadata.container.to_owned(),
+            severity: extract_severity(message),
+            stream: Stream::Unknown,
         }
     }
     pub fn truncate_record(&mut self, db: &str, max_bytes: usize) {
@@ -66,20 +74,21 @@ impl LogRecord {
 impl From<(&String, &Metadata)> for LogRecord {
     // This is used to parse the string from raw data
     fn from((raw_message, metadata): (&String, &Metadata)) -> LogRecord {
+        LogRecord::from((parse_line(raw_message), metadata))
+    }
+}
+impl From<(ContainerLine, &Metadata)> for LogRecord {
+    // This is used for lines parsed and reassembled by the data intake
+    fn from((line, metadata): (ContainerLine, &Metadata)) -> LogRecord {
         let record_id = uuid7().to_string();
-        let mut split = raw_message.splitn(2, 'Z');
-        let datetime_str = split.next().unwrap_or_else(|| {
+        let timestamp = line.timestamp.unwrap_or_else(|| {
             warn!("{}", LogParseError::MissingTimestamp(record_id.clone()));
-            DEFAULT_TS
+            0
         });
-        let ts = dt_from_ts(datetime_str)
-            .map_err(|e| {
-                warn!("{}", LogParseError::ParseError(record_id.clone(), e));
-            })
-            .unwrap_or(0);
-        let message = split.next().unwrap_or(raw_message);
-
-        LogRecord::new(ts, message, record_id, metadata)
+        LogRecord {
+            stream: line.stream,
+            ..LogRecord::new(timestamp, &line.message, record_id, metadata)
+        }
     }
 }
 impl TryFrom<ConsumerRecord> for LogRecord {
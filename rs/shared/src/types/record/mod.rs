@@ -1,3 +1,4 @@
 pub mod classified;
+pub mod line;
 pub mod log;
 pub mod preprocessed;
@@ -1,4 +1,8 @@
-use crate::{preprocessing::log::preprocess_message, types::metadata::Metadata, DbName};
+use crate::{
+    preprocessing::{log::preprocess_message, severity::Severity},
+    types::metadata::{workload_name, Metadata},
+    DbName,
+};
 
 use super::log::LogRecord;
 
@@ -10,9 +14,12 @@ pub struct PreprocessedLogRecord {
     pub preprocessed_message: Vec<String>,
     pub length: u64,
     pub key: String,
+    // classes are shared by the pods of a workload
+    pub workload: String,
     pub namespace: String,
     pub pod_uid: String,
     pub container: String,
+    pub severity: Severity,
 }
 
 impl From<(&String, &String, &Metadata)> for PreprocessedLogRecord {
@@ -32,10 +39,12 @@ impl From<(LogRecord, Vec<String>)> for PreprocessedLogRecord {
             record_id: log.record_id,
             length: preprocessed_message.len() as u64,
             preprocessed_message,
+            workload: workload_name(&log.key),
             key: log.key,
             namespace: log.namespace,
             pod_uid: log.pod_uid,
             container: log.container,
+            severity: log.severity,
         }
     }
 }
@@ -24,6 +24,41 @@ pub async fn post_test_stream(client: &Client, route: &str, test_stream: String)
     response.status()
 }
 
+pub async fn get_test(client: &Client, route: &str) -> Status {
+    let token = get_env_var("AUTH_TOKEN").unwrap();
+    let response = client
+        .get(route)
+        .header(Header::new("Authorization", format!("Bearer {}", token)))
+        .dispatch()
+        .await;
+
+    response.status()
+}
+
+pub async fn put_test(client: &Client, route: &str, json_value: serde_json::Value) -> Status {
+    let token = get_env_var("AUTH_TOKEN").unwrap();
+    let response = client
+        .put(route)
+        .header(ContentType::JSON)
+        .header(Header::new("Authorization", format!("Bearer {}", token)))
+        .json(&json_value)
+        .dispatch()
+        .await;
+
+    response.status()
+}
+
+pub async fn delete_test(client: &Client, route: &str) -> Status {
+    let token = get_env_var("AUTH_TOKEN").unwrap();
+    let response = client
+        .delete(route)
+        .header(Header::new("Authorization", format!("Bearer {}", token)))
+        .dispatch()
+        .await;
+
+    response.status()
+}
+
 pub async fn post_test(client: &Client, route: &str, json_value: serde_json::Value) -> Status {
     let token = get_env_var("AUTH_TOKEN").unwrap();
     let response = client
@@ -37,6 +72,47 @@ pub async fn post_test(client: &Client, route: &str, json_value: serde_json::Val
     response.status()
 }
 
+// The `post_test_response` function posts like `post_test` and returns the body of the response
+// with its status.
+pub async fn post_test_response(
+    client: &Client,
+    route: &str,
+    json_value: serde_json::Value,
+) -> (Status, String) {
+    let token = get_env_var("AUTH_TOKEN").unwrap();
+    let response = client
+        .post(route)
+        .header(ContentType::JSON)
+        .header(Header::new("Authorization", format!("Bearer {}", token)))
+        .json(&json_value)
+        .dispatch()
+        .await;
+
+    let status = response.status();
+    (status, response.into_string().await.unwrap_or_default())
+}
+
+// The `post_test_encoded` function posts a body compressed with the given `Content-Encoding`.
+pub async fn post_test_encoded(
+    client: &Client,
+    route: &str,
+    content_type: ContentType,
+    encoding: &str,
+    body: Vec<u8>,
+) -> Status {
+    let token = get_env_var("AUTH_TOKEN").unwrap();
+    let response = client
+        .post(route)
+        .header(content_type)
+        .header(Header::new("Content-Encoding", encoding.to_string()))
+        .header(Header::new("Authorization", format!("Bearer {}", token)))
+        .body(body)
+        .dispatch()
+        .await;
+
+    response.status()
+}
+
 pub async fn post_test_batch(
     client: &Client,
     route: &str,
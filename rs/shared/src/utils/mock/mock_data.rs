@@ -9,6 +9,8 @@ use crate::constant::{
     CONVERSION_BYTE_TO_MEBIBYTE, FLUVIO_BYTES_SAFTY_MARGIN, OPENAI_EMBEDDING_TOKEN_LIMIT,
     TOPIC_LOG_BYTES_PER_RECORD,
 };
+use crate::preprocessing::severity::Severity;
+use crate::preprocessing::variable::VarKind;
 use crate::types::class::{item::Item, Class};
 use crate::types::metadata::Metadata;
 
@@ -18,6 +20,8 @@ use super::mock_client::{generate_podname, get_test_metadata};
 pub enum TestCase {
     Simple,
     Short,
+    OptionalToken,
+    LeadingVariable,
     DataIntakeLimit,
     DataProcessingLimit,
     OpenAiRateLimit,
@@ -28,6 +32,8 @@ impl Display for TestCase {
         let name = match self {
             TestCase::Simple => "simple",
             TestCase::Short => "short",
+            TestCase::OptionalToken => "optional-token",
+            TestCase::LeadingVariable => "leading-variable",
             TestCase::DataIntakeLimit => "data-intake-limit",
             TestCase::DataProcessingLimit => "data-processing-limit",
             TestCase::OpenAiRateLimit => "openai-ratelimit",
@@ -83,6 +89,11 @@ pub fn class_from_items(items: Vec<Item>, similarity: f64, metadata: &Metadata)
         namespace: metadata.namespace.to_owned(),
         container: metadata.container.to_owned(),
         token_count: 0,
+        first_seen: 0,
+        last_seen: 0,
+        deleted: false,
+        samples: vec![],
+        severity: Severity::default(),
     }
 }
 
@@ -100,7 +111,7 @@ pub fn get_test_data(case: TestCase) -> TestData {
                     Item::Fix("test".to_string()),
                     Item::Fix("log".to_string()),
                     Item::Fix("line".to_string()),
-                    Item::Var,
+                    Item::Var(VarKind::Any),
                 ],
                 0.875,
                 &metadata,
@@ -127,18 +138,70 @@ pub fn get_test_data(case: TestCase) -> TestData {
             let items = vec![
                 Item::Fix("Trace".to_string()),
                 Item::Fix("[".to_string()),
-                Item::Var,
+                Item::Var(VarKind::Any),
                 Item::Fix("]".to_string()),
                 Item::Fix(":".to_string()),
                 Item::Fix("[".to_string()),
-                Item::Var,
+                Item::Var(VarKind::Duration),
                 Item::Fix("]".to_string()),
                 Item::Fix("[".to_string()),
-                Item::Var,
+                Item::Var(VarKind::Duration),
                 Item::Fix("]".to_string()),
                 Item::Fix("END".to_string()),
             ];
-            let expected_class = class_from_items(items, 0.75, &metadata);
+            let expected_class = class_from_items(items, 11.0 / 12.0, &metadata);
+            TestData {
+                raw_messages,
+                expected_class,
+                metadata,
+            }
+        }
+        TestCase::OptionalToken => {
+            let raw_messages = vec![
+                format!(
+                    "{} INFO Connection to primary db established",
+                    get_test_timestamp(1)
+                ),
+                format!(
+                    "{} INFO Connection to db established",
+                    get_test_timestamp(2)
+                ),
+                format!(
+                    "{} INFO Connection to primary db established",
+                    get_test_timestamp(3)
+                ),
+                format!(
+                    "{} INFO Connection to db established",
+                    get_test_timestamp(4)
+                ),
+            ];
+            let items = vec![
+                Item::Fix("INFO".to_string()),
+                Item::Fix("Connection".to_string()),
+                Item::Fix("to".to_string()),
+                Item::Opt("primary".to_string()),
+                Item::Fix("db".to_string()),
+                Item::Fix("established".to_string()),
+            ];
+            let expected_class = class_from_items(items, 5.0 / 6.0, &metadata);
+            TestData {
+                raw_messages,
+                expected_class,
+                metadata,
+            }
+        }
+        TestCase::LeadingVariable => {
+            let raw_messages = (1..=4)
+                .map(|shard| format!("shard{shard} flushed memtable to disk"))
+                .collect();
+            let items = vec![
+                Item::Var(VarKind::Any),
+                Item::Fix("flushed".to_string()),
+                Item::Fix("memtable".to_string()),
+                Item::Fix("to".to_string()),
+                Item::Fix("disk".to_string()),
+            ];
+            let expected_class = class_from_items(items, 4.0 / 5.0, &metadata);
             TestData {
                 raw_messages,
                 expected_class,
@@ -232,5 +295,5 @@ fn generate_repeated_message_random(r: usize) -> String {
 }
 
 fn generate_null_class(metadata: &Metadata) -> Class {
-    class_from_items(vec![Item::Var], 0.0, metadata)
+    class_from_items(vec![Item::Var(VarKind::Any)], 0.0, metadata)
 }
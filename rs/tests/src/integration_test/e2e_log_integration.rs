@@ -7,11 +7,12 @@ mod tests {
 
     use rstest::rstest;
     use shared::connections::greptime::greptime_connection::GreptimeTable;
-    use shared::constant::OPENAI_EMBEDDING_TOKEN_LIMIT;
+    use shared::constant::{GREPTIME_CLASSIFIED_LOG_KIND, OPENAI_EMBEDDING_TOKEN_LIMIT};
     use shared::mock::rocket::get_test_client;
     use shared::qdrant_util::string_filter;
     use shared::setup_tracing;
     use shared::types::class::vectorized::{from_scored_point, VectorizedClass};
+    use shared::types::metadata::workload_name;
 
     use shared::utils::mock::mock_data::{get_test_data, TestCase};
     use shared::utils::mock::{mock_client::post_test_stream, mock_stream::get_multipart_stream};
@@ -64,6 +65,8 @@ mod tests {
         let greptime = GreptimeConnection::new().await?;
         let qdrant = QdrantConnection::new().await.unwrap();
         let table = GreptimeTable::from(&test_data.metadata);
+        let mut classified_table = table.clone();
+        classified_table.kind = GREPTIME_CLASSIFIED_LOG_KIND.to_string();
         let customer_id = get_env_var("CLIENT_ID_LOCAL").unwrap();
         let db = DbName::Log.id(&customer_id);
         qdrant.create_collection(&db).await.unwrap();
@@ -71,14 +74,19 @@ mod tests {
         let start_time = tokio::time::Instant::now();
         let timeout = Duration::from_secs(30);
         let mut rows = Vec::new();
+        let mut classified_rows = Vec::new();
         let mut classes: Vec<VectorizedClass> = Vec::new();
 
         while start_time.elapsed() < timeout {
             // check greptime
             rows = greptime.query(&db, &table, "record_id").await.unwrap();
+            classified_rows = greptime
+                .query(&db, &classified_table, "class_id")
+                .await
+                .unwrap_or_default();
 
             // check qdrant
-            let filter = string_filter("key", &test_data.metadata.pod_name);
+            let filter = string_filter("key", &workload_name(&test_data.metadata.pod_name));
             let points = qdrant
                 .query_points(&db, Some(filter), 1000, true)
                 .await
@@ -90,7 +98,10 @@ mod tests {
                 test_data.expected_class.count,
                 table.format_name()
             );
-            if !rows.is_empty() && classes.len() == test_data.expected_class.count as usize {
+            if !rows.is_empty()
+                && classified_rows.len() == rows.len()
+                && classes.len() == test_data.expected_class.count as usize
+            {
                 // successfully received data
                 RECEIVED_LOGS
                     .lock()
@@ -108,6 +119,7 @@ mod tests {
         }
 
         assert_eq!(rows.len(), test_data.raw_messages.len());
+        assert_eq!(classified_rows.len(), rows.len());
         assert_eq!(classes.len(), test_data.expected_class.count as usize);
         Ok(())
     }